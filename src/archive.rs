@@ -0,0 +1,148 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Archives a backup with `extract_archive = true` can be unpacked from. The format is kept in
+/// log.toml so a restore packs the folder the same way again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// Picks the format from the file extension.
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let lower = filename.to_ascii_lowercase();
+        if lower.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if lower.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else {
+            None
+        }
+    }
+
+    /// The name stored in log.toml, also used as the file extension.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::TarGz => "tar.gz",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "zip" => Some(ArchiveFormat::Zip),
+            "tar" => Some(ArchiveFormat::Tar),
+            "tar.gz" => Some(ArchiveFormat::TarGz),
+            _ => None,
+        }
+    }
+
+    /// `filename` without the archive extension. EX: "dump.tar.gz" => "dump"
+    pub fn strip_extension<'a>(&self, filename: &'a str) -> &'a str {
+        let lower = filename.to_ascii_lowercase();
+        let extensions: &[&str] = match self {
+            ArchiveFormat::Zip => &[".zip"],
+            ArchiveFormat::Tar => &[".tar"],
+            ArchiveFormat::TarGz => &[".tar.gz", ".tgz"],
+        };
+
+        extensions
+            .iter()
+            .find(|ext| lower.ends_with(*ext))
+            .map(|ext| &filename[..filename.len() - ext.len()])
+            .unwrap_or(filename)
+    }
+}
+
+/// Unpacks `archive` into `dest`, which is created. Entries that would land outside `dest`
+/// (absolute paths, `..`) are refused by both the zip and the tar reader.
+pub fn extract(archive: &Path, format: ArchiveFormat, dest: &Path) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(dest)?;
+    let file = File::open(archive)?;
+
+    match format {
+        ArchiveFormat::Zip => ZipArchive::new(file)?.extract(dest)?,
+        ArchiveFormat::Tar => tar::Archive::new(file).unpack(dest)?,
+        ArchiveFormat::TarGz => tar::Archive::new(GzDecoder::new(file)).unpack(dest)?,
+    }
+
+    Ok(())
+}
+
+/// Packs the contents of `dir` into a new archive at `out`.
+pub fn pack(dir: &Path, format: ArchiveFormat, out: &Path) -> Result<(), Box<dyn Error>> {
+    let file = File::create(out)?;
+
+    match format {
+        ArchiveFormat::Zip => {
+            let mut zip = ZipWriter::new(file);
+            let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+            add_to_zip(&mut zip, dir, "", options)?;
+            zip.finish()?;
+        }
+        ArchiveFormat::Tar => {
+            let mut tar = tar::Builder::new(file);
+            tar.append_dir_all(".", dir)?;
+            tar.into_inner()?.sync_all()?;
+        }
+        ArchiveFormat::TarGz => {
+            let mut tar = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+            tar.append_dir_all(".", dir)?;
+            tar.into_inner()?.finish()?.sync_all()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn add_to_zip(
+    zip: &mut ZipWriter<File>,
+    dir: &Path,
+    prefix: &str,
+    options: SimpleFileOptions,
+) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        let path = entry.path();
+
+        if path.is_dir() {
+            zip.add_directory(format!("{}/", name), options)?;
+            add_to_zip(zip, &path, &format!("{}/", name), options)?;
+        } else {
+            zip.start_file(name, options)?;
+            io::copy(&mut File::open(&path)?, zip)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Size of a file, or of everything inside a folder.
+pub fn disk_size(path: &Path) -> u64 {
+    if !path.is_dir() {
+        return path.metadata().map(|m| m.len()).unwrap_or(0);
+    }
+
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| disk_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
@@ -5,12 +5,76 @@ pub const DEFAULT_CONFIG_TOML: &str = r#"
 #                                                                              #
 ################################################################################
 
+# Load another config file first and apply this one on top of it, so dev/staging/prod can
+# share a base file. Tables such as [smtp] are merged key by key; other values, including
+# the whole [[backups]] and [[urls]] lists, replace the base. Relative to this file.
+# Changes made in the app are saved to the file the changed setting comes from.
+#include = "base.toml"
+
+# Any string value can use ${NAME} to insert an environment variable, EX: url = "https://${DB_HOST}/dump",
+# so one config works across deployments and secrets stay out of this file. Variables are also read
+# from a .env file (NAME=value lines) next to this file; the real environment wins. An unset variable
+# stops WSS from starting and names where it is used. Write $${ for a literal ${.
+# command, pre_command and post_command are the exception: they are passed to the shell as
+# written, which expands ${NAME} (%NAME% with cmd on Windows) itself when they run, including
+# the hook variables below.
+
+# Start with the backup schedule enabled. Once the schedule has been toggled in the
+# app, the last choice is remembered in state.toml and used instead.
+backup_enabled_on_start = false
+
+# Minutes after enabling the backup schedule in the app during which scheduled backups are
+# skipped, so a backup that happens to be due right after the click doesn't start while you
+# are still setting things up. 0 lets them run right away.
+backup_enable_grace_minutes = 0
+
+# Folder for everything WSS writes: internal_log.toml, state.toml, stats.toml and, with a
+# relative backup_root, the backup folders. Created if missing. Empty keeps them in the
+# working directory, as before. The --data-dir command line option overrides it.
+# EX: data_dir = "/var/lib/websync-station"
+data_dir = ""
+
+# Directory that holds one folder per backup (named after its id). Relative paths are
+# relative to data_dir. Folders resolving outside this directory are refused.
+backup_root = "."
+
+# Scheduled backups that share a minute start at a random point up to this many seconds
+# into it (max 59), so they don't all hit the backup server at once. 0 turns it off.
+backup_jitter_seconds = 0
+
+# Check all URLs right after launch, and run the backups that missed their schedule while
+# WSS wasn't running (if the backup schedule is enabled). Warnings count against daily_max.
+run_on_start = false
+
+# How many backups may run at the same time. Backups that come due while this many are running
+# wait in a queue, shown in the app as "running" and "queued".
+max_concurrent_backups = 2
+
+# Every this many hours, re-hash the stored backups and compare them with the SHA-256 recorded
+# in each log.toml when they were downloaded. Missing or changed restore points are logged and
+# sent as a backup warning. Backups stored before hashes were recorded are skipped.
+#integrity_check_hours = 24
+
+# Timezone of backup schedules (at, time and cron) and of the times shown in the app, as an
+# IANA name such as "Europe/Oslo". When the clocks change, a daily backup still runs once.
+# Unset, schedules are in UTC and times are shown in the system's timezone.
+#timezone = "Europe/Oslo"
+
+# Where a monthly time = minutes (not at) counts from: 1 is 00:00 on the 1st, 0 is a day earlier,
+# as configs written before this setting did. Left out, it is 0 so those keep their day.
+monthly_day_base = 1
+
+# Format of the internal log. "toml" keeps internal_log.toml as before, "jsonl" appends one JSON
+# object per line to internal_log.jsonl, which log shippers (Loki, ELK, ...) can read directly.
+internal_log_format = "toml"
+
 
 
 ################################################################################
 #                                                                              #
 #  AUTH                                                                        #
 #  The database backup system sets a token in header as Bearer                 #
+#  (downloads only for backups with send_auth = true)                          #
 #                                                                              #
 ################################################################################
 
@@ -25,6 +89,8 @@ secret= "a-string-secret-at-least-256-bits-long"
 jwt_expiry = 600 
 
 # EXAMPLE PAYLOAD, write whatever payload you want.(excluding iat and exp, these are added automatically)
+# Nested tables, arrays and dates are supported. Claims keep the order written here, and any iat/exp
+# keys in the payload are ignored.
 [payload]
 sub = "1234567890" # Example payload
 name = "John Doe" # Example payload
@@ -35,19 +101,176 @@ admin =  true # Example payload
 #                             DATABASE BACKUP                                  #
 #                                                                              #
 #  description: description                                                    #
+#  id: (optional) folder name for this backup, a-z, 0-9, '-' and '_' only.     #
+#        Generated from the description and written here on first run, so the  #
+#        description can be changed freely afterwards.                         #
 #  url: route that returns a single file for backup                            #
-#  restore: route that accepts a single file for restoring a backup            #
-#        Note: POST, multipart/form-data, form field "file" is expected.       #
+#  command: (instead of url) shell command whose output (stdout) is stored     #
+#        as the backup, run with sh -c (cmd /C on Windows). A non-zero exit    #
+#        status fails the backup. Files are named <id>_<timestamp>.dump        #
+#        unless filename_template is set.                                      #
+#        EX: command = "pg_dump -Fc maindb"                                    #
+#  pre_command: (optional) shell command run before the backup, EX: to lock    #
+#        tables. A non-zero exit status aborts the backup and sends a warning. #
+#  post_command: (optional) shell command run after the backup, whether it     #
+#        worked or not (but not when pre_command failed), EX: to unlock again. #
+#        It gets WSS_BACKUP_RESULT (success/failed) and WSS_BACKUP_FILE (the   #
+#        stored file) in its environment, both hooks get WSS_BACKUP_ID. What   #
+#        the hooks print is written to the internal log.                       #
+#  command_timeout_minutes: (optional) command, pre_command and post_command   #
+#        are killed, with everything they started, when they run longer than   #
+#        this and the backup fails. Default 60, 0 lets them run forever.       #
+#  restore: (optional) route that accepts a single file for restoring a backup #
+#        Note: POST, multipart/form-data, the file is sent in form field       #
+#        "file" as application/octet-stream unless changed below.              #
+#        A list is tried in order until one target accepts the file, for       #
+#        failover. EX: restore = ["https://db1.example.com/restore",           #
+#                                 "https://db2.example.com/restore"]           #
+#  restore_field_name: (optional) form field for the file. Default "file".     #
+#  restore_content_type: (optional) MIME type of the file in the form.         #
+#        Default "application/octet-stream". EX: "application/sql"             #
+#  backup_before_restore: (optional) true ticks "Back up the current state     #
+#        first" in the restore confirmation by default. The backup then runs   #
+#        before the restore, which only happens if the backup succeeded, so    #
+#        a wrong restore can be undone. Default false.                         #
+#  username / password: (optional) HTTP basic auth for url and verify_url.     #
+#        Both must be set. Used instead of the bearer token when present.      #
+#  send_auth: (optional) send the token (or JWT) as a Bearer header when       #
+#        downloading from url. Default false, so credentials only go to        #
+#        the backup hosts that need them.                                      #
+#  token_query_param: (optional, with send_auth) send the token (or JWT) as    #
+#        this query parameter of url instead of an Authorization header,       #
+#        for signed or token-in-URL endpoints.                                 #
+#        EX: token_query_param = "access_token"                                #
+#  verify_url: (optional) checked with a GET after a restore. The restore is   #
+#        only logged as successful if it answers with a 2xx status.            #
+#  test_restore_url: (optional) staging route the newest restore point is      #
+#        uploaded to every test_restore_hours, the way restore is, to prove    #
+#        the backups can really be restored. Use a disposable endpoint, never  #
+#        a production one. "Test restore now" runs it right away.              #
+#  test_restore_health_url: (optional) checked with a GET after a test         #
+#        restore, which only passes if it answers with a 2xx status.           #
+#  test_restore_hours: (optional) how often to run the test restore. Off       #
+#        unless both this and test_restore_url are set, as uploading a full    #
+#        backup can be expensive. Failures are sent as backup warnings. The    #
+#        time of the last one is kept in state.toml across restarts.           #
 #  max: number of backups to store before rotation begins.                     #
-#  interval: h/d/w/m/y will schedule hourly/daily/weekly/monthly/yeary updates #
+#  max_age_days: (optional) also remove backups older than this many days.     #
+#        When both max and max_age_days are set, both rules apply.             #
+#  retention: (optional) "count" (default) uses max and max_age_days.          #
+#        "gfs" keeps the newest backup of each of the last N hours, days,      #
+#        weeks and months instead. Set the tier sizes in a sub table:          #
+#        [backups.gfs]                                                         #
+#        hourly = 24                                                           #
+#        daily = 7                                                             #
+#        weekly = 4                                                            #
+#        monthly = 12                                                          #
+#  max_total_bytes: (optional) remove the oldest backups until the folder      #
+#        uses at most this many bytes. The newest backup is always kept.       #
+#        Works with both retention modes. The strictest rule wins.             #
+#  interval: h/d/w/m will schedule hourly/daily/weekly/monthly backups         #
+#           or custom for every period_minutes, from at each day               #
 #           Ex: interval = "d"                                                 #
-#  time: minute of backup (UTC) EX: 725 => five past noon (12 * 60 + 5)        #
-#        Note: If interval is set to "h", the backups happens at mod(60) etc.  #
-#              EX: time = 185 --> will backup at xx.05 if interval is hourly   #
-#              EX: time = 6485 (24*60*4 + 12*60 + 5) will backup fri. 12:05,   #
-#                  and at 12:05 if interval is set to "d".                     #
-#        Note: To keep things simple just set time to 0, or 5, which works     #
-#              fine for all intervals.                                         #
+#  at: when to back up within the interval, in timezone (top of this file):    #
+#        h: at = ":05"        five past every hour                             #
+#        d: at = "02:30"      every day at 02:30                               #
+#        w: at = "Fri 12:05"  every Friday at 12:05                            #
+#        m: at = "15 02:30"   the 15th of every month at 02:30                 #
+#        custom: at = "07:00" first run of the day, then every period          #
+#  time: (instead of at) minute of backup within the interval.                 #
+#        EX: 725 => 12:05 daily. Maximum per interval: h < 60, d < 1440,       #
+#        w < 10080 (from Monday 00:00), m < 44640 (from the 1st),              #
+#        custom < 1440.                                                        #
+#        Monthly times count from 00:00 on the 1st, see monthly_day_base.      #
+#        Larger values are reduced to fit and a warning is logged at start.    #
+#        When both are set, at wins. Without either, time is 0.                #
+#  period_minutes: (interval = "custom" only) minutes between backups,         #
+#        1 to 1440. Runs at at/time, then every period until midnight, and     #
+#        starts over at at/time the next day.                                  #
+#        EX: interval = "custom", period_minutes = 360, at = "07:00"           #
+#            => 07:00, 13:00 and 19:00 every day                               #
+#  weekdays: (optional, interval = "w" only) run on each of these days at      #
+#        the same time of day, so at/time are a time of day (at = "02:30").    #
+#        EX: interval = "w", weekdays = ["Mon", "Wed", "Fri"], at = "02:30"    #
+#                                                                              #
+#  filename_template: (optional) name for stored files. Placeholders:          #
+#        {description}, {timestamp} (2024-06-01T0200), {date} (2024-06-01),    #
+#        {filename} (name sent by the server) and {ext} (its extension).       #
+#        EX: filename_template = "maindb_{timestamp}.{ext}"                    #
+#        The result is sanitized the same way server filenames are.            #
+#                                                                              #
+#  raw: (optional) true stores the response exactly as sent. By default a      #
+#        gzip/deflate/brotli Content-Encoding is decoded before saving, so the #
+#        file on disk is the plain dump. A file that is itself a .gz (without  #
+#        Content-Encoding) is always stored as-is.                             #
+#                                                                              #
+#  accept_statuses: (optional) status codes accepted for the download on       #
+#        top of 2xx (which, 206 included, is always accepted). EX: [520]       #
+#                                                                              #
+#  connect_timeout_secs: (optional) seconds allowed to connect to the url.     #
+#        Default 30.                                                           #
+#  stall_timeout_secs: (optional) abort the download when no data arrives      #
+#        for this many seconds. Default 300. There is no cap on the total      #
+#        time, so a large dump that keeps streaming is never cut off.          #
+#                                                                              #
+#  checksum_url: (optional) url that serves the SHA-256 of the file the        #
+#        backup url sends, as a bare hash or sha256sum output. It is fetched   #
+#        with the same auth after the download. A different hash fails the     #
+#        backup, which is then not stored and sends a warning.                 #
+#  verify_etag: (optional) true compares the download with its ETag, for       #
+#        servers whose ETag is the SHA-256 of the file. Use raw = true if      #
+#        the server also compresses the response. The verified checksum is     #
+#        recorded as server_checksum in log.toml.                              #
+#                                                                              #
+#  latest_copy: (optional) true keeps latest.<ext> in the backup folder        #
+#        pointing at the newest backup (a symlink, or a copy where symlinks    #
+#        aren't available). It is not counted by max or max_total_bytes.       #
+#                                                                              #
+#  write_sidecar: (optional) true writes <file>.meta.json next to each         #
+#        backup with its source url (or command), timestamp, size, SHA-256,    #
+#        server checksum and download time, for other tools and audits. It     #
+#        is not counted by max or max_total_bytes and is deleted together      #
+#        with its backup.                                                      #
+#                                                                              #
+#  extract_archive: (optional) true unpacks a downloaded .zip, .tar or         #
+#        .tar.gz into a folder named <name>_<timestamp>, kept as one restore   #
+#        point with the size of everything in it. Restore packs the folder     #
+#        into the same kind of archive again before uploading it.              #
+#                                                                              #
+#  partition_by: (optional) "day" or "month" stores each backup in a date      #
+#        subfolder, EX: <id>/2024/06/18/ or <id>/2024/06/, in the configured   #
+#        timezone. log.toml stays in <id>/ and lists the files with their      #
+#        subfolder. Rotation removes date folders once they are empty.         #
+#        Default "none" (every file directly in <id>/).                        #
+#                                                                              #
+#  jitter_seconds: (optional) overrides backup_jitter_seconds (top of this     #
+#        file) for this backup.                                                #
+#  digest: (optional) true reports this backup's results and problems in the   #
+#        daily digest (digest_at in [warning_settings]) instead of warning     #
+#        right away. Default false.                                            #
+#                                                                              #
+#  max_staleness_hours: (optional) warns once when the newest restore point    #
+#        is older than this, EX: 36 for a daily backup. This also catches      #
+#        backups that stopped running without an error. Before the first       #
+#        backup the time counts from when WSS started. The UI marks such a     #
+#        backup STALE.                                                         #
+#                                                                              #
+#  max_shrink_percent: (optional) warn when a new backup is more than this     #
+#        many percent smaller than the previous one, EX: 50. A dump that       #
+#        drops from 500 MB to 2 KB is usually broken.                          #
+#  keep_on_shrink: (optional) true skips rotation after such a shrink, so      #
+#        older, good backups are not removed to make room. Default false.      #
+#  min_free_bytes: (optional) before each backup, warn once when the disk      #
+#        of the backup folder has less free space than this, EX: 5000000000    #
+#        for 5 GB. The free space is shown next to each backup in the app.     #
+#  skip_on_low_space: (optional) true also skips the backup while there is     #
+#        less than min_free_bytes free, instead of risking a full disk.        #
+#                                                                              #
+#  cron: (optional) cron expression that replaces interval and time.           #
+#        Both "min hour dom month dow" and the 6 field form with seconds       #
+#        are accepted. Use names for weekdays to avoid numbering mistakes.     #
+#        EX: cron = "30 2 * * Mon-Fri"  --> every weekday at 02:30             #
+#        EX: cron = "0 3 1,15 * *"      --> 03:00 on the 1st and 15th          #
 #                                                                              #
 ################################################################################
 
@@ -67,7 +290,8 @@ admin =  true # Example payload
 #restore = "http://your-second-restore-url.com/restore" # URL to restore backup
 #max = 10
 #interval = "w"
-#time = 0
+#at = "Sun 03:00"
+#max_staleness_hours = 192 # warn if no backup succeeded for 8 days
 
 
 
@@ -78,6 +302,43 @@ admin =  true # Example payload
 #                                                                              #
 #  interval_minutes = minutes between uptime checking                          #
 #  downtime_tolerance = number of failed uptime checks allowed before warning. #
+#  downtime_tolerance_minutes = (optional) minutes a URL must stay down        #
+#        before warning. Replaces downtime_tolerance and does not depend on    #
+#        interval_minutes.                                                     #
+#  connectivity_check_urls = (optional) probed before the URLs are checked.    #
+#        If none of them answer, the host is treated as offline: per-URL and   #
+#        backup alerts are paused and a single "host was offline from X to Y"  #
+#        warning is sent once it is back. Default is ["https://1.1.1.1/",      #
+#        "https://8.8.8.8/"], set to [] to turn the check off.                 #
+#  Each [[urls]] entry can set its own interval_minutes to be checked more or  #
+#  less often than the rest.                                                   #
+#  fail_on_redirect = (optional) true treats a 3xx answer as down instead      #
+#        of following it, so a site that suddenly redirects to a parking or    #
+#        login page is flagged. Default false (redirects are followed as set   #
+#        by follow_redirects in [http]).                                       #
+#  stats_days = (optional) days of history behind the uptime percentage        #
+#        shown next to each URL (stored in stats.toml). Default 30.            #
+#  cert_expiry_warn_days = (optional) warn when the TLS certificate of an      #
+#        https URL expires within this many days, even though the site is      #
+#        up. One warning per certificate. Default 14, set to 0 to turn off.    #
+#  uptime_concurrency = (optional) how many URLs are checked at the same       #
+#        time. The others wait until a check finishes, so a long list doesn't  #
+#        open hundreds of connections at once. Default 8.                      #
+#  [[urls]] entries can also set username and password (both) to use HTTP      #
+#  basic auth for their check, and token_query_param to add the token (or      #
+#  JWT) as that query parameter, EX: token_query_param = "key".                #
+#  A url that isn't an absolute http(s) URL is reported when the config is     #
+#  loaded, shown gray (hover it for the reason) and never checked, so a typo   #
+#  doesn't look like an outage.                                                #
+#  expect_final_url = (optional) where the check has to end up after           #
+#        redirects, `*` matches any text. Ending up anywhere else, EX: on a    #
+#        login or error page, counts as down even with a 200.                  #
+#        EX: expect_final_url = "https://www.example.com/" for a URL that      #
+#        redirects from http:// and the apex domain. Needs redirects to be     #
+#        followed (fail_on_redirect = false).                                  #
+#  digest = (optional) true reports this URL going down and coming back in     #
+#        the daily digest (digest_at in [warning_settings]) instead of         #
+#        warning right away, for lower-priority monitors. Default false.       #
 #                                                                              #
 #    Note: With a high interval I recommend using a low downtime_tolerance     #
 #          Recomended values are interval of 10 and tolerance of 1             #
@@ -88,6 +349,12 @@ admin =  true # Example payload
 [url_uptime_settings]
 interval_minutes = 60 # time between checks in minutes
 downtime_tolerance = 1 # number of failed checks before warning
+#downtime_tolerance_minutes = 15 # warn when a URL has been down this long instead
+#connectivity_check_urls = ["https://1.1.1.1/", "https://8.8.8.8/"] # [] disables the offline check
+#fail_on_redirect = true # a 3xx answer counts as down
+#stats_days = 30 # days of history behind the uptime percentage
+#cert_expiry_warn_days = 14 # 0 disables the certificate expiry warning
+#uptime_concurrency = 8 # URLs checked at the same time
 
 
 # These URLS should be websites or anything that accepts a GET request and returns
@@ -99,7 +366,9 @@ downtime_tolerance = 1 # number of failed checks before warning
 
 #[[urls]]
 #description = "GitHub"
-#url = "https://github.com
+#url = "https://github.com/"
+#expect_final_url = "https://github.com/*" # optional, counts as down when redirected elsewhere
+#interval_minutes = 5 # optional, overrides interval_minutes above for this URL
 
 
 
@@ -114,14 +383,54 @@ downtime_tolerance = 1 # number of failed checks before warning
 #  request will be a JSON object with:                                         #
 #  {                                                                           #
 #   "time": String // UTC timestamp,                                           #
+#   "instance": String // instance_name from [http], or the hostname,          #
+#   "hostname": String // the machine's hostname,                              #
+#   "run_id": String // random ID, new every time WSS starts,                  #
 #   "description": tring // description of the error,                          #
-#   "logs": String[] // Last 50 lines of the log                               #
+#   "logs": String[] // Last warning_log_lines lines of the log (default 50)   #
 #  }                                                                           #
 #                                                                              #
 #                                                                              #
 #                                                                              #
 #  If `use_email` is true, it will send an email using the SMTP settings.      #
 #                                                                              #
+#  If `desktop_notifications` is true, warnings also show up as a desktop      #
+#  notification on the machine WSS runs on (channel name "desktop"). Snooze,   #
+#  maintenance windows and daily_max apply to it like to the other channels.   #
+#  This needs a desktop session, a headless service can't show them.           #
+#                                                                              #
+#  uptime_channels / backup_channels (optional) pick the channels per kind of  #
+#  warning, EX: uptime_channels = ["post"] and backup_channels = ["email"].    #
+#  Uptime covers URLs down/back up and the host going offline. A kind          #
+#  without a list uses use_email, send_post_request and desktop_notifications. #
+#                                                                              #
+#  daily_max caps the warnings sent per channel per day, so a noisy webhook    #
+#  can't use up the emails. channel_daily_max sets a different cap for single  #
+#  channels, EX: channel_daily_max = { email = 4, post = 50 }.                 #
+#                                                                              #
+#  warning_cooldown_minutes (optional) is the minimum time between two         #
+#  warnings of the same kind (uptime or backup), so a flapping service         #
+#  doesn't send a burst before daily_max kicks in. Warnings held back by       #
+#  the cooldown are still written to the internal log. Default 0 (off).        #
+#                                                                              #
+#  escalation (optional) adds channels for URLs that stay down. Each step      #
+#  fires once per outage, when a URL has failed after_failed_checks checks in  #
+#  a row or has been down for after_minutes, and sends an ESCALATED warning    #
+#  through its channels. Everything resets when the URL is back up. EX:        #
+#    [[warning_settings.escalation]]                                           #
+#    after_failed_checks = 3                                                   #
+#    channels = ["post"]                                                       #
+#    [[warning_settings.escalation]]                                           #
+#    after_minutes = 120                                                       #
+#    channels = ["email", "post"]                                              #
+#                                                                              #
+#  digest_at (optional, "HH:MM" in timezone) emails one summary a day to       #
+#  email instead of warning right away, for URLs and backups that set          #
+#  digest = true. It lists what happened since the last one: URLs going down   #
+#  and coming back, certificates expiring, backups succeeding and failing.     #
+#  Nothing is sent when there is nothing to report. Events are kept in         #
+#  state.toml until the digest went out. Other entries warn as usual.          #
+#                                                                              #
 #  NOTE: For Gmail and similar providers, you must use an app-specific         #
 #        password, not your regular account password. For Gmail go to:         #
 #        https://myaccount.google.com/apppasswords                             #
@@ -131,9 +440,20 @@ downtime_tolerance = 1 # number of failed checks before warning
 [warning_settings]
 use_email = false # Set to true to enable email warnings
 send_post_request = false # Set to true to enable POST warnings
+desktop_notifications = false # Set to true to show warnings as desktop notifications
 post_request_routes = ["https://your-site.com/mycentrallog"] # Array of URLs to send POST requests to
 email = "myemailaccount@domain.com" # Email address to send warnings to
-daily_max = 4 # Max number of emails to send per day. Set to 0 to disable.
+daily_max = 4 # Max number of warnings per channel (email, POST, desktop) per day. Set to 0 to disable.
+#channel_daily_max = { post = 50 } # optional, per channel caps that replace daily_max
+#warning_cooldown_minutes = 15 # optional, minimum time between two uptime (or two backup) warnings
+warning_log_lines = 50 # Number of internal log lines included in warnings.
+#uptime_channels = ["post"] # optional, replaces use_email/send_post_request/desktop_notifications for uptime warnings
+#backup_channels = ["email"] # optional, the same for backup warnings
+#digest_at = "08:00" # optional, daily summary email for entries with digest = true
+
+#[[warning_settings.escalation]] # optional, see above
+#after_failed_checks = 3
+#channels = ["post"]
 
 [smtp]
 server = "smtp.gmail.com"
@@ -141,5 +461,99 @@ port = 587
 username = "myemailaccount@domain.com"
 password = "some pass word here"
 from = "myemailaccount@domain.com"
+tls_mode = "starttls" # "starttls" (port 587), "implicit" (SMTPS, port 465) or "none" (unencrypted)
+
+
+
+################################################################################
+#                                                                              #
+#                                     HTTP                                     #
+#                                                                              #
+#  Applied to every request WSS makes (uptime checks, backups, restores and    #
+#  warning POSTs).                                                             #
+#                                                                              #
+#  user_agent: User-Agent header. Default is WebSyncStation/<version>.         #
+#  instance_name: (optional) sent as the X-WebSync-Instance header so servers  #
+#        can tell several WSS installations apart in their logs. Warnings      #
+#        carry it in the email subject and POST payload, the hostname is       #
+#        used when it is empty. Non-ASCII characters are percent-encoded in    #
+#        the header (UTF-8 bytes as %XX), warnings show the name as written.   #
+#  follow_redirects: true (default, up to 10), false, or the maximum number    #
+#        of redirects to follow. Applies to every request. With false, a       #
+#        backup download that gets a redirect (e.g. to a login page) fails.    #
+#  ca_cert_path: (optional) PEM file with certificates to trust on top of the  #
+#        system ones, for servers with a certificate from an internal CA.      #
+#        The file may hold several certificates. EX: "certs/internal-ca.pem"   #
+#  danger_accept_invalid_certs: (optional) true turns off TLS certificate      #
+#        checks for every request. Anyone on the network can then read or      #
+#        change backups and tokens, so only use it in a lab. Default false.    #
+#                                                                              #
+################################################################################
+
+[http]
+#user_agent = "WebSyncStation/0.1.0"
+instance_name = "" # e.g. "office-server"
+#follow_redirects = 5 # true (default), false or a maximum number of redirects
+#ca_cert_path = "certs/internal-ca.pem"
+#danger_accept_invalid_certs = false
+
+
+
+################################################################################
+#                                                                              #
+#                             MAINTENANCE WINDOWS                              #
+#                                                                              #
+#  While a window is active, failures are still logged but no warnings         #
+#  (email or POST) are sent, and the app shows "Maintenance mode active".      #
+#                                                                              #
+#  One-off window, RFC 3339 timestamps:                                        #
+#        start = "2024-06-01T02:00:00Z"                                        #
+#        end = "2024-06-01T04:00:00Z"                                          #
+#  Recurring window, HH:MM in UTC (may run past midnight):                     #
+#        weekdays = ["Sun"] # optional, every day when left out                #
+#        from = "02:00"                                                        #
+#        to = "04:00"                                                          #
+#                                                                              #
+################################################################################
+
+#[[maintenance_windows]]
+#description = "Weekly database upgrade"
+#weekdays = ["Sun"]
+#from = "02:00"
+#to = "04:00"
+
+
+
+################################################################################
+#                                                                              #
+#                               HEALTH ENDPOINT                                #
+#                                                                              #
+#  With a port set, WSS answers GET /healthz with a small JSON body (uptime,   #
+#  last minute tick handled, number of monitored URLs) so a watchdog,          #
+#  container orchestrator or load balancer can tell it is alive. The status    #
+#  is 200, or 503 when no tick has been handled for stale_after_seconds        #
+#  (default 180).                                                              #
+#                                                                              #
+#  address: interface to listen on. Default "127.0.0.1" (this machine only),   #
+#        use "0.0.0.0" to reach it from other machines or outside a container. #
+#  status: (optional) true also answers GET /status with JSON for dashboards:  #
+#        every URL (up or down, down since, certificate expiry), every backup  #
+#        (restore points, last success, stale, running or queued) and the      #
+#        last warning_log_lines internal log entries. Refreshed every minute   #
+#        and when a backup starts or finishes. Default false.                  #
+#  heartbeat_url: (optional) GET this URL every heartbeat_minutes while WSS    #
+#        runs, for a dead man's switch such as healthchecks.io that alerts you #
+#        when the pings stop because WSS crashed or hung. Needs no port.       #
+#        EX: heartbeat_url = "https://hc-ping.com/<your-uuid>"                 #
+#  heartbeat_minutes: (optional) minutes between pings. Default 1.             #
+#                                                                              #
+################################################################################
 
+[health]
+#port = 8089
+#address = "127.0.0.1"
+#stale_after_seconds = 180
+#status = false
+#heartbeat_url = "https://hc-ping.com/<your-uuid>"
+#heartbeat_minutes = 1
 "#; // End of the default config
\ No newline at end of file
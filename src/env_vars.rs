@@ -0,0 +1,86 @@
+use std::env;
+use std::path::Path;
+use toml::{Table, Value};
+
+/// Loads `KEY=value` lines from `path` into the environment. Variables that are already set
+/// win, so the real environment can override the file. A missing file is not an error.
+pub fn load_dotenv(path: &Path) -> Result<(), String> {
+    match dotenvy::from_path(path) {
+        Ok(()) => Ok(()),
+        Err(dotenvy::Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("{} could not be loaded: {}", path.display(), e)),
+    }
+}
+
+/// Replaces `${NAME}` with the environment variable `NAME`. `$${` stays a literal `${`.
+/// Returns the names of the variables that are not set.
+pub fn expand(text: &str) -> Result<String, Vec<String>> {
+    let mut expanded = String::with_capacity(text.len());
+    let mut missing = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start..];
+
+        if let Some(escaped) = after.strip_prefix("$${") {
+            expanded.push_str("${");
+            rest = escaped;
+        } else if let Some((name, tail)) = after.strip_prefix("${").and_then(|inner| inner.split_once('}')) {
+            match env::var(name) {
+                Ok(value) => expanded.push_str(&value),
+                Err(_) => missing.push(name.to_string()),
+            }
+            rest = tail;
+        } else {
+            expanded.push('$');
+            rest = &after[1..];
+        }
+    }
+    expanded.push_str(rest);
+
+    if missing.is_empty() {
+        Ok(expanded)
+    } else {
+        Err(missing)
+    }
+}
+
+/// Shell commands are left as written, the shell expands their variables when they run. That
+/// way hooks can use the ones only set for them, like ${WSS_BACKUP_FILE}.
+const SHELL_COMMAND_KEYS: [&str; 3] = ["command", "pre_command", "post_command"];
+
+/// Expands every string in the table, however deeply nested, except shell commands. Returns
+/// each unresolved variable together with where it is used, EX: "${DB_HOST} in backups[0].url".
+pub fn expand_table(table: &mut Table) -> Result<(), Vec<String>> {
+    let mut missing = Vec::new();
+    for (key, value) in table.iter_mut().filter(|(key, _)| !SHELL_COMMAND_KEYS.contains(&key.as_str())) {
+        expand_value(value, key, &mut missing);
+    }
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(missing)
+    }
+}
+
+fn expand_value(value: &mut Value, path: &str, missing: &mut Vec<String>) {
+    match value {
+        Value::String(text) => match expand(text) {
+            Ok(expanded) => *text = expanded,
+            Err(names) => missing.extend(names.into_iter().map(|name| format!("${{{}}} in {}", name, path))),
+        },
+        Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                expand_value(item, &format!("{}[{}]", path, i), missing);
+            }
+        }
+        Value::Table(table) => {
+            for (key, item) in table.iter_mut().filter(|(key, _)| !SHELL_COMMAND_KEYS.contains(&key.as_str())) {
+                expand_value(item, &format!("{}.{}", path, key), missing);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// `[health]` in config.toml. No server is started unless a port is set.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct HealthSettings {
+    pub port: Option<u16>,
+    pub address: String, // interface to listen on, "0.0.0.0" to reach it from outside a container
+    pub stale_after_seconds: u64, // /healthz answers 503 when no minute tick was handled for this long
+    pub status: bool, // also answer GET /status with URL and backup states and recent log lines
+    pub heartbeat_url: Option<String>, // GET this every heartbeat_minutes, for a dead man's switch
+    pub heartbeat_minutes: u32,
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            port: None,
+            address: "127.0.0.1".to_string(),
+            stale_after_seconds: 180,
+            status: false,
+            heartbeat_url: None,
+            heartbeat_minutes: 1,
+        }
+    }
+}
+
+/// What `GET /healthz` reports. The app records every minute tick it handles, so a stuck
+/// main loop shows up as a stale tick even though the server thread still answers.
+pub struct Health {
+    started: DateTime<Utc>,
+    last_tick: Mutex<Option<DateTime<Utc>>>,
+    monitored_urls: AtomicUsize,
+    status: Mutex<JsonValue>, // snapshot for GET /status, published by the main loop
+}
+
+impl Health {
+    pub fn new(monitored_urls: usize) -> Self {
+        Self {
+            started: Utc::now(),
+            last_tick: Mutex::new(None),
+            monitored_urls: AtomicUsize::new(monitored_urls),
+            status: Mutex::new(json!({})),
+        }
+    }
+
+    /// Replaces what `GET /status` answers with.
+    pub fn publish_status(&self, status: JsonValue) {
+        *self.status.lock().unwrap_or_else(|e| e.into_inner()) = status;
+    }
+
+    /// Updates the count after URLs were added while running.
+    pub fn set_monitored_urls(&self, monitored_urls: usize) {
+        self.monitored_urls.store(monitored_urls, Ordering::Relaxed);
+    }
+
+    pub fn record_tick(&self, tick: DateTime<Utc>) {
+        *self.last_tick.lock().unwrap_or_else(|e| e.into_inner()) = Some(tick);
+    }
+
+    /** status code and JSON body */
+    fn report(&self, stale_after_seconds: u64) -> (u16, String) {
+        let now = Utc::now();
+        let last_tick = *self.last_tick.lock().unwrap_or_else(|e| e.into_inner());
+
+        // before the first tick count from the start, so a fresh instance isn't reported as hung
+        let quiet_seconds = (now - last_tick.unwrap_or(self.started)).num_seconds();
+        let healthy = quiet_seconds <= stale_after_seconds as i64;
+
+        let body = json!({
+            "status": if healthy { "ok" } else { "stale" },
+            "uptime_seconds": (now - self.started).num_seconds(),
+            "last_tick": last_tick.map(|tick| tick.to_rfc3339()),
+            "monitored_urls": self.monitored_urls.load(Ordering::Relaxed),
+        });
+
+        (if healthy { 200 } else { 503 }, body.to_string())
+    }
+}
+
+/// Starts the health endpoint (and /status when enabled) on its own thread when a port is
+/// configured.
+pub fn serve(settings: &HealthSettings, health: Arc<Health>) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(port) = settings.port else {
+        return Ok(());
+    };
+
+    let listener = TcpListener::bind((settings.address.as_str(), port))?;
+    info!("Health endpoint on http://{}:{}/healthz", settings.address, port);
+
+    if settings.status {
+        info!("Status endpoint on http://{}:{}/status", settings.address, port);
+    }
+
+    let settings = settings.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = answer(stream, &health, &settings) {
+                        debug!("Health request failed: {}", e);
+                    }
+                }
+                Err(e) => warn!("Health endpoint could not accept a connection: {}", e),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn answer(mut stream: TcpStream, health: &Health, settings: &HealthSettings) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("").split('?').next().unwrap_or("");
+
+    let (status, body) = match (method, path) {
+        ("GET", "/healthz") => health.report(settings.stale_after_seconds),
+        ("GET", "/status") if settings.status => {
+            let status = health.status.lock().unwrap_or_else(|e| e.into_inner());
+            (200, status.to_string())
+        }
+        ("GET", _) => (404, json!({ "error": "not found" }).to_string()),
+        _ => (405, json!({ "error": "only GET is supported" }).to_string()),
+    };
+
+    let reason = match status {
+        200 => "OK",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Service Unavailable",
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
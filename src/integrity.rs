@@ -0,0 +1,84 @@
+use crate::LogEntry;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+/// What re-hashing the restore points of one backup found.
+pub struct Report {
+    pub id: String,
+    pub checked: usize,  // restore points with a recorded hash
+    pub unhashed: usize, // logged before hashes were stored, nothing to compare with
+    pub problems: Vec<(String, String)>, // (filename, what is wrong)
+}
+
+/// SHA-256 of a stored backup as lowercase hex. An unpacked archive folder is hashed over its
+/// files in name order, each together with its path inside the folder, so a renamed or moved
+/// file also changes the hash.
+pub fn hash_path(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+
+    if path.is_dir() {
+        hash_dir(&mut hasher, path, "")?;
+    } else {
+        io::copy(&mut File::open(path)?, &mut hasher)?;
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn hash_dir(hasher: &mut Sha256, dir: &Path, prefix: &str) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        let path = entry.path();
+
+        if path.is_dir() {
+            hash_dir(hasher, &path, &format!("{}/", name))?;
+        } else {
+            hasher.update(name.as_bytes());
+            hasher.update(path.metadata()?.len().to_le_bytes());
+            io::copy(&mut File::open(&path)?, hasher)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-hashes every restore point in `entries` that has a recorded hash. Meant for a worker
+/// thread, large backups take a while to read.
+pub fn verify(id: String, folder: &Path, entries: &[LogEntry]) -> Report {
+    let mut report = Report {
+        id,
+        checked: 0,
+        unhashed: 0,
+        problems: Vec::new(),
+    };
+
+    for entry in entries {
+        let Some(expected) = &entry.sha256 else {
+            report.unhashed += 1;
+            continue;
+        };
+        report.checked += 1;
+
+        let path = folder.join(&entry.filename);
+        if !path.exists() {
+            report.problems.push((entry.filename.clone(), "the file is missing".to_string()));
+            continue;
+        }
+
+        match hash_path(&path) {
+            Ok(actual) if actual == *expected => {}
+            Ok(actual) => report.problems.push((
+                entry.filename.clone(),
+                format!("the content changed (expected SHA-256 {}, found {})", expected, actual),
+            )),
+            Err(e) => report.problems.push((entry.filename.clone(), format!("it could not be read: {}", e))),
+        }
+    }
+
+    report
+}
@@ -3,8 +3,10 @@
 use chrono::prelude::*; // Brings DateTime, Utc, etc. into scope
 use chrono::Timelike; // Brings `.minute()`, `.hour()`, `.second()` into scope
 use chrono::Utc;
+use chrono_tz::Tz;
+use cron::Schedule;
 use eframe::egui::{
-    self, Align, Color32, Frame, Label, Layout, RichText, Rounding, ScrollArea, Stroke, Vec2,
+    self, Align, Button, Color32, Frame, Label, Layout, RichText, Rounding, ScrollArea, Stroke, Vec2,
     ViewportBuilder,
 };
 use jsonwebtoken::{encode, EncodingKey, Header};
@@ -14,45 +16,191 @@ use lettre::{
     Message, SmtpTransport, Transport,
     transport::smtp::client::{Tls, TlsParameters},
 };
-use reqwest::blocking::Client;
-use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use reqwest::blocking::{Client, ClientBuilder, RequestBuilder, Response};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE, ETAG, LOCATION, RANGE, RETRY_AFTER};
+use reqwest::tls::TlsInfo;
+use reqwest::Certificate;
+use reqwest::StatusCode;
+use reqwest::redirect::Policy;
 use reqwest::blocking::multipart;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Map as JsonMap, Value as JsonValue};
 use std::collections::HashMap;
 use std::error::Error;
-use std::fs::{create_dir_all, read_to_string, remove_file, write, File};
-use std::io::copy;
-use std::path::{Path, PathBuf};
+use std::fs::{create_dir_all, read_dir, read_to_string, remove_dir_all, remove_file, rename, write, File, OpenOptions};
+use std::io::{copy, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::path::{Component, Path, PathBuf};
 use std::str;
-use std::sync::mpsc::Receiver;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::time::{SystemTime, UNIX_EPOCH};
-use toml::Value as TomlValue;
+use toml::{Table as TomlTable, Value as TomlValue};
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::EnvFilter;
 use url::Url;
+use uuid::Uuid;
 
+use archive::ArchiveFormat;
+use health::{Health, HealthSettings};
+use maintenance::MaintenanceWindow;
+use stats::UptimeStats;
+
+mod archive;
 mod default_config;
+mod env_vars;
+mod health;
+mod integrity;
+mod maintenance;
+mod retention;
+mod service;
+mod stats;
+mod url_import;
 
 #[derive(Default, Deserialize)]
 struct UrlEntry {
     description: String,
     url: String,
+    #[serde(default)]
+    interval_minutes: Option<u32>, // overrides url_uptime_settings.interval_minutes
+    #[serde(default)]
+    username: Option<String>, // HTTP basic auth, together with password
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    token_query_param: Option<String>, // send the token as this query parameter on checks, e.g. "access_token"
+    #[serde(default)]
+    expect_final_url: Option<String>, // where redirects must end up, `*` matches anything. Elsewhere counts as down
+    #[serde(default)]
+    digest: bool, // report outages in the daily digest instead of alerting right away
     #[serde(skip)]
     is_ok: bool,
+    #[serde(skip)]
+    invalid: Option<String>, // why `url` can't be used, found at load. Such URLs are never checked
+    #[serde(skip)]
+    down_since: Option<DateTime<Utc>>, // start of the current run of failed checks
+    #[serde(skip)]
+    alerted_down: bool, // a warning went out for this URL, send a recovery notice when it is back
+    #[serde(skip)]
+    cert_expires: Option<DateTime<Utc>>, // notAfter of the TLS certificate seen on the last check
+    #[serde(skip)]
+    alerted_cert_expiry: bool, // warned about the current certificate, reset once it is renewed
+    #[serde(skip)]
+    outage_started: Option<DateTime<Utc>>, // like down_since, but not restarted when a warning goes out
+    #[serde(skip)]
+    failed_checks: u32, // consecutive failed checks in the current outage
+    #[serde(skip)]
+    escalation_level: usize, // escalation steps already sent for the current outage
+    #[serde(skip)]
+    muted: bool, // still checked and shown, but never part of a warning. Kept in state.toml
+}
+
+impl UrlEntry {
+    /** whether problems with this URL go out as warnings right away */
+    fn alerts(&self) -> bool {
+        !self.muted && !self.digest
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 struct LogEntry {
     filename: String,
     timestamp: String,
-    size: u32,
+    size: u64,
+    #[serde(default)]
+    duration_ms: Option<u64>, // how long the download took, missing in older logs
+    #[serde(default)]
+    archive: Option<String>, // "zip", "tar" or "tar.gz" when this is an unpacked folder
+    #[serde(default)]
+    sha256: Option<String>, // hash of the stored file or folder, missing in older logs
+    #[serde(default)]
+    server_checksum: Option<String>, // SHA-256 published by the server (checksum_url/ETag) that the download matched
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 struct InternalLogEntry {
     message: String,
     timestamp: String,
+    #[serde(default)] // entries written before levels existed are Info
+    level: LogLevel,
+    #[serde(default = "default_count")]
+    count: u32, // consecutive identical messages are kept as one entry
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+impl InternalLogEntry {
+    fn new(level: LogLevel, message: String) -> Self {
+        Self {
+            message,
+            timestamp: Utc::now().to_rfc3339(),
+            level,
+            count: 1,
+        }
+    }
+
+    /** the message with the repeat count, EX: "Google is down (x37)" */
+    fn text(&self) -> String {
+        if self.count > 1 {
+            format!("{} (x{})", self.message, self.count)
+        } else {
+            self.message.clone()
+        }
+    }
+}
+
+/** adds an entry, or bumps the count and timestamp of the last one if it says the same thing */
+fn push_internal_log(log: &mut Vec<InternalLogEntry>, entry: InternalLogEntry) {
+    if let Some(last) = log.last_mut() {
+        if last.message == entry.message && last.level == entry.level {
+            last.count += entry.count;
+            last.timestamp = entry.timestamp;
+            return;
+        }
+    }
+
+    log.push(entry);
+}
+
+/** file format of the internal log, `internal_log_format` in config.toml */
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum InternalLogFormat {
+    Toml,  // internal_log.toml, one [[entries]] table per entry
+    Jsonl, // internal_log.jsonl, one JSON object per line
+}
+
+impl InternalLogFormat {
+    fn path(self) -> PathBuf {
+        match self {
+            InternalLogFormat::Toml => data_path("internal_log.toml"),
+            InternalLogFormat::Jsonl => data_path("internal_log.jsonl"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
+enum LogLevel {
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    /** color of the line in the internal log view */
+    fn color(self) -> Color32 {
+        match self {
+            LogLevel::Info => Color32::LIGHT_GREEN,
+            LogLevel::Warn => Color32::from_rgb(255, 191, 0), // amber
+            LogLevel::Error => Color32::from_rgb(255, 90, 90),
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -72,19 +220,292 @@ pub struct SmtpConfig {
     pub username: String,
     pub password: String,
     pub from: String,
+    #[serde(default = "default_tls_mode")]
+    pub tls_mode: String, // "starttls", "implicit" (SMTPS, usually port 465) or "none"
+}
+
+fn default_tls_mode() -> String {
+    "starttls".to_string()
 }
 
 #[derive(Default, Deserialize, Serialize, Clone)]
 struct BackupEntry {
     description: String,
+    #[serde(default)]
+    id: String, // folder name on disk, generated from the description and saved to config.toml when empty
+    #[serde(default)]
     url: String,
-    restore: String,
+    #[serde(default)]
+    command: Option<String>, // shell command whose stdout is the backup, used instead of url
+    #[serde(default)]
+    pre_command: Option<String>, // run before the backup, a non-zero exit status aborts it
+    #[serde(default)]
+    post_command: Option<String>, // run after the backup, whether it worked or not
+    #[serde(default = "default_command_timeout_minutes")]
+    command_timeout_minutes: u64, // command, pre_command and post_command are killed after this long
+    #[serde(default)]
+    restore: RestoreTargets, // empty disables the Restore button
+    #[serde(default = "default_restore_field_name")]
+    restore_field_name: String, // multipart form field the file is uploaded in
+    #[serde(default = "default_restore_content_type")]
+    restore_content_type: String, // MIME type of that field
+    #[serde(default)]
+    username: Option<String>, // HTTP basic auth for url and verify_url, together with password
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    send_auth: bool, // send the token (or a JWT) with downloads, off so credentials only go where asked
+    #[serde(default)]
+    token_query_param: Option<String>, // send the token as this query parameter instead of a Bearer header
+    #[serde(default)]
+    verify_url: Option<String>, // GET after a restore, must answer 2xx for the restore to count as done
+    #[serde(default)]
+    test_restore_url: Option<String>, // staging route the newest restore point is restored to as a test
+    #[serde(default)]
+    test_restore_health_url: Option<String>, // GET after a test restore, must answer 2xx for it to pass
+    #[serde(default)]
+    test_restore_hours: Option<u32>, // how often to test restore, None never runs it
     max: u32,
+    #[serde(default)]
+    max_age_days: Option<u32>, // also rotate out restore points older than this
+    #[serde(default)]
+    max_total_bytes: Option<u64>, // also rotate out the oldest restore points to stay under this size
+    #[serde(default)]
+    retention: Option<String>, // "count" (default) or "gfs"
+    #[serde(default)]
+    gfs: retention::GfsTiers, // tier sizes used when retention = "gfs"
     interval: String,
+    #[serde(default)]
     time: u32,
+    #[serde(default)]
+    at: Option<String>, // readable alternative to time, EX: "Fri 12:05", turned into time at load
+    #[serde(default)]
+    digest: bool, // report results and problems in the daily digest instead of alerting right away
+    #[serde(default)]
+    weekdays: Vec<String>, // with interval = "w", run on each of these days, EX: ["Mon", "Wed", "Fri"]
+    #[serde(default)]
+    backup_before_restore: bool, // preselects "Back up the current state first" when restoring
+    #[serde(default)]
+    period_minutes: Option<u32>, // length of the period for interval = "custom", at most a day
+    #[serde(default)]
+    cron: Option<String>, // takes precedence over interval/time when set
+    #[serde(default)]
+    filename_template: Option<String>, // e.g. "maindb_{timestamp}.{ext}"
+    #[serde(default)]
+    raw: bool, // keep Content-Encoding compressed bytes instead of decoding them
+    #[serde(default)]
+    latest_copy: bool, // keep latest.<ext> pointing at the newest backup
+    #[serde(default)]
+    write_sidecar: bool, // write <file>.meta.json with the log entry next to each restore point
+    #[serde(default)]
+    extract_archive: bool, // unpack a downloaded .zip/.tar/.tar.gz into a folder per restore point
+    #[serde(default)]
+    partition_by: Option<String>, // "none" (default), "day" or "month": store in <folder>/YYYY/MM[/DD]
+    #[serde(default)]
+    accept_statuses: Vec<u16>, // accepted on top of any 2xx, e.g. [206]
+    #[serde(default)]
+    connect_timeout_secs: Option<u64>, // time allowed to connect, default 30
+    #[serde(default)]
+    stall_timeout_secs: Option<u64>, // abort a download that sends nothing for this long, default 300
+    #[serde(default)]
+    checksum_url: Option<String>, // serves the SHA-256 of the file the backup url just sent
+    #[serde(default)]
+    verify_etag: bool, // the ETag of the download is its SHA-256
+    #[serde(default)]
+    jitter_seconds: Option<u32>, // overrides backup_jitter_seconds
+    #[serde(default)]
+    max_staleness_hours: Option<u32>, // warn when the newest restore point is older than this
+    #[serde(default)]
+    max_shrink_percent: Option<u32>, // warn when a backup is this much smaller than the one before
+    #[serde(default)]
+    keep_on_shrink: bool, // skip rotation after such a shrink, so the good backups stay
+    #[serde(default)]
+    min_free_bytes: Option<u64>, // warn before a backup when the folder's disk has less free space
+    #[serde(default)]
+    skip_on_low_space: bool, // don't start the backup then, instead of risking a half-written file
+    #[serde(skip)]
+    schedule: Option<Schedule>, // parsed from `cron` at config load
+    #[serde(skip)]
+    weekday_set: Vec<Weekday>, // parsed from `weekdays` at config load, Monday first
+    #[serde(skip)]
+    auth_test: Option<(bool, String)>, // (success, message) from the "Test auth" button
+    #[serde(skip)]
+    auth_testing: bool, // a "Test auth" request is on its way
+    #[serde(skip)]
+    integrity: Option<(bool, String)>, // (all intact, summary) of the last integrity check
+    #[serde(skip)]
+    test_restore: Option<(bool, String)>, // (passed, summary) of the last test restore
+    #[serde(skip)]
+    folder: Option<PathBuf>, // `id` below backup_root, None if it couldn't be used safely
     #[serde(skip)] // <-- Important
     #[serde(default)]
     logs: Vec<LogEntry>,
+    #[serde(skip, default = "default_true")] // paused state lives in state.toml
+    enabled: bool,
+    #[serde(skip, default = "Utc::now")] // stands in for the last success until there is one
+    watched_since: DateTime<Utc>,
+    #[serde(skip)]
+    alerted_stale: bool, // a staleness warning was sent, cleared by the next successful backup
+    #[serde(skip)]
+    free_space: Option<u64>, // free bytes on the folder's disk, refreshed every minute
+    #[serde(skip)]
+    alerted_low_space: bool, // a low space warning was sent, cleared once there is room again
+    #[serde(skip, default = "Utc::now")] // from state.toml, the first test restore runs test_restore_hours after it is set up
+    last_test_restore: DateTime<Utc>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_command_timeout_minutes() -> u64 {
+    60
+}
+
+fn default_restore_field_name() -> String {
+    "file".to_string()
+}
+
+fn default_restore_content_type() -> String {
+    "application/octet-stream".to_string()
+}
+
+fn default_backup_root() -> String {
+    ".".to_string()
+}
+
+fn default_max_concurrent_backups() -> usize {
+    2
+}
+
+fn default_internal_log_format() -> String {
+    "toml".to_string()
+}
+
+/** the alert settings panel. Edits stay here until they are saved */
+#[derive(Clone)]
+struct AlertSettingsForm {
+    smtp: SmtpConfig,
+    email: String,
+    use_email: bool,
+    send_post_request: bool,
+    desktop_notifications: bool,
+    post_request_routes: String, // one URL per line
+    daily_max: u32,
+}
+
+/** a due backup waiting for a free slot, see max_concurrent_backups */
+struct QueuedBackup {
+    id: String,
+    not_before: DateTime<Utc>, // jitter, the backup doesn't start before this
+}
+
+/** sent back by the thread a backup runs on */
+struct BackupOutcome {
+    id: String,
+    result: Result<String, String>, // the stored filename, or what went wrong
+    archive: Option<ArchiveFormat>, // set when the filename is a folder unpacked from this kind of archive
+    sha256: Option<String>, // hash of what was stored, for the integrity check
+    server_checksum: Option<String>, // the server's checksum the download was verified against
+    hook_log: Vec<(LogLevel, String)>, // output of pre_command/post_command for the internal log
+    duration_ms: u64,
+    online: bool, // from a connectivity probe after a failed download, true when none ran
+}
+
+/** sent back by the thread a test restore runs on */
+struct TestRestoreOutcome {
+    id: String,
+    filename: String, // the restore point that was tested
+    result: Result<String, String>, // where it was restored and checked, or what went wrong
+}
+
+/** what a worker thread needs to check one URL, owned so it can move there */
+struct UptimeJob {
+    index: usize,
+    url: String,
+    auth: Option<(String, String)>,
+    query_token: Option<(String, String)>, // (parameter, token)
+    expect_final_url: Option<String>,
+}
+
+/** sent back by an uptime check worker */
+struct UptimeResult {
+    index: usize,
+    url: String, // finds the entry again if the list was reordered meanwhile
+    result: Result<Option<DateTime<Utc>>, String>, // certificate expiry, or why the check failed
+}
+
+/** an uptime check running on up to uptime_concurrency threads. process_uptime_results
+applies the results as they come in and evaluates warnings once all are in */
+struct UptimeSweep {
+    total: usize,
+    done: usize,
+    results_rx: Receiver<UptimeResult>,
+    online_rx: Receiver<bool>, // the connectivity probe, sent before any URL is checked
+    probed: bool, // the probe result is in and the host was online
+}
+
+/** runtime state that should survive a restart, kept out of config.toml */
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+struct AppState {
+    paused_backups: Vec<String>, // ids of paused backups (descriptions in older files)
+    muted_urls: Vec<String>, // urls whose alerts are muted
+    digest_events: Vec<DigestEvent>, // collected since the last digest, so a restart doesn't lose them
+    backup_enabled: Option<bool>, // last state of the global backup toggle
+    log_panel_height: Option<f32>, // internal log panel height set in the app
+    log_font_size: Option<f32>, // internal log text size set in the app
+    last_test_restores: HashMap<String, String>, // backup id -> RFC 3339 start of its last scheduled test restore
+}
+
+/** the two kinds of entries that can be reordered and renamed from the GUI */
+#[derive(Clone, Copy, PartialEq)]
+enum EntryKind {
+    Url,
+    Backup,
+}
+
+impl EntryKind {
+    /** the array of tables holding this kind of entry in config.toml */
+    fn config_key(self) -> &'static str {
+        match self {
+            EntryKind::Url => "urls",
+            EntryKind::Backup => "backups",
+        }
+    }
+}
+
+/** a change to the URL/backup lists, applied after the lists are drawn */
+enum EntryAction {
+    Move(EntryKind, usize, usize), // (kind, from, to)
+    Rename(EntryKind, usize, String),
+}
+
+/** what a warning is about, each category has its own channels */
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum AlertCategory {
+    Uptime, // URLs down or back up, host offline
+    Backup, // failed backups
+}
+
+/** what a warning says, shared by the email and POST channels */
+struct Warning {
+    category: AlertCategory,
+    subject: String,     // email subject
+    body: String,        // email body
+    description: String, // "description" in the POST payload
+    logs: Vec<String>,   // "logs" in the POST payload
+}
+
+/** a warning as it went out, with the instance details filled in. The last one is kept in
+memory for "Replay last warning" */
+struct SentWarning {
+    category: AlertCategory,
+    subject: String,    // email subject and desktop notification title
+    body: String,       // email body
+    description: String, // desktop notification text
+    payload: JsonValue, // POSTed to post_request_routes
+    sent_at: DateTime<Utc>,
 }
 
 #[derive(Default, Deserialize)]
@@ -92,9 +513,150 @@ struct BackupEntry {
 struct WarningSettings {
     use_email: bool,
     send_post_request: bool,
+    desktop_notifications: bool, // show failures as OS notifications on this machine
     post_request_routes: Vec<String>,
     email: String,
     daily_max: u32,
+    channel_daily_max: HashMap<String, u32>, // per channel caps, e.g. { post = 50 }. Other channels use daily_max
+    warning_cooldown_minutes: u32, // no new warning of the same category within this many minutes, 0 turns it off
+    #[serde(default = "default_warning_log_lines")]
+    warning_log_lines: usize, // number of internal log lines included in warnings
+    uptime_channels: Option<Vec<String>>, // "email"/"post"/"desktop", replaces the flags above for uptime warnings
+    backup_channels: Option<Vec<String>>, // the same for backup warnings
+    escalation: Vec<EscalationStep>, // extra channels for URLs that stay down, in order
+    digest_at: Option<String>, // "HH:MM" in timezone to email the digest of entries with digest = true
+    #[serde(skip)]
+    digest_minute: Option<u32>, // digest_at as minutes after midnight, None when there is no digest
+}
+
+/** something that happened to a URL or backup with digest = true, waiting for the next digest */
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct DigestEvent {
+    timestamp: String,
+    message: String,
+}
+
+/** `[[warning_settings.escalation]]`: once a URL has failed `after_failed_checks` checks in a
+row or has been down for `after_minutes`, a warning also goes out through `channels`. Each
+step fires once per outage */
+#[derive(Deserialize, Clone)]
+struct EscalationStep {
+    #[serde(default)]
+    after_failed_checks: Option<u32>,
+    #[serde(default)]
+    after_minutes: Option<u32>,
+    channels: Vec<String>,
+}
+
+impl EscalationStep {
+    fn reached(&self, failed_checks: u32, minutes_down: i64) -> bool {
+        self.after_failed_checks.is_some_and(|checks| failed_checks >= checks)
+            || self.after_minutes.is_some_and(|minutes| minutes_down >= minutes as i64)
+    }
+}
+
+const ALERT_CHANNELS: [&str; 3] = ["email", "post", "desktop"];
+
+impl WarningSettings {
+    /** whether a channel ("email", "post" or "desktop") is used for a category. Without a channel list
+    for the category, use_email and send_post_request decide */
+    fn channel_enabled(&self, category: AlertCategory, channel: &str) -> bool {
+        let channels = match category {
+            AlertCategory::Uptime => &self.uptime_channels,
+            AlertCategory::Backup => &self.backup_channels,
+        };
+
+        match channels {
+            Some(channels) => channels.iter().any(|name| name == channel),
+            None => match channel {
+                "email" => self.use_email,
+                "post" => self.send_post_request,
+                "desktop" => self.desktop_notifications,
+                _ => false,
+            },
+        }
+    }
+}
+
+impl WarningSettings {
+    /** how many warnings a channel may send per day */
+    fn daily_max_for(&self, channel: &str) -> u32 {
+        self.channel_daily_max.get(channel).copied().unwrap_or(self.daily_max)
+    }
+}
+
+fn default_warning_log_lines() -> usize {
+    50
+}
+
+/** applied to every HTTP client the app builds */
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+struct HttpSettings {
+    user_agent: String,
+    instance_name: String, // sent as X-WebSync-Instance when not empty
+    follow_redirects: FollowRedirects,
+    ca_cert_path: Option<String>, // PEM file with extra certificates to trust, e.g. an internal CA
+    danger_accept_invalid_certs: bool, // turns off certificate checks, for lab setups only
+    #[serde(skip)]
+    ca_certs: Vec<Certificate>, // read from ca_cert_path at config load
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            user_agent: format!("WebSyncStation/{}", env!("CARGO_PKG_VERSION")),
+            instance_name: String::new(),
+            follow_redirects: FollowRedirects::Enabled(true),
+            ca_cert_path: None,
+            danger_accept_invalid_certs: false,
+            ca_certs: vec![],
+        }
+    }
+}
+
+/** `restore = "url"` or a list of urls, tried in order until one accepts the file */
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+enum RestoreTargets {
+    One(String),
+    List(Vec<String>),
+}
+
+impl Default for RestoreTargets {
+    fn default() -> Self {
+        RestoreTargets::One(String::new())
+    }
+}
+
+impl RestoreTargets {
+    /** the configured urls in the order they are tried, empty ones left out */
+    fn urls(&self) -> Vec<&str> {
+        let urls = match self {
+            RestoreTargets::One(url) => vec![url.as_str()],
+            RestoreTargets::List(urls) => urls.iter().map(String::as_str).collect(),
+        };
+        urls.into_iter().filter(|url| !url.is_empty()).collect()
+    }
+}
+
+/** `follow_redirects = true/false` or the maximum number of redirects to follow */
+#[derive(Deserialize, Clone, Copy)]
+#[serde(untagged)]
+enum FollowRedirects {
+    Enabled(bool),
+    Max(usize),
+}
+
+impl FollowRedirects {
+    /** how many redirects to follow, 0 means the redirect response itself is the result */
+    fn limit(self) -> usize {
+        match self {
+            FollowRedirects::Enabled(true) => 10, // reqwest's default
+            FollowRedirects::Enabled(false) => 0,
+            FollowRedirects::Max(max) => max,
+        }
+    }
 }
 
 #[derive(Default, Deserialize)]
@@ -102,82 +664,338 @@ struct WarningSettings {
 struct UptimeUrlSettings {
     interval_minutes: u32,
     downtime_tolerance: u32,
+    downtime_tolerance_minutes: Option<u32>, // replaces the count above when set
+    connectivity_check_urls: Option<Vec<String>>, // None uses DEFAULT_CONNECTIVITY_CHECK_URLS, [] disables
+    fail_on_redirect: bool, // a 3xx answer counts as down instead of being followed
+    stats_days: Option<u32>, // days of history behind the uptime percentage, default 30
+    cert_expiry_warn_days: Option<u32>, // warn when a TLS certificate expires within this many days, default 14, 0 disables
+    uptime_concurrency: Option<usize>, // URLs checked at the same time, default 8
 }
 
+impl UptimeUrlSettings {
+    fn stats_window_days(&self) -> u32 {
+        self.stats_days.unwrap_or(30).max(1)
+    }
+
+    fn cert_warn_days(&self) -> u32 {
+        self.cert_expiry_warn_days.unwrap_or(14)
+    }
+
+    fn concurrency(&self) -> usize {
+        self.uptime_concurrency.unwrap_or(8).max(1)
+    }
+}
+
+/** internal log panel size until it is changed in the app, and the range the controls allow */
+const DEFAULT_LOG_PANEL_HEIGHT: f32 = 200.0;
+const LOG_PANEL_HEIGHTS: std::ops::RangeInclusive<f32> = 100.0..=1000.0;
+const DEFAULT_LOG_FONT_SIZE: f32 = 12.0; // egui's monospace size
+const LOG_FONT_SIZES: std::ops::RangeInclusive<f32> = 8.0..=32.0;
+
+/** shortest wait and most tries when a server answers 429 or 503 with Retry-After */
+const RETRY_AFTER_MIN_DELAY: Duration = Duration::from_secs(1);
+const RETRY_AFTER_MAX_ATTEMPTS: u32 = 5;
+const RETRY_AFTER_SLEEP_STEP: Duration = Duration::from_millis(200); // how soon a shutdown ends the wait
+
+/** how long shutting down waits for running backups before leaving them unfinished */
+const SHUTDOWN_BACKUP_WAIT: Duration = Duration::from_secs(30);
+
+/** probed to tell "this host is offline" apart from "the monitored services are down" */
+const DEFAULT_CONNECTIVITY_CHECK_URLS: [&str; 2] = ["https://1.1.1.1/", "https://8.8.8.8/"];
+
 struct StatusChecker {
     uptime_url_settings: UptimeUrlSettings,
     uptime_fails: u32,
+    uptime_sweep: Option<UptimeSweep>, // the uptime check that is running, if any
     internal_log: Vec<InternalLogEntry>,
+    internal_log_format: InternalLogFormat,
+    internal_log_written: (usize, u32), // entries already in the log file, and the count of the last one
+    log_panel_height: f32,
+    log_font_size: f32,
     warning_settings: WarningSettings,
     uptime_urls: Vec<UrlEntry>,
     backups: Vec<BackupEntry>,
     secret: String,
     token: String,
     jwt_expiry: u64,
-    payload: HashMap<String, TomlValue>,
+    payload: TomlTable,
     backup_enabled: bool,
-    backup_trigger_rx: Receiver<()>,
+    backup_jitter_seconds: u32,
+    backup_enable_grace_minutes: u32,
+    backup_enabled_at: Option<DateTime<Utc>>, // when the schedule was last enabled in the app, None since start
+    timezone: Tz, // backup schedules are wall-clock times here
+    display_timezone: Option<Tz>, // times shown in the app and in warnings, None is the system's zone
+    run_on_start_pending: bool, // run_on_start is set and the startup run hasn't happened yet
+    backup_trigger_rx: Receiver<DateTime<Utc>>, // one message per minute boundary
+    max_concurrent_backups: usize,
+    backup_queue: Vec<QueuedBackup>, // due backups waiting for a free slot
+    running_backups: Vec<String>, // ids of the backups running on their own thread right now
+    backup_outcome_tx: Sender<BackupOutcome>,
+    backup_outcome_rx: Receiver<BackupOutcome>,
+    integrity_check_hours: Option<u32>, // re-hash stored backups this often, None turns it off
+    last_integrity_check: DateTime<Utc>, // start of the last scheduled scan, or when WSS started
+    verifying_backups: Vec<String>, // ids of the backups being re-hashed right now
+    integrity_tx: Sender<integrity::Report>,
+    integrity_rx: Receiver<integrity::Report>,
+    test_restoring: Vec<(String, String)>, // (backup id, filename) of the test restores running right now
+    test_restore_tx: Sender<TestRestoreOutcome>,
+    test_restore_rx: Receiver<TestRestoreOutcome>,
+    auth_test_tx: Sender<(String, (bool, String))>, // (backup id, result) of "Test auth" requests
+    auth_test_rx: Receiver<(String, (bool, String))>,
+    shutting_down: Arc<AtomicBool>, // set when the window closes, stops new scheduled work
     smtp_config: SmtpConfig,
-    warnings_sent: u32,
+    http_settings: HttpSettings,
+    maintenance_windows: Vec<MaintenanceWindow>,
+    health: Arc<Health>, // shared with the /healthz server thread
+    heartbeat_url: Option<String>, // pinged while WSS runs, so an external monitor notices when it stops
+    heartbeat_minutes: u32,
+    last_heartbeat: Option<DateTime<Utc>>,
+    warnings_sent: HashMap<String, u32>, // per channel, reset at midnight UTC
+    last_warning_sent: HashMap<AlertCategory, DateTime<Utc>>, // for warning_cooldown_minutes
+    last_sent_warning: Option<SentWarning>, // for "Replay last warning", never saved
+    digest_events: Vec<DigestEvent>,
+    pending_delete: Option<(String, String)>, // (backup id, filename) awaiting confirmation
+    pending_restore: Option<(String, String, bool)>, // (backup id, filename, back up first) awaiting confirmation
+    restores_after_backup: Vec<(String, String)>, // (backup id, filename) to restore once its safety backup is done
+    editing_description: Option<(EntryKind, usize, String)>, // entry being renamed and the new name
+    alert_test_results: Vec<(bool, String)>, // (success, message) from the test buttons
+    alert_test_tx: Sender<Vec<(bool, String)>>, // results of test POSTs, sent from their thread
+    alert_test_rx: Receiver<Vec<(bool, String)>>,
+    alert_settings_form: Option<AlertSettingsForm>, // filled when the settings panel is first opened
+    import_path: String, // CSV or JSON file for "Import URLs"
+    import_result: Option<(bool, String)>, // (success, message) of the last import
+    startup_problems: Vec<String>, // config warnings shown in a dialog until dismissed
+    hostname: String,
+    instance: String, // http.instance_name, or the hostname when that is empty. Shown in every alert
+    run_id: String,   // new for every start, so alerts from a restarted instance can be told apart
+    redact_curl_token: bool,
+    snooze_until: Option<DateTime<Utc>>,
+    snooze_minutes: u32,
+    paused_until: Option<DateTime<Utc>>, // no uptime checks or scheduled backups before this
+    pause_minutes: u32,
+    offline_since: Option<DateTime<Utc>>,
+    uptime_stats: UptimeStats,
 }
 
 impl Default for StatusChecker {
     fn default() -> Self {
         let (_tx, rx) = std::sync::mpsc::channel();
+        let (outcome_tx, outcome_rx) = std::sync::mpsc::channel();
+        let (integrity_tx, integrity_rx) = std::sync::mpsc::channel();
+        let (test_restore_tx, test_restore_rx) = std::sync::mpsc::channel();
+        let (auth_test_tx, auth_test_rx) = std::sync::mpsc::channel();
+        let (alert_test_tx, alert_test_rx) = std::sync::mpsc::channel();
         Self {
             uptime_url_settings: UptimeUrlSettings {
                 interval_minutes: 5,
                 downtime_tolerance: 3,
+                downtime_tolerance_minutes: None,
+                connectivity_check_urls: None,
+                fail_on_redirect: false,
+                stats_days: None,
+                cert_expiry_warn_days: None,
+                uptime_concurrency: None,
             },
             uptime_fails: 0,
+            uptime_sweep: None,
             internal_log: vec![],
+            internal_log_format: InternalLogFormat::Toml,
+            internal_log_written: (0, 0),
+            log_panel_height: DEFAULT_LOG_PANEL_HEIGHT,
+            log_font_size: DEFAULT_LOG_FONT_SIZE,
             warning_settings: WarningSettings {
                 use_email: false,
                 send_post_request: false,
+                desktop_notifications: false,
                 post_request_routes: vec![],
                 email: "test@example.com".to_string(),
                 daily_max: 5,
+                channel_daily_max: HashMap::new(),
+                warning_cooldown_minutes: 0,
+                warning_log_lines: default_warning_log_lines(),
+                uptime_channels: None,
+                backup_channels: None,
+                escalation: vec![],
+                digest_at: None,
+                digest_minute: None,
             },
             uptime_urls: vec![UrlEntry {
                 description: "google.com".to_string(),
                 url: "https://google.com".to_string(),
+                interval_minutes: None,
+                username: None,
+                password: None,
+                token_query_param: None,
+                expect_final_url: None,
                 is_ok: false,
+                invalid: None,
+                down_since: None,
+                alerted_down: false,
+                cert_expires: None,
+                alerted_cert_expiry: false,
+                outage_started: None,
+                failed_checks: 0,
+                escalation_level: 0,
+                muted: false,
+                digest: false,
             }],
             backups: vec![BackupEntry {
                 description: "https://nosite.com".to_string(),
+                id: "nosite".to_string(),
                 url: "https://nosite.com".to_string(),
-                restore: "https://nosite.com".to_string(),
+                command: None,
+                pre_command: None,
+                post_command: None,
+                command_timeout_minutes: default_command_timeout_minutes(),
+                restore: RestoreTargets::One("https://nosite.com".to_string()),
+                restore_field_name: default_restore_field_name(),
+                restore_content_type: default_restore_content_type(),
+                username: None,
+                password: None,
+                send_auth: false,
+                token_query_param: None,
+                verify_url: None,
+                test_restore_url: None,
+                test_restore_health_url: None,
+                test_restore_hours: None,
                 max: 10,
+                max_age_days: None,
+                max_total_bytes: None,
+                retention: None,
+                gfs: retention::GfsTiers::default(),
                 interval: "d".to_string(),
                 time: 800,
+                at: None,
+                period_minutes: None,
+                weekdays: vec![],
+                digest: false,
+                backup_before_restore: false,
+                cron: None,
+                schedule: None,
+                weekday_set: vec![],
+                folder: None,
+                auth_test: None,
+                auth_testing: false,
+                integrity: None,
+                test_restore: None,
+                filename_template: None,
+                raw: false,
+                latest_copy: false,
+                write_sidecar: false,
+                extract_archive: false,
+                partition_by: None,
+                accept_statuses: vec![],
+                connect_timeout_secs: None,
+                stall_timeout_secs: None,
+                checksum_url: None,
+                verify_etag: false,
+                jitter_seconds: None,
+                max_staleness_hours: None,
+                max_shrink_percent: None,
+                keep_on_shrink: false,
+                min_free_bytes: None,
+                skip_on_low_space: false,
                 logs: Vec::new(),
+                enabled: true,
+                watched_since: Utc::now(),
+                alerted_stale: false,
+                free_space: None,
+                alerted_low_space: false,
+                last_test_restore: Utc::now(),
             }],
             // backup_logs: vec![],
             token: "".to_string(),
             secret: "".to_string(),
             jwt_expiry: 600,
-            payload: HashMap::new(),
+            payload: TomlTable::new(),
             backup_enabled: false,
+            backup_jitter_seconds: 0,
+            backup_enable_grace_minutes: 0,
+            backup_enabled_at: None,
+            timezone: Tz::UTC,
+            display_timezone: None,
+            run_on_start_pending: false,
             backup_trigger_rx: rx,
+            max_concurrent_backups: 1,
+            backup_queue: vec![],
+            running_backups: vec![],
+            backup_outcome_tx: outcome_tx,
+            backup_outcome_rx: outcome_rx,
+            integrity_check_hours: None,
+            last_integrity_check: Utc::now(),
+            verifying_backups: vec![],
+            integrity_tx,
+            integrity_rx,
+            test_restoring: vec![],
+            test_restore_tx,
+            test_restore_rx,
+            auth_test_tx,
+            auth_test_rx,
+            shutting_down: Arc::new(AtomicBool::new(false)),
             smtp_config: SmtpConfig {
                 server: "smtp.example.com".to_string(),
                 port: 587,
                 username: "nouser".to_string(),
                 password: "nopassword".to_string(),
                 from: "nobody".to_string(),
+                tls_mode: default_tls_mode(),
             },
-            warnings_sent: 0,
+            http_settings: HttpSettings::default(),
+            maintenance_windows: vec![],
+            health: Arc::new(Health::new(0)),
+            heartbeat_url: None,
+            heartbeat_minutes: 1,
+            last_heartbeat: None,
+            warnings_sent: HashMap::new(),
+            last_warning_sent: HashMap::new(),
+            last_sent_warning: None,
+            digest_events: vec![],
+            pending_delete: None,
+            pending_restore: None,
+            restores_after_backup: vec![],
+            editing_description: None,
+            alert_test_results: vec![],
+            alert_test_tx,
+            alert_test_rx,
+            alert_settings_form: None,
+            startup_problems: Vec::new(),
+            import_path: String::new(),
+            import_result: None,
+            hostname: machine_hostname(),
+            instance: machine_hostname(),
+            run_id: Uuid::new_v4().to_string(),
+            redact_curl_token: true,
+            snooze_until: None,
+            snooze_minutes: 60,
+            paused_until: None,
+            pause_minutes: 60,
+            offline_since: None,
+            uptime_stats: UptimeStats::default(),
         }
     }
 }
 
 impl From<Config> for StatusChecker {
     fn from(cfg: Config) -> Self {
+        let monitored_urls = cfg.urls.len();
         let (_tx, rx) = std::sync::mpsc::channel();
+        let (outcome_tx, outcome_rx) = std::sync::mpsc::channel();
+        let (integrity_tx, integrity_rx) = std::sync::mpsc::channel();
+        let (test_restore_tx, test_restore_rx) = std::sync::mpsc::channel();
+        let (auth_test_tx, auth_test_rx) = std::sync::mpsc::channel();
+        let (alert_test_tx, alert_test_rx) = std::sync::mpsc::channel();
         Self {
             uptime_url_settings: cfg.url_uptime_settings,
             uptime_fails: 0,
+            uptime_sweep: None,
             internal_log: vec![],
+            internal_log_format: match cfg.internal_log_format.as_str() {
+                "jsonl" => InternalLogFormat::Jsonl,
+                _ => InternalLogFormat::Toml,
+            },
+            internal_log_written: (0, 0),
+            log_panel_height: DEFAULT_LOG_PANEL_HEIGHT,
+            log_font_size: DEFAULT_LOG_FONT_SIZE,
             warning_settings: cfg.warning_settings,
             uptime_urls: cfg.urls,
             backups: cfg.backups,
@@ -186,978 +1004,5535 @@ impl From<Config> for StatusChecker {
             jwt_expiry: cfg.jwt_expiry,
             payload: cfg.payload,
             backup_enabled: false,
+            backup_jitter_seconds: cfg.backup_jitter_seconds,
+            backup_enable_grace_minutes: cfg.backup_enable_grace_minutes,
+            backup_enabled_at: None,
+            timezone: cfg.timezone.as_deref().and_then(|name| name.parse().ok()).unwrap_or(Tz::UTC),
+            display_timezone: cfg.timezone.as_deref().and_then(|name| name.parse().ok()),
+            run_on_start_pending: cfg.run_on_start,
             backup_trigger_rx: rx,
+            max_concurrent_backups: cfg.max_concurrent_backups,
+            backup_queue: vec![],
+            running_backups: vec![],
+            backup_outcome_tx: outcome_tx,
+            backup_outcome_rx: outcome_rx,
+            integrity_check_hours: cfg.integrity_check_hours,
+            last_integrity_check: Utc::now(),
+            verifying_backups: vec![],
+            integrity_tx,
+            integrity_rx,
+            test_restoring: vec![],
+            test_restore_tx,
+            test_restore_rx,
+            auth_test_tx,
+            auth_test_rx,
+            shutting_down: Arc::new(AtomicBool::new(false)),
             smtp_config: cfg.smtp,
-            warnings_sent: 0,
+            instance: instance_label(&cfg.http),
+            http_settings: cfg.http,
+            maintenance_windows: cfg.maintenance_windows,
+            health: Arc::new(Health::new(monitored_urls)),
+            heartbeat_url: cfg.health.heartbeat_url.clone(),
+            heartbeat_minutes: cfg.health.heartbeat_minutes.max(1),
+            last_heartbeat: None,
+            warnings_sent: HashMap::new(),
+            last_warning_sent: HashMap::new(),
+            last_sent_warning: None,
+            digest_events: vec![],
+            pending_delete: None,
+            pending_restore: None,
+            restores_after_backup: vec![],
+            editing_description: None,
+            alert_test_results: vec![],
+            alert_test_tx,
+            alert_test_rx,
+            alert_settings_form: None,
+            startup_problems: Vec::new(),
+            import_path: String::new(),
+            import_result: None,
+            hostname: machine_hostname(),
+            run_id: Uuid::new_v4().to_string(),
+            redact_curl_token: true,
+            snooze_until: None,
+            snooze_minutes: 60,
+            paused_until: None,
+            pause_minutes: 60,
+            offline_since: None,
+            uptime_stats: UptimeStats::default(),
         }
     }
 }
 
 impl StatusChecker {
-    /** we assume this runs once a minute */
-    fn auto_backup(&mut self) {
-        let current_time = Utc::now();
-        let minute = current_time.minute();
-        let hour = current_time.hour() * 60;
-        let day = current_time.weekday() as u32 * 24 * 60;
-        let month = current_time.day() * 24 * 60;
+    /** GETs heartbeat_url every heartbeat_minutes on its own thread. If WSS crashes or hangs the
+    pings stop, which a service like healthchecks.io turns into an alert. A failed ping is only
+    logged, the monitor on the other end is the one that warns */
+    fn send_heartbeat(&mut self) {
+        let Some(url) = self.heartbeat_url.clone() else {
+            return;
+        };
 
-        let mut to_backup = Vec::new();
+        let minute = minute_start(Utc::now());
+        let interval = chrono::Duration::minutes(self.heartbeat_minutes as i64);
+        if self.last_heartbeat.is_some_and(|last| minute - last < interval) {
+            return;
+        }
+        self.last_heartbeat = Some(minute);
 
-        for (i, backup) in self.backups.iter().enumerate() {
-            let interval = &backup.interval;
-            let time = backup.time;
-
-            let should_backup = if interval == "h" {
-                let hour_time = time % 60;
-                minute == hour_time
-            } else if interval == "d" {
-                let day_minute = hour + minute;
-                let day_time = time % (24 * 60);
-                day_minute == day_time
-            } else if interval == "w" {
-                let week_minute = day + hour + minute;
-                let week_time = time % (7 * 24 * 60);
-                week_minute == week_time
-            } else if interval == "m" {
-                let month_minute = month + hour + minute;
-                let month_time = time % (31 * 24 * 60);
-                month_minute == month_time
-            } else {
-                false
-            };
+        let http = self.http_settings.clone();
+        thread::spawn(move || match send_request(&url, None, None, None, &http) {
+            Ok(_) => debug!("Heartbeat sent to {}", url),
+            Err(e) => warn!("Heartbeat to {} failed: {}", url, e),
+        });
+    }
 
-            if should_backup {
-                to_backup.push(i);
-            }
+    /** handles a batch of minute ticks, more than one after the machine slept or the clock jumped */
+    fn handle_ticks(&mut self, ticks: &[DateTime<Utc>]) {
+        if let Some(tick) = ticks.last() {
+            self.health.record_tick(*tick);
         }
 
-        for i in to_backup {
-            self.attempt_backup(i);
+        // also while paused, the heartbeat says WSS is alive, not that it is checking
+        self.send_heartbeat();
+
+        if let Some(digest_minute) = self.warning_settings.digest_minute {
+            let due = ticks.iter().any(|tick| {
+                let local = tick.with_timezone(&self.timezone);
+                local.hour() * 60 + local.minute() == digest_minute
+            });
+            if due {
+                self.send_digest();
+            }
         }
-    }
 
-    fn uptime_check(&mut self) {
-        let url_length = self.uptime_urls.len();
+        if ticks.len() > 1 {
+            self.add_internal_log(LogLevel::Warn, format!(
+                "Catching up on {} missed minutes (system sleep or clock change)",
+                ticks.len() - 1
+            ));
+        }
 
-        for i in 0..url_length {
-            let url_test: &str = &self.uptime_urls[i].url;
+        if ticks.iter().any(|tick| tick.hour() == 0 && tick.minute() == 0) {
+            // Reset the warnings sent counters at the start of a new day
+            self.warnings_sent.clear();
+        }
 
-            match send_request(url_test) {
-                Ok(()) => {
-                    self.uptime_urls[i].is_ok = true;
-                }
-                Err(_err) => {
-                    self.uptime_urls[i].is_ok = false;
-                    self.uptime_fails += 1;
-                    self.internal_log.push(InternalLogEntry {
-                        message: format!("{} is down", self.uptime_urls[i].description),
-                        timestamp: Utc::now().to_rfc3339(),
-                    });
+        self.refresh_free_space();
 
-                    print_to_internal_log_file(InternalLog {
-                        entries: self.internal_log.clone(),
-                    });
+        self.expire_pause();
+        if self.paused_until.is_some() {
+            // ticks during a pause are dropped, nothing is caught up afterwards
+            self.publish_status();
+            return;
+        }
 
-                }
-            }
+        if self.backup_enabled {
+            self.auto_backup(ticks);
+            self.check_backup_staleness();
         }
 
-        if self.uptime_fails > self.uptime_url_settings.downtime_tolerance {
-            let mut message_for_email = "Uptime check failed for the following URLs:\n".to_string();
-            let mut failed_url_descriptions = Vec::new();
+        self.schedule_integrity_check();
+        self.schedule_test_restores();
 
-            for i in 0..url_length {
-                if !self.uptime_urls[i].is_ok {
-                    message_for_email.push_str(&format!("{}\n", self.uptime_urls[i].description));
-                    failed_url_descriptions.push(self.uptime_urls[i].description.as_str());
-                }
-            }
-            
-            let log_lines: Vec<String> = self.internal_log
-                .iter()
-                .rev() // Reverse the order to get the latest entries first...
-                .take(50)
-                .map(|entry| format!("{} - {}", entry.timestamp, entry.message))
-                .collect();
-
-            message_for_email.push_str(&format!(
-                "\nThese are the last {} lines of the internal log:\n{}",
-                log_lines.len(),
-                join_with_line_breaks(log_lines.clone()) // Clone for email
-            ));
+        let due_urls: Vec<usize> = (0..self.uptime_urls.len())
+            .filter(|&i| {
+                let interval = self.uptime_urls[i]
+                    .interval_minutes
+                    .unwrap_or(self.uptime_url_settings.interval_minutes);
 
+                ticks.iter().any(|tick| {
+                    let total_minutes = tick.hour() * 60 + tick.minute();
+                    total_minutes.is_multiple_of(interval)
+                })
+            })
+            .collect();
 
+        // one check per URL is enough, even if several were missed
+        if !due_urls.is_empty() {
+            self.uptime_check_urls(&due_urls);
+        }
 
-            let mut has_sent_warning = false;
-            let is_over_daily_limit = self.warnings_sent >= self.warning_settings.daily_max;
+        self.publish_status();
+    }
 
-            if is_over_daily_limit {
-                self.internal_log.push(InternalLogEntry {
-                    message: "Warning limit exceeded".to_string(),
-                    timestamp: Utc::now().to_rfc3339(),
-                });
+    /** hands the current URL and backup states to the /status endpoint. The server runs on
+    its own thread, so it answers from this snapshot, which is refreshed every minute and
+    after each backup */
+    fn publish_status(&self) {
+        let now = Utc::now();
+
+        let urls: Vec<JsonValue> = self
+            .uptime_urls
+            .iter()
+            .map(|entry| {
+                json!({
+                    "description": entry.description,
+                    "url": entry.url,
+                    "ok": entry.is_ok,
+                    "invalid": entry.invalid,
+                    "down_since": entry.down_since.map(|since| since.to_rfc3339()),
+                    "cert_expires": entry.cert_expires.map(|expires| expires.to_rfc3339()),
+                    "muted": entry.muted,
+                })
+            })
+            .collect();
+
+        let backups: Vec<JsonValue> = self
+            .backups
+            .iter()
+            .map(|backup| {
+                json!({
+                    "id": backup.id,
+                    "description": backup.description,
+                    "paused": !backup.enabled,
+                    "restore_points": backup.logs.len(),
+                    "last_success": last_backup_success(backup).map(|last| last.to_rfc3339()),
+                    "stale": is_backup_stale(backup, now),
+                    "free_bytes": backup.free_space,
+                    "running": self.running_backups.contains(&backup.id),
+                    "queued": self.backup_queue.iter().any(|queued| queued.id == backup.id),
+                })
+            })
+            .collect();
+
+        let log_start = self.internal_log.len().saturating_sub(self.warning_settings.warning_log_lines);
+
+        self.health.publish_status(json!({
+            "time": now.to_rfc3339(),
+            "instance": self.instance,
+            "run_id": self.run_id,
+            "backup_schedule_enabled": self.backup_enabled,
+            "paused_until": self.paused_until.map(|until| until.to_rfc3339()),
+            "urls": urls,
+            "backups": backups,
+            "internal_log": &self.internal_log[log_start..],
+        }));
+    }
 
-                print_to_internal_log_file(InternalLog {
-                    entries: self.internal_log.clone(),
-                });
+    /** warns once when a backup has had no successful run for longer than its
+    max_staleness_hours. This also catches backups that stopped being attempted at all */
+    fn check_backup_staleness(&mut self) {
+        let now = Utc::now();
 
+        for i in 0..self.backups.len() {
+            if !self.backups[i].enabled {
+                continue; // paused on purpose
             }
 
+            let stale = is_backup_stale(&self.backups[i], now);
 
+            if !stale {
+                if self.backups[i].alerted_stale {
+                    self.backups[i].alerted_stale = false;
+                    let message = format!("RESOLVED: {} has a fresh backup again", self.backups[i].description);
+                    self.add_internal_log(LogLevel::Info, message);
+                }
+                continue;
+            }
 
-            if self.warning_settings.use_email && !is_over_daily_limit {
+            if self.backups[i].alerted_stale {
+                continue;
+            }
 
-                has_sent_warning = true;
+            let last_success = match last_backup_success(&self.backups[i]) {
+                Some(last) => format!("the last successful backup was at {}", format_timestamp(&last.to_rfc3339(), self.display_timezone)),
+                None => "there has been no successful backup yet".to_string(),
+            };
+            let message = format!(
+                "Backup {} is stale: {} (max_staleness_hours = {})",
+                self.backups[i].description,
+                last_success,
+                self.backups[i].max_staleness_hours.unwrap_or_default()
+            );
+            warn!("{}", message);
+            self.add_internal_log(LogLevel::Warn, message.clone());
+
+            let warning = Warning {
+                category: AlertCategory::Backup,
+                subject: format!("Backup stale: {}", self.backups[i].description),
+                body: message.clone(),
+                description: message,
+                logs: self.recent_log_lines(),
+            };
 
-                let smtp = &self.smtp_config;
-                let email_result = try_to_send_email(
-                    &self.warning_settings.email,
-                    "Uptime check failed",
-                    &message_for_email,
-                    smtp,
-                );
-                match email_result {
-                    Ok(_) => println!("Warning email sent successfully!"),
-                    Err(e) => println!("Failed to send warning email: {}", e),
-                };
+            // retried on the next tick if it couldn't be sent
+            if self.send_backup_warning(i, &warning) {
+                self.backups[i].alerted_stale = true;
             }
+        }
+    }
 
-            if self.warning_settings.send_post_request && !is_over_daily_limit {
+    /** reads the free space of every backup folder's disk, for the UI and /status */
+    fn refresh_free_space(&mut self) {
+        for backup in &mut self.backups {
+            backup.free_space = backup.folder.as_deref().and_then(|folder| free_space(folder).ok());
+        }
+    }
 
-                has_sent_warning = true;
+    /** warns once when the disk of backup i has less than min_free_bytes free. Returns false
+    when the backup should be skipped because of it (skip_on_low_space) */
+    fn check_free_space(&mut self, i: usize) -> bool {
+        let Some(min_free) = self.backups[i].min_free_bytes else {
+            return true;
+        };
+        let Some(free) = self.backups[i].folder.as_deref().and_then(|folder| free_space(folder).ok()) else {
+            return true; // unknown, a failing write will still be reported
+        };
+        self.backups[i].free_space = Some(free);
+
+        if free >= min_free {
+            if self.backups[i].alerted_low_space {
+                self.backups[i].alerted_low_space = false;
+                let message = format!(
+                    "RESOLVED: the disk of {} has {} free again",
+                    self.backups[i].description,
+                    format_bytes(free)
+                );
+                self.add_internal_log(LogLevel::Info, message);
+            }
+            return true;
+        }
 
-                let warning_payload = json!({
-                    "time": Utc::now().to_rfc3339(),
-                    "description": format!("Uptime check failed. URLs down: {}", failed_url_descriptions.join(", ")),
-                    "logs": log_lines // Use the already collected log_lines
-                });
-                let json_string = warning_payload.to_string();
-
-                let token_to_use = if self.token.is_empty() {
-                    match create_jwt(&self.payload, &self.secret, &self.jwt_expiry) {
-                        Ok(jwt) => jwt,
-                        Err(e) => {
-                            println!("Failed to create JWT for warning POST: {}", e);
-                            String::new() // Use empty string if JWT creation fails
-                        }
-                    }
-                } else {
-                    self.token.clone()
-                };
-                
-                // Proceed even if token_to_use is empty, as the server might not require auth
-                // or an empty Bearer token might be acceptable in some scenarios.
-                // If a token is absolutely required and JWT creation fails, this will likely fail at the server.
-                for route_url in &self.warning_settings.post_request_routes {
-                    match send_warning_post_request(&token_to_use, &json_string, route_url) {
-                        Ok(_) => println!("Successfully sent POST warning to {}", route_url),
-                        Err(e) => println!("Failed to send POST warning to {}: {}", route_url, e),
-                    }
-                }
+        let skip = self.backups[i].skip_on_low_space;
+        let message = format!(
+            "Low disk space for backup {}: {} free, min_free_bytes is {}{}",
+            self.backups[i].description,
+            format_bytes(free),
+            format_bytes(min_free),
+            if skip { ". The backup is skipped until there is room" } else { "" }
+        );
+        warn!("{}", message);
+        self.add_internal_log(if skip { LogLevel::Error } else { LogLevel::Warn }, message.clone());
+
+        if !self.backups[i].alerted_low_space {
+            let warning = Warning {
+                category: AlertCategory::Backup,
+                subject: format!("Low disk space: {}", self.backups[i].description),
+                body: message.clone(),
+                description: message,
+                logs: self.recent_log_lines(),
+            };
+            if self.send_backup_warning(i, &warning) {
+                self.backups[i].alerted_low_space = true;
             }
+        }
 
+        !skip
+    }
 
-            if has_sent_warning {
-                self.warnings_sent += 1;
-            }
+    /** starts a check of every backup once integrity_check_hours have passed since the last one */
+    fn schedule_integrity_check(&mut self) {
+        let Some(hours) = self.integrity_check_hours.filter(|&hours| hours > 0) else {
+            return;
+        };
 
+        let now = Utc::now();
+        if now - self.last_integrity_check < chrono::Duration::hours(hours as i64) {
+            return;
+        }
+        self.last_integrity_check = now;
 
-            self.uptime_fails = 0; // Reset fails after warnings are sent
-        } else {
-            // Optional: Log that no warning was sent if needed for debugging
-            // println!("Uptime checks passed or tolerance not exceeded. No warning sent.");
+        info!("Starting the scheduled integrity check of {} backups", self.backups.len());
+        for i in 0..self.backups.len() {
+            self.verify_backup(i);
         }
     }
 
+    /** re-hashes the restore points of a backup on its own thread and compares them with
+    log.toml, process_integrity_reports picks up the result */
+    fn verify_backup(&mut self, i: usize) {
+        if self.verifying_backups.contains(&self.backups[i].id) {
+            return;
+        }
+        let Some(folder) = self.backups[i].folder.clone() else {
+            return;
+        };
 
+        let id = self.backups[i].id.clone();
+        let entries = self.backups[i].logs.clone();
+        let integrity_tx = self.integrity_tx.clone();
+        self.verifying_backups.push(id.clone());
 
-
-
-    
-    fn import_internal_log(&mut self) {
-        let log = load_internal_log().unwrap_or_else(|_| InternalLog { entries: vec![] });
-        self.internal_log = log.entries;
+        thread::spawn(move || {
+            let _ = integrity_tx.send(integrity::verify(id, &folder, &entries));
+        });
     }
 
+    /** logs finished integrity checks and warns about restore points that are missing or changed */
+    fn process_integrity_reports(&mut self) {
+        let reports: Vec<integrity::Report> = self.integrity_rx.try_iter().collect();
 
+        for report in reports {
+            self.verifying_backups.retain(|id| *id != report.id);
+            let Some(i) = self.backups.iter().position(|backup| backup.id == report.id) else {
+                continue;
+            };
 
-    fn from_config() -> Result<Self, Box<dyn std::error::Error>> {
-        let mut config = load_config()?;
-        let mut backups = config.backups;
+            // restore points rotated out while the check ran are gone on purpose
+            let problems: Vec<(String, String)> = report
+                .problems
+                .into_iter()
+                .filter(|(filename, _)| self.backups[i].logs.iter().any(|entry| entry.filename == *filename))
+                .collect();
+            let description = self.backups[i].description.clone();
 
+            if problems.is_empty() {
+                let mut message = format!("Integrity check of {}: {} restore points intact", description, report.checked);
+                if report.unhashed > 0 {
+                    message.push_str(&format!(", {} older ones have no recorded hash", report.unhashed));
+                }
+                self.add_internal_log(LogLevel::Info, message.clone());
+                self.backups[i].integrity = Some((true, message));
+                continue;
+            }
 
-        if config.url_uptime_settings.interval_minutes == 0 {
-            // Option 1: Log and use a default
-            eprintln!("Warning: url_uptime_settings.interval_minutes is 0. Using default of 60 minutes.");
-            config.url_uptime_settings.interval_minutes = 60; 
+            let details: Vec<String> = problems
+                .iter()
+                .map(|(filename, problem)| format!("{}: {}", filename, problem))
+                .collect();
+            let message = format!(
+                "Integrity check of {} failed for {} restore point(s):\n{}",
+                description,
+                problems.len(),
+                details.join("\n")
+            );
+            error!("{}", message);
+            self.add_internal_log(LogLevel::Error, message.clone());
+            self.backups[i].integrity = Some((false, format!("{} restore point(s) damaged, see the log", problems.len())));
+
+            let warning = Warning {
+                category: AlertCategory::Backup,
+                subject: format!("Backup integrity check failed: {}", description),
+                body: message.clone(),
+                description: message,
+                logs: self.recent_log_lines(),
+            };
+            self.send_backup_warning(i, &warning);
         }
+    }
 
+    /** starts a test restore of every backup whose test_restore_hours have passed since its last one */
+    fn schedule_test_restores(&mut self) {
+        let now = Utc::now();
 
+        for i in 0..self.backups.len() {
+            let Some(hours) = self.backups[i].test_restore_hours.filter(|&hours| hours > 0) else {
+                continue;
+            };
+            if self.backups[i].test_restore_url.is_none()
+                || now - self.backups[i].last_test_restore < chrono::Duration::hours(hours as i64)
+            {
+                continue;
+            }
+            self.backups[i].last_test_restore = now;
+            self.test_restore_backup(i);
+            self.save_state();
+        }
+    }
 
-        //loads the log for each backup.
-        for entry in &mut backups {
-            let logs = load_log(&entry.description).unwrap_or_else(|_| Log { entries: vec![] });
-            entry.logs = logs.entries;
+    /** restores the newest restore point of a backup to its test_restore_url on its own thread,
+    process_test_restores picks up the result. Production restore routes are never touched */
+    fn test_restore_backup(&mut self, i: usize) {
+        let backup = &self.backups[i];
+        if self.is_test_restoring(&backup.id) {
+            return;
         }
+        let Some(url) = backup.test_restore_url.clone() else {
+            return;
+        };
 
-        let (_tx, rx) = std::sync::mpsc::channel();
+        let newest = backup
+            .logs
+            .iter()
+            .enumerate()
+            .filter_map(|(j, entry)| DateTime::parse_from_rfc3339(&entry.timestamp).ok().map(|timestamp| (timestamp, j)))
+            .max_by_key(|(timestamp, _)| *timestamp)
+            .map(|(_, j)| j);
+        let Some(j) = newest else {
+            let message = format!("Skipped the test restore of {}, there is no restore point yet", backup.description);
+            self.add_internal_log(LogLevel::Warn, message);
+            return;
+        };
 
-        let mut app = Self {
-            uptime_url_settings: config.url_uptime_settings,
-            internal_log: vec![],
-            warning_settings: config.warning_settings,
-            uptime_urls: config.urls,
-            backups,
-            token: config.token,
-            secret: config.secret,
-            jwt_expiry: config.jwt_expiry,
-            payload: config.payload,
-            backup_enabled: false,
-            backup_trigger_rx: rx,
-            smtp_config: config.smtp,
-            uptime_fails: 0,
-            warnings_sent: 0,
+        let id = backup.id.clone();
+        let filename = backup.logs[j].filename.clone();
+        let path = self.restore_point_path(i, j);
+        let archive = backup.logs[j].archive.as_deref().and_then(ArchiveFormat::parse);
+        let staging = BackupEntry {
+            restore: RestoreTargets::One(url),
+            logs: Vec::new(),
+            ..backup.clone()
         };
+        let token = self.bearer_token();
+        let http = self.http_settings.clone();
+        let test_restore_tx = self.test_restore_tx.clone();
 
-        app.import_internal_log();
+        info!("Starting the test restore of {} from {}", filename, staging.description);
+        self.test_restoring.push((id.clone(), filename.clone()));
 
-        Ok(app)
+        thread::spawn(move || {
+            let result = test_restore(&staging, &path, archive, &token, &http).map_err(|e| e.to_string());
+            let _ = test_restore_tx.send(TestRestoreOutcome { id, filename, result });
+        });
     }
 
-    fn attempt_backup(&mut self, i: usize) {
-        println!("Attempting backup of {}", self.backups[i].url);
-
-        let save_path = &self.backups[i].description;
+    fn is_test_restoring(&self, id: &str) -> bool {
+        self.test_restoring.iter().any(|(running, _)| running == id)
+    }
 
-        let token = "";
+    /** logs finished test restores and warns about the ones that failed */
+    fn process_test_restores(&mut self) {
+        let outcomes: Vec<TestRestoreOutcome> = self.test_restore_rx.try_iter().collect();
 
-        let backup_attempt = download_file(&self.backups[i].url, save_path, token);
+        for outcome in outcomes {
+            self.test_restoring.retain(|(id, _)| *id != outcome.id);
+            let Some(i) = self.backups.iter().position(|backup| backup.id == outcome.id) else {
+                continue;
+            };
+            let description = self.backups[i].description.clone();
 
-        match backup_attempt {
-            Ok(filename) => {
-                println!("It worked: {}", filename);
+            match outcome.result {
+                Ok(done) => {
+                    let message = format!("Test restore of {} from {} passed: {}", outcome.filename, description, done);
+                    self.add_internal_log(LogLevel::Info, message.clone());
+                    self.backups[i].test_restore = Some((true, message));
+                }
+                Err(err) => {
+                    let message = format!("Test restore of {} from {} failed: {}", outcome.filename, description, err);
+                    error!("{}", message);
+                    self.add_internal_log(LogLevel::Error, message.clone());
+                    self.backups[i].test_restore = Some((false, message.clone()));
+
+                    let warning = Warning {
+                        category: AlertCategory::Backup,
+                        subject: format!("Backup test restore failed: {}", description),
+                        body: message.clone(),
+                        description: message,
+                        logs: self.recent_log_lines(),
+                    };
+                    self.send_backup_warning(i, &warning);
+                }
+            }
+        }
+    }
 
-                let _ = add_to_backup_log(&filename, &self.backups[i].description);
+    /** runs every backup scheduled in one of the given minutes, each at most once */
+    fn auto_backup(&mut self, ticks: &[DateTime<Utc>]) {
+        let mut to_backup = Vec::new();
 
-                // Re-read logs after successful backup
-                match load_log(&save_path) {
-                    Ok(log) => {
-                        self.backups[i].logs = log.entries;
+        for (i, backup) in self.backups.iter().enumerate() {
+            if !backup.enabled {
+                continue;
+            }
 
-                        let filename = self.backups[i].description.clone();
+            for tick in ticks.iter().filter(|tick| is_backup_due(backup, tick, self.timezone)) {
+                if self.in_enable_grace(*tick) {
+                    info!(
+                        "Skipping scheduled backup of {}, the schedule was enabled less than {} minutes ago",
+                        backup.description, self.backup_enable_grace_minutes
+                    );
+                } else {
+                    to_backup.push(i);
+                    break;
+                }
+            }
+        }
 
-                        println!("Trying to remove: {}", filename);
+        // spread backups over the minute so they don't all hit the server at once. Only for the
+        // current minute, missed minutes being caught up on run right away
+        let minute = minute_start(Utc::now());
+        let is_current = ticks.last().is_some_and(|tick| minute_start(*tick) == minute);
+
+        let mut planned: Vec<(u32, usize)> = to_backup
+            .into_iter()
+            .map(|i| {
+                let jitter = self.backups[i].jitter_seconds.unwrap_or(self.backup_jitter_seconds).min(59);
+                let offset = if is_current && jitter > 0 { random_below(jitter + 1) } else { 0 };
+                (offset, i)
+            })
+            .collect();
+        planned.sort();
 
-                        self.remove_backups_over_limit(&filename);
-                    }
-                    Err(err) => {
-                        println!("Could not reload log after backup: {}", err);
-                        self.backups[i].logs = vec![];
-                    }
-                }
+        for (offset, i) in planned {
+            if offset > 0 {
+                debug!("Backup of {} starts {} s into the minute", self.backups[i].description, offset);
             }
-            Err(err) => {
+            self.queue_backup(i, minute + chrono::Duration::seconds(offset as i64));
+        }
+    }
 
-                let error_message = format!("Backup failed for URL: {}. Error: {}", self.backups[i].url, err);
-                println!("{}", error_message);
-                self.internal_log.push(InternalLogEntry {
-                    message: error_message.clone(),
-                    timestamp: Utc::now().to_rfc3339(),
-                });
+    /** whether a tick falls within backup_enable_grace_minutes of enabling the schedule */
+    fn in_enable_grace(&self, tick: DateTime<Utc>) -> bool {
+        self.backup_enabled_at.is_some_and(|enabled_at| {
+            tick < enabled_at + chrono::Duration::minutes(self.backup_enable_grace_minutes as i64)
+        })
+    }
 
-                // Save the internal log after adding the new entry
+    /** queues a backup to run as soon as a slot is free and `not_before` has passed. A backup
+    that is already queued or running isn't queued twice */
+    fn queue_backup(&mut self, i: usize, not_before: DateTime<Utc>) {
+        if self.is_backup_pending(&self.backups[i].id) {
+            debug!("{} is already queued or running", self.backups[i].description);
+            return;
+        }
 
-                print_to_internal_log_file(InternalLog {
-                    entries: self.internal_log.clone(),
-                });
+        self.backup_queue.push(QueuedBackup {
+            id: self.backups[i].id.clone(),
+            not_before,
+        });
+    }
 
+    fn is_backup_pending(&self, id: &str) -> bool {
+        self.running_backups.iter().any(|running| running == id)
+            || self.backup_queue.iter().any(|queued| queued.id == id)
+    }
 
+    /** handles finished backups and starts queued ones while fewer than max_concurrent_backups run */
+    fn process_backup_queue(&mut self) {
+        let finished: Vec<BackupOutcome> = self.backup_outcome_rx.try_iter().collect();
+        let mut changed = !finished.is_empty();
+        for outcome in finished {
+            self.running_backups.retain(|id| *id != outcome.id);
+            let (id, succeeded) = (outcome.id.clone(), outcome.result.is_ok());
+            self.finish_backup(outcome);
+            self.restore_after_backup(&id, succeeded);
+        }
 
-                let mut has_sent_warning = false;
-                let is_over_daily_limit = self.warnings_sent >= self.warning_settings.daily_max;
+        if self.shutting_down.load(Ordering::SeqCst) {
+            return;
+        }
 
-                if is_over_daily_limit {
-                    self.internal_log.push(InternalLogEntry {
-                        message: "Warning limit exceeded".to_string(),
-                        timestamp: Utc::now().to_rfc3339(),
-                    });
-    
-                    print_to_internal_log_file(InternalLog {
-                        entries: self.internal_log.clone(),
-                    });
-    
-                }
+        let now = Utc::now();
+        while self.running_backups.len() < self.max_concurrent_backups.max(1) {
+            let Some(next) = self.backup_queue.iter().position(|queued| queued.not_before <= now) else {
+                break;
+            };
+            let queued = self.backup_queue.remove(next);
+            self.start_backup(&queued.id);
+            if !self.running_backups.contains(&queued.id) {
+                // skipped before it started (no folder, low disk space), that counts as failed
+                self.restore_after_backup(&queued.id, false);
+            }
+            changed = true;
+        }
 
-                
-                if self.warning_settings.use_email && !is_over_daily_limit  {
+        if changed {
+            self.publish_status();
+        }
+    }
 
+    /** stops scheduling, waits for running backups and writes the internal log. Used when the
+    window closes and when a headless instance is stopped */
+    fn shut_down(&mut self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
 
-                        has_sent_warning = true;
+        if !self.backup_queue.is_empty() {
+            info!("Shutting down, skipping {} queued backups", self.backup_queue.len());
+        }
 
+        // let running backups finish, so they are logged and rotated like any other, but don't
+        // keep the app from closing because of a slow download
+        if !self.running_backups.is_empty() {
+            info!("Waiting for {} running backups to finish", self.running_backups.len());
+        }
+        let deadline = Instant::now() + SHUTDOWN_BACKUP_WAIT;
+        while !self.running_backups.is_empty() {
+            let Ok(outcome) = self.backup_outcome_rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) else {
+                break;
+            };
+            self.running_backups.retain(|id| *id != outcome.id);
+            self.finish_backup(outcome);
+        }
+        if !self.running_backups.is_empty() {
+            let message = format!(
+                "Shut down without waiting longer than {} s for running backups: {}",
+                SHUTDOWN_BACKUP_WAIT.as_secs(),
+                self.running_backups.join(", ")
+            );
+            warn!("{}", message);
+            push_internal_log(&mut self.internal_log, InternalLogEntry::new(LogLevel::Warn, message));
+        }
 
-                    println!("Sending backup failure warning email...");
-                    let smtp = &self.smtp_config;
-                    let email_result = try_to_send_email(
-                        &self.warning_settings.email,
-                        "Backup failed",
-                        &error_message,
-                        smtp,
-                    );
-                    match email_result {
-                        Ok(_) => println!("Warning email sent successfully!"),
-                        Err(e) => println!("Failed to send warning email: {}", e),
-                    }
-                }
+        push_internal_log(
+            &mut self.internal_log,
+            InternalLogEntry::new(LogLevel::Info, "WebSync Station shut down".to_string()),
+        );
 
-                if self.warning_settings.send_post_request && !is_over_daily_limit {
+        self.save_internal_log();
+    }
 
+    /** from_config with its own minute ticker, for running without a window */
+    fn headless() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut app = Self::from_config()?;
+        app.backup_trigger_rx = spawn_ticker(app.shutting_down.clone());
+        Ok(app)
+    }
 
-                        has_sent_warning = true;
-                    
+    /** the same work update() does every frame, until shutting_down is set */
+    fn run_headless(&mut self) {
+        self.add_internal_log(LogLevel::Info, "WebSync Station started without a window".to_string());
 
+        while !self.shutting_down.load(Ordering::SeqCst) {
+            let ticks: Vec<DateTime<Utc>> = self.backup_trigger_rx.try_iter().collect();
+            if !ticks.is_empty() {
+                self.handle_ticks(&ticks);
+            }
 
-                     let log_lines: Vec<String> = self.internal_log
-                        .iter()
-                        .rev()
-                        .take(50)
-                        .map(|entry| format!("{} - {}", entry.timestamp, entry.message))
-                        .collect();
+            if self.run_on_start_pending {
+                self.run_on_start_pending = false;
+                self.run_on_start();
+            }
 
-                    let warning_payload = json!({
-                        "time": Utc::now().to_rfc3339(),
-                        "description": error_message, // Use the detailed error message
-                        "logs": log_lines
-                    });
-                    let json_string = warning_payload.to_string();
-                    
-                    // Reuse token logic from above or re-evaluate if needed for this specific POST
-                    // For simplicity, let's assume the same token logic applies.
-                    let post_token = if self.token.is_empty() {
-                        create_jwt(&self.payload, &self.secret, &self.jwt_expiry).unwrap_or_default()
-                    } else {
-                        self.token.clone()
-                    };
+            self.process_backup_queue();
+            self.process_integrity_reports();
+            self.process_test_restores();
+            self.process_alert_test_results();
+            self.process_uptime_results();
 
-                    for route_url in &self.warning_settings.post_request_routes {
-                        match send_warning_post_request(&post_token, &json_string, route_url) {
-                            Ok(_) => println!("Successfully sent POST warning for backup failure to {}", route_url),
-                            Err(e) => println!("Failed to send POST warning for backup failure to {}: {}", route_url, e),
-                        }
-                    }
-                }
+            thread::sleep(Duration::from_millis(500));
+        }
 
+        self.shut_down();
+    }
 
-                if has_sent_warning{
-                    self.warnings_sent += 1;
+    /** one uptime sweep, plus every backup that has missed its schedule when backups are enabled */
+    fn run_on_start(&mut self) {
+        self.add_internal_log(LogLevel::Info, "Running startup checks (run_on_start)".to_string());
+        self.uptime_check();
 
-                }
+        if !self.backup_enabled {
+            return;
+        }
 
+        let now = Utc::now();
+        let overdue: Vec<usize> = (0..self.backups.len())
+            .filter(|&i| self.backups[i].enabled && is_backup_overdue(&self.backups[i], now, self.timezone))
+            .collect();
 
+        for i in overdue {
+            if self.shutting_down.load(Ordering::SeqCst) {
+                break;
             }
+            info!("{} missed its schedule, backing up now", self.backups[i].description);
+            self.queue_backup(i, now);
         }
     }
 
-    fn remove_backups_over_limit(&mut self, description: &str) {
-        for backup in &mut self.backups {
-            if backup.description == description {
-                let number_over_limit = backup.logs.len() as i32 - backup.max as i32;
-
-                if number_over_limit > 0 {
-                    println!("There are {} backups over limit", number_over_limit);
+    fn uptime_check(&mut self) {
+        let all_urls: Vec<usize> = (0..self.uptime_urls.len()).collect();
+        self.uptime_check_urls(&all_urls);
+    }
 
-                    let mut j = 0;
+    /** indices of the URLs that are down. Invalid ones are never checked, so they don't count */
+    fn failing_urls(&self) -> Vec<usize> {
+        (0..self.uptime_urls.len())
+            .filter(|&i| !self.uptime_urls[i].is_ok && self.uptime_urls[i].invalid.is_none())
+            .collect()
+    }
 
-                    loop {
-                        if j >= number_over_limit || j > 5 {
-                            break;
-                        }
+    /** checks the given URLs on worker threads, at most uptime_concurrency at a time. Warnings
+    are evaluated for all URLs once every result is in. Does nothing while a check is running */
+    fn uptime_check_urls(&mut self, indices: &[usize]) {
+        if self.uptime_sweep.is_some() {
+            debug!("An uptime check is still running, not starting another one");
+            return;
+        }
 
-                        let filename = &backup.logs[0].filename;
+        let mut http = self.http_settings.clone();
+        if self.uptime_url_settings.fail_on_redirect {
+            http.follow_redirects = FollowRedirects::Enabled(false);
+        }
 
-                        let delete_attempt = delete_file(&filename, &backup.description);
+        let jobs: std::collections::VecDeque<UptimeJob> = indices
+            .iter()
+            .filter(|&&i| self.uptime_urls[i].invalid.is_none())
+            .map(|&i| {
+                let entry = &self.uptime_urls[i];
+                UptimeJob {
+                    index: i,
+                    url: entry.url.clone(),
+                    auth: basic_auth(&entry.username, &entry.password)
+                        .map(|(username, password)| (username.to_string(), password.to_string())),
+                    query_token: entry.token_query_param.as_ref().map(|param| (param.clone(), self.bearer_token())),
+                    expect_final_url: entry.expect_final_url.clone(),
+                }
+            })
+            .collect();
 
-                        match delete_attempt {
-                            Ok(()) => {
-                                println!("file delete success");
+        if jobs.is_empty() {
+            self.finish_uptime_sweep();
+            return;
+        }
 
-                                //remove the first log entry
-                                backup.logs.remove(0);
+        let (results_tx, results_rx) = std::sync::mpsc::channel();
+        let (online_tx, online_rx) = std::sync::mpsc::channel();
+        let total = jobs.len();
+        let workers = self.uptime_url_settings.concurrency().min(total);
+        let jobs = Arc::new(std::sync::Mutex::new(jobs));
+        let connectivity_urls = self.connectivity_check_urls();
+        let probe_http = self.http_settings.clone();
+
+        thread::spawn(move || {
+            // every URL would fail while the host is offline, so they are only checked once it is online
+            let online = probe_connectivity(&connectivity_urls, &probe_http);
+            if online_tx.send(online).is_err() || !online {
+                return;
+            }
 
-                                //save the log file again
-                                let log_path = Path::new(&backup.description).join("log.toml");
-                                let log = Log {
-                                    entries: backup.logs.clone(),
-                                };
-                                if let Ok(toml_str) = toml::to_string(&log) {
-                                    // ignore write errors here; handle them if you care
-                                    let _ = write(&log_path, toml_str);
-                                } else {
-                                    println!("Failed to write log file!");
-                                }
-                            }
-                            // Err(err) => println!("file delete fail{}: {}", err),
-                            Err(err) => println!("file delete fail: {}", err),
+            // each worker takes the next URL when it is done with one, so a few slow URLs don't
+            // hold up the rest and never more than `workers` requests are open
+            for _ in 0..workers {
+                let jobs = jobs.clone();
+                let results_tx = results_tx.clone();
+                let http = http.clone();
+
+                thread::spawn(move || {
+                    while let Some(job) = jobs.lock().ok().and_then(|mut jobs| jobs.pop_front()) {
+                        let auth = job.auth.as_ref().map(|(username, password)| (username.as_str(), password.as_str()));
+                        let query_token = job.query_token.as_ref().map(|(param, token)| (param.as_str(), token.as_str()));
+
+                        let result = send_request(&job.url, auth, query_token, job.expect_final_url.as_deref(), &http)
+                            .map_err(|e| e.to_string());
+                        if results_tx.send(UptimeResult { index: job.index, url: job.url, result }).is_err() {
+                            break;
                         }
-
-                        j += 1;
                     }
-                }
+                });
             }
-        }
+        });
+
+        self.uptime_sweep = Some(UptimeSweep { total, done: 0, results_rx, online_rx, probed: false });
     }
-}
 
-#[derive(Deserialize)]
-struct Config {
-    url_uptime_settings: UptimeUrlSettings,
-    warning_settings: WarningSettings,
-    #[serde(default)] // If "urls" is missing, it will default to an empty Vec<UrlEntry>
-    urls: Vec<UrlEntry>,
-    #[serde(default)] // If "backups" is missing, it will default to an empty Vec<BackupEntry>
-    backups: Vec<BackupEntry>,
-    token: String,
-    secret: String,
-    jwt_expiry: u64,
-    #[serde(default)] // For HashMap, default is an empty map
-    payload: HashMap<String, TomlValue>,
-    smtp: SmtpConfig,
-}
+    /** applies the results of the running uptime check that have come in, and evaluates
+    warnings once it is complete */
+    fn process_uptime_results(&mut self) {
+        let Some(sweep) = &mut self.uptime_sweep else {
+            return;
+        };
 
+        if !sweep.probed {
+            let online = match sweep.online_rx.try_recv() {
+                Ok(online) => online,
+                Err(std::sync::mpsc::TryRecvError::Empty) => return,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => true, // the probe died, don't claim offline
+            };
+            sweep.probed = true;
+
+            self.apply_connectivity(online);
+            if !online {
+                // every URL would fail, the offline notice covers it
+                self.uptime_sweep = None;
+                self.publish_status();
+                return;
+            }
+        }
 
+        let Some(sweep) = &mut self.uptime_sweep else {
+            return;
+        };
+        let mut results = Vec::new();
+        let mut workers_gone = false;
+        loop {
+            match sweep.results_rx.try_recv() {
+                Ok(result) => results.push(result),
+                Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    workers_gone = true; // a worker that died won't send its results
+                    break;
+                }
+            }
+        }
+        sweep.done += results.len();
+        let complete = workers_gone || sweep.done >= sweep.total;
 
-fn main() -> eframe::Result<()> {
+        for result in results {
+            self.apply_uptime_result(result);
+        }
 
+        if complete {
+            self.uptime_sweep = None;
+            self.finish_uptime_sweep();
+            self.publish_status();
+        }
+    }
 
-    let config_path = Path::new("config.toml");
-    let app_config_result = load_config();
+    fn apply_uptime_result(&mut self, result: UptimeResult) {
+        let i = match self.uptime_urls.get(result.index) {
+            Some(entry) if entry.url == result.url => result.index,
+            _ => match self.uptime_urls.iter().position(|entry| entry.url == result.url) {
+                Some(i) => i,
+                None => return, // no longer monitored
+            },
+        };
 
-    if app_config_result.is_err() {
-        eprintln!(
-            "Warning: Could not load 'config.toml': {}",
-            app_config_result.as_ref().err().unwrap() // Show the error
-        );
+        let was_down = self.uptime_urls[i].down_since.is_some();
+        let failed_checks = self.uptime_urls[i].failed_checks;
 
-        if !config_path.exists() {
-            eprintln!("'config.toml' not found. Attempting to create a default one.");
-            match write(config_path, default_config::DEFAULT_CONFIG_TOML) {
-                Ok(_) => {
-                    eprintln!("Successfully created 'config.toml' with default settings.");
-                    eprintln!("Please review and edit 'config.toml' then restart the application.");
-                    return Ok(());
-                }
-                Err(e) => {
-                    eprintln!("Error: Could not write default 'config.toml': {}", e);
+        match result.result {
+            Ok(cert_expires) => {
+                if was_down && self.uptime_urls[i].digest {
+                    let description = self.uptime_urls[i].description.clone();
+                    self.add_to_digest(format!("{} is back up after {} failed checks", description, failed_checks));
                 }
+                self.uptime_urls[i].is_ok = true;
+                self.uptime_urls[i].down_since = None;
+                self.uptime_urls[i].cert_expires = cert_expires;
+                self.uptime_urls[i].outage_started = None;
+                self.uptime_urls[i].failed_checks = 0;
+                self.uptime_urls[i].escalation_level = 0;
+            }
+            Err(_err) => {
+                self.uptime_urls[i].is_ok = false;
+                self.uptime_urls[i].down_since.get_or_insert_with(Utc::now);
+                self.uptime_urls[i].outage_started.get_or_insert_with(Utc::now);
+                self.uptime_urls[i].failed_checks += 1;
+
+                let message = if self.uptime_urls[i].muted {
+                    format!("{} is down (alerts muted)", self.uptime_urls[i].description)
+                } else if self.uptime_urls[i].digest {
+                    if !was_down {
+                        let description = self.uptime_urls[i].description.clone();
+                        self.add_to_digest(format!("{} went down", description));
+                    }
+                    format!("{} is down (reported in the digest)", self.uptime_urls[i].description)
+                } else {
+                    self.uptime_fails += 1;
+                    format!("{} is down", self.uptime_urls[i].description)
+                };
+                push_internal_log(&mut self.internal_log, InternalLogEntry::new(LogLevel::Error, message));
+
+                self.save_internal_log();
+
             }
-        } else {
-            eprintln!("'config.toml' exists but is malformed. Please fix it or delete it to generate a default.");
         }
+
+        let today = Utc::now().date_naive();
+        self.uptime_stats.record(&self.uptime_urls[i].url, self.uptime_urls[i].is_ok, today);
     }
- 
 
+    /** stats, certificate expiry and the down/escalation/recovery warnings after an uptime check */
+    fn finish_uptime_sweep(&mut self) {
+        let url_length = self.uptime_urls.len();
 
-    let options = eframe::NativeOptions {
-        viewport: ViewportBuilder::default().with_inner_size(Vec2::new(800.0, 600.0)),
-        ..Default::default()
-    };
+        self.uptime_stats.prune(Utc::now().date_naive(), self.uptime_url_settings.stats_window_days());
+        if let Err(e) = self.uptime_stats.save() {
+            error!("Failed to write stats file: {}", e);
+        }
 
-    eframe::run_native(
-        "WebSync Station",
-        options,
-        Box::new(|_cc| {
-            let mut app = StatusChecker::from_config().unwrap_or_else(|err| {
-                eprintln!("Failed to load config: {}", err);
-                StatusChecker::default()
-            });
+        self.check_cert_expiry();
 
+        let tolerance_exceeded = match self.uptime_url_settings.downtime_tolerance_minutes {
+            Some(tolerance_minutes) => {
+                let now = Utc::now();
+                self.uptime_urls.iter().filter(|entry| entry.alerts()).any(|entry| match entry.down_since {
+                    Some(since) => (now - since).num_minutes() >= tolerance_minutes as i64,
+                    None => false,
+                })
+            }
+            None => self.uptime_fails > self.uptime_url_settings.downtime_tolerance,
+        };
 
+        if tolerance_exceeded {
+            let mut message_for_email = "Uptime check failed for the following URLs:\n".to_string();
+            let mut failed_url_descriptions = Vec::new();
 
-            if app.internal_log.is_empty(){
-                app.internal_log.push(InternalLogEntry {
-                    message: "Welcome to WebSync Station. If this is your first time using WWS remember to edit the config.toml file and then restart the app.".to_string(),
-                    timestamp: Utc::now().to_rfc3339(),
-                });
+            for i in 0..url_length {
+                if !self.uptime_urls[i].is_ok && self.uptime_urls[i].invalid.is_none() && self.uptime_urls[i].alerts() {
+                    message_for_email.push_str(&format!("{}\n", self.uptime_urls[i].description));
+                    failed_url_descriptions.push(self.uptime_urls[i].description.clone());
+                }
             }
 
+            let log_lines = self.recent_log_lines();
+
+            message_for_email.push_str(&format!(
+                "\nThese are the last {} lines of the internal log:\n{}",
+                log_lines.len(),
+                join_with_line_breaks(log_lines.clone()) // Clone for email
+            ));
 
-            let (tx, rx) = std::sync::mpsc::channel();
-            app.backup_trigger_rx = rx;
+            let warning = Warning {
+                category: AlertCategory::Uptime,
+                subject: "Uptime check failed".to_string(),
+                body: message_for_email,
+                description: format!("Uptime check failed. URLs down: {}", failed_url_descriptions.join(", ")),
+                logs: log_lines,
+            };
 
-            thread::spawn(move || {
-                loop {
-                    let now = Utc::now();
-                    let next_min = (now.minute() + 1) % 60; // wraps 59→0
-                    let next_time = NaiveTime::from_hms_opt(now.hour(), next_min, 0)
-                        .expect("hour/minute within valid range");
-                    let next_tick = now
-                        .date_naive()
-                        .and_time(next_time)
-                        .and_local_timezone(Utc)
-                        .unwrap();
-
-                    let sleep_dur = (next_tick - now)
-                        .to_std()
-                        .unwrap_or_else(|_| Duration::from_secs(60));
-
-                    thread::sleep(sleep_dur);
-
-                    // 4) poke the UI
-                    if tx.send(()).is_err() {
-                        break; // if the receiver was dropped, exit the loop
+            if self.send_limited_warning(&warning) {
+                for entry in &mut self.uptime_urls {
+                    if !entry.is_ok && entry.invalid.is_none() && entry.alerts() {
+                        entry.alerted_down = true;
                     }
                 }
-            });
+            }
 
-            Box::new(app)
-        }),
-    )
-}
+            self.uptime_fails = 0; // Reset fails after warnings are sent
 
-impl eframe::App for StatusChecker {
-    //this runs several times a second
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            egui::ScrollArea::vertical().show(ui, |ui| {
-                while let Ok(()) = self.backup_trigger_rx.try_recv() {
+            // restart the downtime window so a URL that stays down is not reported on every check
+            let now = Utc::now();
+            for entry in &mut self.uptime_urls {
+                if entry.down_since.is_some() {
+                    entry.down_since = Some(now);
+                }
+            }
+        }
 
-                    let current_time = Utc::now();
-                    let minute = current_time.minute();
-                    let hour = current_time.hour() * 60;
-                    let total_minutes = hour + minute;
+        self.escalate_outages();
 
-                    if minute == 0 && hour == 0{
+        // close the loop on URLs we warned about, these do not count against daily_max
+        let recovered: Vec<String> = self
+            .uptime_urls
+            .iter_mut()
+            .filter(|entry| entry.is_ok && entry.alerted_down)
+            .map(|entry| {
+                entry.alerted_down = false;
+                entry.description.clone()
+            })
+            .collect();
+
+        if !recovered.is_empty() {
+            let message = format!("RESOLVED: {} is back up", recovered.join(", "));
+            self.add_internal_log(LogLevel::Info, message.clone());
+
+            let warning = Warning {
+                category: AlertCategory::Uptime,
+                subject: format!("RESOLVED: {} is back up", recovered.join(", ")),
+                body: format!("The following URLs are reachable again:\n{}", join_with_line_breaks(recovered)),
+                description: message,
+                logs: self.recent_log_lines(),
+            };
 
-                        // Reset the warnings sent counter at the start of a new day
-                        self.warnings_sent = 0;
+            if self.alerts_muted_reason().is_none() {
+                self.dispatch_warning(&warning);
+            }
+        }
+    }
 
-                    }
-                    
+    /** sends the escalation steps a down URL has reached since the last check, through the
+    step's channels on top of the usual ones. Steps that couldn't be sent (muted, over the
+    daily limit) are tried again on the next check */
+    fn escalate_outages(&mut self) {
+        if self.warning_settings.escalation.is_empty() {
+            return;
+        }
 
+        let now = Utc::now();
 
-                    if self.backup_enabled {
-                        self.auto_backup();
-                    }
+        for i in 0..self.uptime_urls.len() {
+            let entry = &self.uptime_urls[i];
+            let Some(started) = entry.outage_started.filter(|_| entry.alerts()) else {
+                continue;
+            };
+            let minutes_down = (now - started).num_minutes();
 
+            let reached: Vec<usize> = (entry.escalation_level..self.warning_settings.escalation.len())
+                .filter(|&step| self.warning_settings.escalation[step].reached(entry.failed_checks, minutes_down))
+                .collect();
+            let Some(&last_step) = reached.last() else {
+                continue;
+            };
 
-                    if total_minutes % self.uptime_url_settings.interval_minutes == 0 {
-                        self.uptime_check();
+            let mut channels: Vec<&'static str> = Vec::new();
+            for &step in &reached {
+                for channel in &self.warning_settings.escalation[step].channels {
+                    if let Some(known) = ALERT_CHANNELS.iter().find(|known| *known == channel) {
+                        if !channels.contains(known) {
+                            channels.push(known);
+                        }
                     }
                 }
+            }
 
-                ctx.request_repaint_after(Duration::from_secs(1)); // keep UI responsive
+            let message = format!(
+                "ESCALATED: {} is still down after {} failed checks ({} minutes)",
+                entry.description, entry.failed_checks, minutes_down
+            );
+            self.add_internal_log(LogLevel::Error, message.clone());
+
+            let warning = Warning {
+                category: AlertCategory::Uptime,
+                subject: format!("ESCALATED: {} is still down", self.uptime_urls[i].description),
+                body: message.clone(),
+                description: message,
+                logs: self.recent_log_lines(),
+            };
 
-                // if ui.button("Test autobackup").clicked() {
-                //     self.auto_backup();
-                // }
+            if self.send_limited_warning_to(&warning, &channels) {
+                self.uptime_urls[i].escalation_level = last_step + 1;
+                self.uptime_urls[i].alerted_down = true;
+            }
+        }
+    }
 
-                // if ui.button("Test internal log").clicked() {
-                //     let entry = InternalLogEntry {
-                //         message: "Test message".to_string(),
-                //         timestamp: Utc::now().to_rfc3339(),
-                //     };
+    /** the URLs probe_connectivity tries, connectivity_check_urls or the defaults */
+    fn connectivity_check_urls(&self) -> Vec<String> {
+        match &self.uptime_url_settings.connectivity_check_urls {
+            Some(urls) => urls.clone(),
+            None => DEFAULT_CONNECTIVITY_CHECK_URLS.iter().map(|url| url.to_string()).collect(),
+        }
+    }
 
-                //     self.internal_log.push(entry);
+    /** takes the result of a connectivity probe that ran on a worker thread and raises a single
+    notice when the host goes offline or comes back */
+    fn apply_connectivity(&mut self, online: bool) {
+        match (online, self.offline_since) {
+            (false, None) => {
+                self.offline_since = Some(Utc::now());
+
+                // nothing can be sent while offline, the warning goes out once connectivity returns
+                let message = format!(
+                    "Host appears offline: none of {} answered. Per-URL alerts are paused until connectivity returns",
+                    self.connectivity_check_urls().join(", ")
+                );
+                warn!("{}", message);
+                self.add_internal_log(LogLevel::Error, message);
+            }
+            (true, Some(since)) => {
+                self.offline_since = None;
+
+                let message = format!(
+                    "Host was offline from {} to {}: none of {} answered",
+                    format_in_zone(since, self.display_timezone, "%Y-%m-%d %H:%M"),
+                    format_in_zone(Utc::now(), self.display_timezone, "%Y-%m-%d %H:%M"),
+                    self.connectivity_check_urls().join(", ")
+                );
+                info!("{}", message);
+                self.add_internal_log(LogLevel::Info, message.clone());
+
+                let warning = Warning {
+                    category: AlertCategory::Uptime,
+                    subject: "Host was offline".to_string(),
+                    body: message.clone(),
+                    description: message,
+                    logs: self.recent_log_lines(),
+                };
+                self.send_limited_warning(&warning);
+            }
+            _ => {}
+        }
+    }
 
-                //     print_to_internal_log_file(InternalLog {
-                //         entries: self.internal_log.clone(),
-                //     });
-                // }
+    /** the newest internal log lines, newest first, as included in warnings */
+    fn recent_log_lines(&self) -> Vec<String> {
+        self.internal_log
+            .iter()
+            .rev() // Reverse the order to get the latest entries first...
+            .take(self.warning_settings.warning_log_lines)
+            .map(|entry| format!("{} - {}", entry.timestamp, entry.text()))
+            .collect()
+    }
 
-                //This is inside the closure, and it is the main loop of the app.
+    fn add_internal_log(&mut self, level: LogLevel, message: String) {
+        push_internal_log(&mut self.internal_log, InternalLogEntry::new(level, message));
 
-                ui.heading("WebSync Station");
+        self.save_internal_log();
+    }
 
-                ui.add_space(10.0);
-                let url_length = self.uptime_urls.len();
+    /** the active maintenance window, if any */
+    fn active_maintenance(&self) -> Option<&MaintenanceWindow> {
+        let now = Utc::now();
+        self.maintenance_windows.iter().find(|window| window.is_active(now))
+    }
 
-                ui.horizontal(|ui| {
-                    ui.vertical(|ui| {
-                        let mut i = 0;
+    /** stops uptime checks and scheduled backups for `minutes`, manual actions still work */
+    fn pause_all(&mut self, minutes: u32) {
+        let until = Utc::now() + chrono::Duration::minutes(minutes as i64);
+        self.paused_until = Some(until);
+        self.add_internal_log(LogLevel::Info, format!(
+            "Uptime checks and backups paused until {}",
+            until.format("%H:%M UTC")
+        ));
+        self.publish_status();
+    }
 
-                        loop {
+    /** ends the pause early, or once it has run out */
+    fn expire_pause(&mut self) {
+        if self.paused_until.is_some_and(|until| until <= Utc::now()) {
+            self.resume_all("Pause ended");
+        }
+    }
 
-                            if i >= url_length {
-                                break;
-                            }
+    fn resume_all(&mut self, reason: &str) {
+        self.paused_until = None;
+        self.add_internal_log(LogLevel::Info, format!("{}, uptime checks and backups resumed", reason));
+        self.publish_status();
+    }
 
+    /** clears the snooze once it has run out */
+    fn expire_snooze(&mut self) {
+        if self.snooze_until.is_some_and(|until| until <= Utc::now()) {
+            self.snooze_until = None;
+            self.add_internal_log(LogLevel::Info, "Alert snooze ended".to_string());
+        }
+    }
 
-                            ui.horizontal(|ui| {
-                                // ui.add_space(ui.available_width() - 80.0); // push the button right
+    /** why warnings are currently not sent (maintenance window or snooze), if they are muted */
+    fn alerts_muted_reason(&mut self) -> Option<String> {
+        self.expire_snooze();
 
-                                ui.add_space(10.0);
+        if let Some(window) = self.active_maintenance() {
+            return Some(format!("Maintenance window \"{}\" active", window.description));
+        }
 
-                                let color = if self.uptime_urls[i].is_ok {
-                                    Color32::from_rgb(0, 200, 0) // Green
-                                } else {
-                                    Color32::from_rgb(200, 0, 0) // Red
-                                };
+        self.snooze_until
+            .map(|until| format!("Alerts snoozed until {}", until.format("%H:%M UTC")))
+    }
 
-                                let text = if self.uptime_urls[i].is_ok {
-                                    "✅"
-                                } else {
-                                    "❌"
-                                };
-                                let button = egui::Button::new(text).fill(color);
+    /** sends a warning through every enabled channel that is below its daily limit. Returns
+    true if it went out on at least one channel */
+    fn send_limited_warning(&mut self, warning: &Warning) -> bool {
+        let channels: Vec<&str> = ALERT_CHANNELS
+            .into_iter()
+            .filter(|channel| self.warning_settings.channel_enabled(warning.category, channel))
+            .collect();
 
-                                ui.add(button);
-                                ui.label(self.uptime_urls[i].description.to_string());
-                            });
+        self.send_limited_warning_to(warning, &channels)
+    }
 
-                            i += 1;
-                            if i >= url_length {
-                                break;
-                            };
-                        }
-                    });
+    /** send_limited_warning for a warning about backup i, which goes into the digest instead when
+    the backup has digest = true. Returns true once it is sent or in the digest */
+    fn send_backup_warning(&mut self, i: usize, warning: &Warning) -> bool {
+        if self.backups[i].digest {
+            self.add_to_digest(warning.description.clone());
+            return true;
+        }
 
-                    ui.add_space(10.0);
+        self.send_limited_warning(warning)
+    }
 
-                    Frame::none()
-                        .fill(Color32::from_rgb(30, 30, 30))
-                        .stroke(Stroke::new(1.0, Color32::WHITE))
-                        .rounding(Rounding::same(4.0))
-                        .inner_margin(Vec2::splat(6.0))
-                        .show(ui, |ui_frame| {
-                            let dynamic_content_width = ui_frame.available_width();
-                            let desired_scroll_area_size = egui::vec2(dynamic_content_width, 200.0);
+    /** keeps an event for the next digest, in state.toml so a restart doesn't lose it */
+    fn add_to_digest(&mut self, message: String) {
+        debug!("Adding to the digest: {}", message);
+        self.digest_events.push(DigestEvent {
+            timestamp: Utc::now().to_rfc3339(),
+            message,
+        });
+        self.save_state();
+    }
 
-                            ui_frame.allocate_ui_with_layout(
-                                desired_scroll_area_size,
-                                Layout::top_down(Align::Min),
-                                |ui_for_scroll_area| {
-                                    // Start building the ScrollArea
-                                    let scroll_area_builder = ScrollArea::vertical()
-                                        .auto_shrink([false, false])
-                                        .stick_to_bottom(true);
+    /** emails everything collected since the last digest. On failure the events are kept and
+    go out with the next one */
+    fn send_digest(&mut self) {
+        if self.digest_events.is_empty() {
+            info!("Nothing to report since the last digest, not sending one");
+            return;
+        }
 
-                                    // Now show the (potentially modified) ScrollArea
-                                    scroll_area_builder.show(
-                                        ui_for_scroll_area,
-                                        |ui_scroll_content| {
-                                            let interal_log_length = self.internal_log.len();
-                                            for i in 0..interal_log_length {
-                                                ui_scroll_content.add(
-                                                    Label::new(
-                                                        RichText::new(format!(
-                                                            "{} - {}",
-                                                            self.internal_log[i].timestamp,
-                                                            self.internal_log[i].message
-                                                        ))
-                                                        .monospace()
-                                                        .color(Color32::LIGHT_GREEN),
-                                                    )
-                                                    .wrap(true),
-                                                );
-                                            }
-                                        },
-                                    );
-                                },
-                            );
-                        });
-                });
+        let lines: Vec<String> = self
+            .digest_events
+            .iter()
+            .map(|event| format!("{} - {}", format_timestamp(&event.timestamp, self.display_timezone), event.message))
+            .collect();
+        let count = lines.len();
+
+        let subject = self.alert_subject(&format!("Digest: {} events", count));
+        let body = self.alert_body(&format!(
+            "Events from the URLs and backups with digest = true since the last digest:\n\n{}",
+            join_with_line_breaks(lines)
+        ));
+
+        match try_to_send_email(&self.warning_settings.email, &subject, &body, &self.smtp_config) {
+            Ok(()) => {
+                self.digest_events.clear();
+                self.save_state();
+                self.add_internal_log(
+                    LogLevel::Info,
+                    format!("Digest with {} events sent to {}", count, self.warning_settings.email),
+                );
+            }
+            Err(e) => {
+                let message = format!("Could not send the digest, its {} events go out with the next one: {}", count, e);
+                error!("{}", message);
+                self.add_internal_log(LogLevel::Error, message);
+            }
+        }
+    }
 
-                /* Add a button for manually checking all the URLs */
+    /** like send_limited_warning, but through the given channels */
+    fn send_limited_warning_to(&mut self, warning: &Warning, channels: &[&str]) -> bool {
+        if let Some(reason) = self.alerts_muted_reason() {
+            self.add_internal_log(LogLevel::Warn, format!("{}, not sending warning: {}", reason, warning.subject));
+            return false;
+        }
 
-                ui.add_space(10.0);
+        // a flapping service would otherwise send a burst of warnings before daily_max stops it
+        let cooldown = chrono::Duration::minutes(self.warning_settings.warning_cooldown_minutes as i64);
+        if let Some(last_sent) = self.last_warning_sent.get(&warning.category) {
+            let next_allowed = *last_sent + cooldown;
+            if Utc::now() < next_allowed {
+                self.add_internal_log(LogLevel::Warn, format!(
+                    "Warning cooldown until {}, not sending warning: {}",
+                    format_in_zone(next_allowed, self.display_timezone, "%H:%M"),
+                    warning.subject
+                ));
+                return false;
+            }
+        }
 
-                if ui.button("Manually check all urls").clicked() {
-                    self.uptime_check();
-                }
+        let sent_warning = self.render_warning(warning);
+        let mut has_sent_warning = false;
 
-                //for testing and making the compliler shut up...
+        for &channel in channels {
+            // each channel has its own budget, so a noisy webhook can't use up the emails
+            let sent = self.warnings_sent.get(channel).copied().unwrap_or(0);
+            if sent >= self.warning_settings.daily_max_for(channel) {
+                self.add_internal_log(LogLevel::Warn, format!("Warning limit exceeded for {}", channel));
+                continue;
+            }
 
-                // let jwt_string: String;
+            self.deliver_warning(&sent_warning, channel);
+            *self.warnings_sent.entry(channel.to_string()).or_default() += 1;
+            has_sent_warning = true;
+        }
 
-                // if ui.button("Click to make JWT").clicked() {
-                //     let jwt_result = create_jwt(&self.payload, &self.secret, &self.jwt_expiry);
+        if has_sent_warning {
+            self.last_warning_sent.insert(warning.category, Utc::now());
+            self.last_sent_warning = Some(sent_warning);
+        }
 
-                //     match jwt_result {
-                //         Ok(jwt) => {
-                //             jwt_string = jwt;
-                //         }
+        has_sent_warning
+    }
 
-                //         Err(_err) => {
-                //             jwt_string = String::from("error");
-                //         }
-                //     }
+    /** sends a warning through every enabled channel, returns false if none are enabled */
+    fn dispatch_warning(&mut self, warning: &Warning) -> bool {
+        let sent_warning = self.render_warning(warning);
+        let mut has_sent_warning = false;
 
-                //     println!("{}", &jwt_string)
-                // }
+        for channel in ALERT_CHANNELS {
+            if self.warning_settings.channel_enabled(warning.category, channel) {
+                self.deliver_warning(&sent_warning, channel);
+                has_sent_warning = true;
+            }
+        }
 
-                ui.add_space(10.0);
+        if has_sent_warning {
+            self.last_sent_warning = Some(sent_warning);
+        }
 
-                ui.separator();
+        has_sent_warning
+    }
 
-                ui.add_space(10.0);
+    /** fills in the instance details, so every channel (and a replay) sends the same content */
+    fn render_warning(&self, warning: &Warning) -> SentWarning {
+        SentWarning {
+            category: warning.category,
+            subject: self.alert_subject(&warning.subject),
+            body: self.alert_body(&warning.body),
+            description: warning.description.clone(),
+            payload: self.alert_payload(&warning.description, &warning.logs),
+            sent_at: Utc::now(),
+        }
+    }
 
-                ui.horizontal(|ui| {
-                    ui.heading("Backup system");
+    /** sends a warning through one channel and logs the outcome. POST routes are sent on their
+    own thread, a slow or rate limiting webhook would otherwise hold up the app */
+    fn deliver_warning(&self, warning: &SentWarning, channel: &str) {
+        if channel == "post" {
+            self.post_in_background(&warning.payload, |results| log_delivery(&results));
+        } else {
+            log_delivery(&self.send_to_channel(warning, channel));
+        }
+    }
 
-                    let enable_caption = if self.backup_enabled {
-                        "Disable backup schedule"
-                    } else {
-                        "Enable backup schedule"
-                    };
+    /** POSTs a payload to every post_request_routes on a worker thread and hands (success,
+    message) for each route to `done` there */
+    fn post_in_background(&self, payload: &JsonValue, done: impl FnOnce(Vec<(bool, String)>) + Send + 'static) {
+        let routes = self.warning_settings.post_request_routes.clone();
+        let json_string = payload.to_string();
+        let http = self.http_settings.clone();
+
+        // Proceed even if the token is empty, as the server might not require auth
+        // or an empty Bearer token might be acceptable in some scenarios.
+        // If a token is absolutely required and JWT creation fails, this will likely fail at the server.
+        let token_to_use = self.bearer_token();
+        let shutting_down = self.shutting_down.clone();
+
+        thread::spawn(move || {
+            let results = routes
+                .iter()
+                .map(|route_url| match send_warning_post_request(&token_to_use, &json_string, route_url, &http, &shutting_down) {
+                    Ok(()) => (true, format!("Successfully sent POST warning to {}", route_url)),
+                    Err(e) => (false, format!("Failed to send POST warning to {}: {}", route_url, e)),
+                })
+                .collect();
+            done(results);
+        });
+    }
 
-                    if ui.button(enable_caption).clicked() {
-                        self.backup_enabled = !self.backup_enabled;
-                    }
+    /** sends a warning through the "email" or "desktop" channel. Returns (success, message) for
+    every email or notification, failures don't stop the others */
+    fn send_to_channel(&self, warning: &SentWarning, channel: &str) -> Vec<(bool, String)> {
+        let mut results = Vec::new();
+
+        if channel == "email" {
+            let email_result = try_to_send_email(
+                &self.warning_settings.email,
+                &warning.subject,
+                &warning.body,
+                &self.smtp_config,
+            );
+            results.push(match email_result {
+                Ok(_) => (true, format!("Warning email sent to {}", self.warning_settings.email)),
+                Err(e) => (false, format!("Failed to send warning email: {}", e)),
+            });
+        }
 
-                    let caption = if self.backup_enabled {
-                        RichText::new("Backup schedules enabled").color(Color32::GREEN)
-                    } else {
-                        RichText::new("Backup schedules disabled").color(Color32::RED)
-                    };
+        if channel == "desktop" {
+            results.push(match show_desktop_notification(&warning.subject, &warning.description) {
+                Ok(()) => (true, "Desktop notification shown".to_string()),
+                Err(e) => (false, format!("Failed to show desktop notification: {}", e)),
+            });
+        }
 
-                    ui.add_space(10.0);
+        results
+    }
 
-                    ui.label(caption);
-                });
+    /** sends the last warning again through the channels its category uses now, marked as a
+    replay and not counted against daily_max. For checking a fix on the receiving side */
+    fn replay_last_warning(&mut self) {
+        let Some(last) = &self.last_sent_warning else {
+            return;
+        };
 
-                ui.separator();
-                //Backup system ui
+        let sent_at = format_in_zone(last.sent_at, self.display_timezone, "%Y-%m-%d %H:%M");
+        let subject = last.subject.clone();
+        let mut payload = last.payload.clone();
+        if let JsonValue::Object(fields) = &mut payload {
+            fields.insert("replay".to_string(), JsonValue::Bool(true));
+            fields.insert("replayed_at".to_string(), JsonValue::String(Utc::now().to_rfc3339()));
+        }
+        let replay = SentWarning {
+            category: last.category,
+            subject: format!("REPLAY: {}", last.subject),
+            body: format!(
+                "This is a replay of a warning first sent at {}, nothing new has failed.\n\n{}",
+                sent_at, last.body
+            ),
+            description: format!("Replay: {}", last.description),
+            payload,
+            sent_at: last.sent_at,
+        };
 
-                let backup_length = self.backups.len();
-                let max_backups = 10;
-                // println!("There are {} backups", backup_length);
-                let mut i = 0;
+        let channels: Vec<&str> = ALERT_CHANNELS
+            .into_iter()
+            .filter(|channel| self.warning_settings.channel_enabled(replay.category, channel))
+            .collect();
+
+        self.alert_test_results = channels
+            .iter()
+            .filter(|channel| **channel != "post")
+            .flat_map(|channel| self.send_to_channel(&replay, channel))
+            .collect();
+        if channels.contains(&"post") {
+            let alert_test_tx = self.alert_test_tx.clone();
+            self.post_in_background(&replay.payload, move |results| {
+                let _ = alert_test_tx.send(results);
+            });
+        } else if self.alert_test_results.is_empty() {
+            self.alert_test_results
+                .push((false, "No channel is enabled for this kind of warning".to_string()));
+        }
 
-                loop {
-                    if i >= backup_length || i > max_backups {
-                        break;
-                    }
+        self.add_internal_log(LogLevel::Info, format!("Replayed the warning from {}: {}", sent_at, subject));
+    }
 
-                    let log_entries_length = self.backups[i].logs.len();
+    /** warns once per certificate about URLs whose TLS certificate expires within
+    cert_expiry_warn_days, even though the site itself is up */
+    fn check_cert_expiry(&mut self) {
+        let warn_days = self.uptime_url_settings.cert_warn_days();
+        if warn_days == 0 {
+            return;
+        }
 
-                    ui.horizontal(|ui| {
-                        ui.label(RichText::new(&self.backups[i].description).strong());
+        let now = Utc::now();
+        let mut expiring = Vec::new();
+        let mut digested = Vec::new();
 
-                        ui.add_space(10.0);
+        for (i, entry) in self.uptime_urls.iter_mut().enumerate() {
+            let Some(expires) = entry.cert_expires.filter(|_| !entry.muted) else {
+                continue;
+            };
+            let days_left = (expires - now).num_days();
+
+            if days_left >= warn_days as i64 {
+                entry.alerted_cert_expiry = false; // renewed, warn again about the next one
+            } else if entry.alerted_cert_expiry {
+                continue;
+            } else if entry.digest {
+                entry.alerted_cert_expiry = true;
+                digested.push(format!("TLS certificate of {} expires in {} days", entry.description, days_left.max(0)));
+            } else {
+                expiring.push((i, format!("{} ({} days left)", entry.description, days_left.max(0))));
+            }
+        }
 
-                        ui.label(format!("Restore points available: {}", log_entries_length));
-                        ui.add_space(10.0);
+        for message in digested {
+            self.add_to_digest(message);
+        }
 
-                        if ui.button("Backup manually now").clicked() {
-                            self.attempt_backup(i);
-                        };
-                    });
+        if expiring.is_empty() {
+            return;
+        }
+
+        let list: Vec<String> = expiring.iter().map(|(_, line)| line.clone()).collect();
+        self.add_internal_log(
+            LogLevel::Warn,
+            format!("TLS certificate expiring soon: {}", list.join(", ")),
+        );
+
+        let log_lines = self.recent_log_lines();
+        let warning = Warning {
+            category: AlertCategory::Uptime,
+            subject: "TLS certificate expiring soon".to_string(),
+            body: format!(
+                "The TLS certificate of the following URLs expires within {} days:\n{}\n\nThese are the last {} lines of the internal log:\n{}",
+                warn_days,
+                list.join("\n"),
+                log_lines.len(),
+                join_with_line_breaks(log_lines.clone())
+            ),
+            description: format!("TLS certificate expiring soon: {}", list.join(", ")),
+            logs: log_lines,
+        };
+
+        if self.send_limited_warning(&warning) {
+            for (i, _) in expiring {
+                self.uptime_urls[i].alerted_cert_expiry = true;
+            }
+        }
+    }
+
+    fn import_internal_log(&mut self) {
+        let path = self.internal_log_format.path();
+        let log = match load_internal_log(self.internal_log_format) {
+            Ok(log) => log,
+            Err(e) => {
+                if path.exists() {
+                    // entries are only ever appended, so a file that can't be read has to go aside
+                    let broken = path.with_extension("broken");
+                    error!("Could not read {}, moving it to {}: {}", path.display(), broken.display(), e);
+                    let _ = rename(&path, &broken);
+                }
+                InternalLog { entries: vec![] }
+            }
+        };
+        self.internal_log = log.entries;
+
+        if self.internal_log.is_empty() {
+            // an empty log may be written as `entries = []`, which [[entries]] can't be appended to
+            let _ = remove_file(&path);
+        }
+
+        self.internal_log_written = (
+            self.internal_log.len(),
+            self.internal_log.last().map_or(0, |entry| entry.count),
+        );
+    }
+
+    /** appends the entries added since the last save to the log file, and the last saved one
+    again if its repeat count went up. The file is never rewritten, so it stays cheap for long
+    logs and a crash can at most cut off the entry being written */
+    fn save_internal_log(&mut self) {
+        let (written, last_count) = self.internal_log_written;
+        let start = match written.checked_sub(1) {
+            Some(last) if self.internal_log.get(last).is_some_and(|entry| entry.count != last_count) => last,
+            _ => written.min(self.internal_log.len()),
+        };
+
+        if let Err(e) = append_internal_log(self.internal_log_format, &self.internal_log[start..]) {
+            error!("Failed to write log: {}", e);
+        }
+
+        self.internal_log_written = (
+            self.internal_log.len(),
+            self.internal_log.last().map_or(0, |entry| entry.count),
+        );
+    }
+
+
+
+    fn from_config() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut config = load_config()?;
+        set_data_dir(&config.data_dir)
+            .map_err(|e| format!("Could not create data_dir \"{}\": {}", config.data_dir, e))?;
+        let mut backups = config.backups;
+
+
+        let mut config_warnings = Vec::new();
+
+        let mut health_settings = config.health.clone();
+        if health_settings.heartbeat_url.as_deref().is_some_and(|url| url.trim().is_empty()) {
+            health_settings.heartbeat_url = None;
+        }
+        if health_settings.heartbeat_minutes == 0 {
+            let message = "health.heartbeat_minutes is 0. Pinging heartbeat_url every minute instead.".to_string();
+            warn!("{}", message);
+            config_warnings.push(message);
+            health_settings.heartbeat_minutes = 1;
+        }
+        let health = Arc::new(Health::new(config.urls.len()));
+
+        let internal_log_format = match config.internal_log_format.as_str() {
+            "toml" => InternalLogFormat::Toml,
+            "jsonl" => InternalLogFormat::Jsonl,
+            other => {
+                let message = format!(
+                    "Unknown internal_log_format \"{}\", use \"toml\" or \"jsonl\". Using toml instead.",
+                    other
+                );
+                warn!("{}", message);
+                config_warnings.push(message);
+                InternalLogFormat::Toml
+            }
+        };
+
+        // without a timezone schedules stay in UTC, while shown times follow the system
+        let display_timezone = match config.timezone.as_deref().map(|name| (name, name.parse::<Tz>())) {
+            None => None,
+            Some((_, Ok(timezone))) => Some(timezone),
+            Some((name, Err(_))) => {
+                let message = format!(
+                    "Unknown timezone \"{}\", use an IANA name like \"Europe/Oslo\". Using UTC instead.",
+                    name
+                );
+                warn!("{}", message);
+                config_warnings.push(message);
+                Some(Tz::UTC)
+            }
+        };
+        let timezone = display_timezone.unwrap_or(Tz::UTC);
+
+        if config.monthly_day_base > 1 {
+            let message = format!(
+                "monthly_day_base = {} is not 0 or 1, using 0 (monthly times count from the day before the 1st)",
+                config.monthly_day_base
+            );
+            warn!("{}", message);
+            config_warnings.push(message);
+        }
+
+        match config.smtp.tls_mode.as_str() {
+            "starttls" | "implicit" => {}
+            "none" => {
+                if !config.smtp.password.is_empty() {
+                    config_warnings.push(
+                        "smtp.tls_mode is \"none\": the SMTP password will be sent unencrypted.".to_string(),
+                    );
+                }
+            }
+            other => config_warnings.push(format!(
+                "Unknown smtp.tls_mode \"{}\". Use \"starttls\", \"implicit\" or \"none\".",
+                other
+            )),
+        }
+
+        if config.url_uptime_settings.interval_minutes == 0 {
+            // Option 1: Log and use a default
+            warn!("url_uptime_settings.interval_minutes is 0. Using default of 60 minutes.");
+            config.url_uptime_settings.interval_minutes = 60; 
+        }
+
+        config.maintenance_windows.retain(|window| match window.validate() {
+            Ok(()) => true,
+            Err(e) => {
+                let message = format!("Ignoring maintenance window \"{}\": {}", window.description, e);
+                warn!("{}", message);
+                config_warnings.push(message);
+                false
+            }
+        });
+
+        // a typo in a URL is a config error, not an outage
+        for entry in &mut config.urls {
+            if let Err(e) = check_url_syntax(&entry.url) {
+                let message = format!("Invalid URL for {}: {}. It will not be checked.", entry.description, e);
+                warn!("{}", message);
+                config_warnings.push(message);
+                entry.invalid = Some(e);
+            }
+
+            let follows_redirects =
+                !config.url_uptime_settings.fail_on_redirect && config.http.follow_redirects.limit() > 0;
+            if entry.expect_final_url.is_some() && !follows_redirects {
+                let message = format!(
+                    "expect_final_url for {} is checked against the URL itself, redirects are not followed (fail_on_redirect or follow_redirects)",
+                    entry.description
+                );
+                warn!("{}", message);
+                config_warnings.push(message);
+            }
+        }
+
+        for (setting, channels) in [
+            ("uptime_channels", &config.warning_settings.uptime_channels),
+            ("backup_channels", &config.warning_settings.backup_channels),
+        ] {
+            for channel in channels.iter().flatten() {
+                if !ALERT_CHANNELS.contains(&channel.as_str()) {
+                    let message = format!(
+                        "Unknown channel \"{}\" in warning_settings.{}. Use \"email\", \"post\" or \"desktop\".",
+                        channel, setting
+                    );
+                    warn!("{}", message);
+                    config_warnings.push(message);
+                }
+            }
+        }
+
+        if let Some(path) = config.http.ca_cert_path.clone() {
+            match load_ca_certs(&path) {
+                Ok(certs) => {
+                    info!("Trusting {} certificate(s) from {}", certs.len(), path);
+                    config.http.ca_certs = certs;
+                }
+                Err(e) => {
+                    let message = format!(
+                        "Could not load http.ca_cert_path {}: {}. Only the system certificates are trusted.",
+                        path, e
+                    );
+                    warn!("{}", message);
+                    config_warnings.push(message);
+                }
+            }
+        }
+
+        if config.http.danger_accept_invalid_certs {
+            let message = "http.danger_accept_invalid_certs is on: TLS certificates are not checked for any request".to_string();
+            warn!("{}", message);
+            config_warnings.push(message);
+        }
+
+        config.warning_settings.escalation.retain(|step| {
+            let problem = if step.after_failed_checks.is_none() && step.after_minutes.is_none() {
+                Some("set after_failed_checks or after_minutes".to_string())
+            } else {
+                step.channels
+                    .iter()
+                    .find(|channel| !ALERT_CHANNELS.contains(&channel.as_str()))
+                    .map(|channel| format!("unknown channel \"{}\", use \"email\", \"post\" or \"desktop\"", channel))
+            };
+
+            match problem {
+                Some(problem) => {
+                    let message = format!("Ignoring a warning_settings.escalation step: {}", problem);
+                    warn!("{}", message);
+                    config_warnings.push(message);
+                    false
+                }
+                None => true,
+            }
+        });
+
+        for channel in config.warning_settings.channel_daily_max.keys() {
+            if !ALERT_CHANNELS.contains(&channel.as_str()) {
+                let message = format!(
+                    "Unknown channel \"{}\" in warning_settings.channel_daily_max. Use \"email\", \"post\" or \"desktop\".",
+                    channel
+                );
+                warn!("{}", message);
+                config_warnings.push(message);
+            }
+        }
+
+        if let Some(at) = &config.warning_settings.digest_at {
+            match NaiveTime::parse_from_str(at.trim(), "%H:%M") {
+                Ok(time) => config.warning_settings.digest_minute = Some(time.hour() * 60 + time.minute()),
+                Err(_) => {
+                    let message = format!("Invalid warning_settings.digest_at \"{}\", use HH:MM. No digest is sent.", at);
+                    error!("{}", message);
+                    config_warnings.push(message);
+                }
+            }
+        }
+
+        if config.warning_settings.digest_minute.is_none() {
+            let digested: Vec<&str> = config
+                .urls
+                .iter()
+                .filter(|entry| entry.digest)
+                .map(|entry| entry.description.as_str())
+                .chain(backups.iter().filter(|entry| entry.digest).map(|entry| entry.description.as_str()))
+                .collect();
+            if !digested.is_empty() {
+                let message = format!(
+                    "digest = true for {} but warning_settings.digest_at is not set, warning right away instead",
+                    digested.join(", ")
+                );
+                warn!("{}", message);
+                config_warnings.push(message);
+            }
+            config.urls.iter_mut().for_each(|entry| entry.digest = false);
+            backups.iter_mut().for_each(|entry| entry.digest = false);
+        }
+
+        for entry in &mut config.urls {
+            if entry.interval_minutes == Some(0) {
+                let message = format!(
+                    "interval_minutes is 0 for {}. Using url_uptime_settings.interval_minutes instead.",
+                    entry.description
+                );
+                warn!("{}", message);
+                config_warnings.push(message);
+                entry.interval_minutes = None;
+            }
+
+            if let Some(message) = check_basic_auth(&mut entry.username, &mut entry.password, &entry.description) {
+                warn!("{}", message);
+                config_warnings.push(message);
+            }
+        }
+
+        for entry in &mut backups {
+            if let Some(message) = check_basic_auth(&mut entry.username, &mut entry.password, &entry.description) {
+                warn!("{}", message);
+                config_warnings.push(message);
+            }
+
+            match (&entry.command, entry.url.is_empty()) {
+                (None, true) => {
+                    let message = format!("Backup {} has neither url nor command and can't run", entry.description);
+                    error!("{}", message);
+                    config_warnings.push(message);
+                }
+                (Some(_), false) => {
+                    let message = format!("Backup {} sets both url and command, using command", entry.description);
+                    warn!("{}", message);
+                    config_warnings.push(message);
+                }
+                _ => {}
+            }
+        }
+
+
+
+        let state = load_state().unwrap_or_default();
+
+        for entry in &mut config.urls {
+            entry.muted = state.muted_urls.contains(&entry.url);
+        }
+
+        let mut uptime_stats = UptimeStats::load().unwrap_or_default();
+        uptime_stats.prune(Utc::now().date_naive(), config.url_uptime_settings.stats_window_days());
+
+        for message in assign_backup_ids(&mut backups, &data_dir().join(&config.backup_root)) {
+            warn!("{}", message);
+            config_warnings.push(message);
+        }
+
+        //loads the log for each backup.
+        for entry in &mut backups {
+            let logs = entry
+                .folder
+                .as_deref()
+                .and_then(|folder| load_log(folder).ok())
+                .unwrap_or(Log { entries: vec![] });
+            entry.logs = logs.entries;
+            entry.enabled = !(state.paused_backups.contains(&entry.id)
+                || state.paused_backups.contains(&entry.description));
+            if let Some(last) = state
+                .last_test_restores
+                .get(&entry.id)
+                .and_then(|last| DateTime::parse_from_rfc3339(last).ok())
+            {
+                entry.last_test_restore = last.with_timezone(&Utc);
+            }
+
+            match entry.retention.as_deref() {
+                None | Some("count") | Some("gfs") => {}
+                Some(other) => {
+                    let message = format!(
+                        "Unknown retention \"{}\" for backup {}. Using max/max_age_days instead.",
+                        other, entry.description
+                    );
+                    warn!("{}", message);
+                    config_warnings.push(message);
+                    entry.retention = None;
+                }
+            }
+
+            if entry.command.is_some() && (entry.checksum_url.is_some() || entry.verify_etag) {
+                let message = format!(
+                    "Backup {} runs a command, checksum_url and verify_etag only apply to downloads and are ignored.",
+                    entry.description
+                );
+                warn!("{}", message);
+                config_warnings.push(message);
+            }
+
+            if let Some(url) = &entry.test_restore_url {
+                if entry.restore.urls().contains(&url.as_str()) {
+                    let message = format!(
+                        "test_restore_url of backup {} is one of its restore routes. Test restores must go to a staging endpoint, turning them off.",
+                        entry.description
+                    );
+                    error!("{}", message);
+                    config_warnings.push(message);
+                    entry.test_restore_url = None;
+                    entry.test_restore_hours = None;
+                }
+            }
+
+            match (&entry.test_restore_url, entry.test_restore_hours) {
+                (Some(_), None) | (Some(_), Some(0)) => {
+                    let message = format!(
+                        "Backup {} has a test_restore_url but no test_restore_hours above 0, so no test restores run",
+                        entry.description
+                    );
+                    warn!("{}", message);
+                    config_warnings.push(message);
+                }
+                (None, Some(_)) => {
+                    let message = format!(
+                        "test_restore_hours needs a test_restore_url, ignoring it for backup {}",
+                        entry.description
+                    );
+                    warn!("{}", message);
+                    config_warnings.push(message);
+                    entry.test_restore_hours = None;
+                }
+                _ => {}
+            }
+
+            match entry.partition_by.as_deref() {
+                None | Some("none") | Some("day") | Some("month") => {}
+                Some(other) => {
+                    let message = format!(
+                        "Unknown partition_by \"{}\" for backup {}. Use \"none\", \"day\" or \"month\". Storing without subfolders.",
+                        other, entry.description
+                    );
+                    warn!("{}", message);
+                    config_warnings.push(message);
+                    entry.partition_by = None;
+                }
+            }
+
+            if entry.token_query_param.is_some() && !entry.send_auth {
+                let message = format!(
+                    "token_query_param is only used with send_auth = true, backup {} is downloaded without a token",
+                    entry.description
+                );
+                warn!("{}", message);
+                config_warnings.push(message);
+            }
+
+            let mut invalid_period = false;
+            if entry.cron.is_none() && entry.interval == "custom" {
+                let problem = match entry.period_minutes {
+                    None => Some("needs period_minutes"),
+                    Some(0) => Some("needs a period_minutes above 0"),
+                    Some(period) if period > 24 * 60 => Some("allows a period_minutes of at most 1440 (one day)"),
+                    Some(_) => None,
+                };
+                if let Some(problem) = problem {
+                    let message = format!(
+                        "interval \"custom\" {} for backup {}. This backup will not run on a schedule.",
+                        problem, entry.description
+                    );
+                    error!("{}", message);
+                    config_warnings.push(message);
+                    entry.interval.clear();
+                    invalid_period = true;
+                }
+            } else if entry.cron.is_none() && entry.period_minutes.is_some() {
+                let message = format!(
+                    "period_minutes is only used with interval = \"custom\", ignoring it for backup {}",
+                    entry.description
+                );
+                warn!("{}", message);
+                config_warnings.push(message);
+            }
+
+            if entry.cron.is_none() && !entry.weekdays.is_empty() {
+                if entry.interval == "w" {
+                    let mut unknown = Vec::new();
+                    for day in &entry.weekdays {
+                        match day.trim().parse::<Weekday>() {
+                            Ok(day) if !entry.weekday_set.contains(&day) => entry.weekday_set.push(day),
+                            Ok(_) => {}
+                            Err(_) => unknown.push(day.as_str()),
+                        }
+                    }
+                    entry.weekday_set.sort_by_key(|day| day.num_days_from_monday());
+
+                    if !unknown.is_empty() {
+                        let message = format!(
+                            "Unknown weekdays {} for backup {}. Use Mon, Tue, Wed, Thu, Fri, Sat or Sun.{}",
+                            unknown.join(", "),
+                            entry.description,
+                            if entry.weekday_set.is_empty() { " This backup will not run on a schedule." } else { "" }
+                        );
+                        if entry.weekday_set.is_empty() {
+                            error!("{}", message);
+                        } else {
+                            warn!("{}", message);
+                        }
+                        config_warnings.push(message);
+                    }
+                    if entry.weekday_set.is_empty() {
+                        entry.interval.clear();
+                        invalid_period = true;
+                    }
+                } else {
+                    let message = format!(
+                        "weekdays is only used with interval = \"w\", ignoring it for backup {}",
+                        entry.description
+                    );
+                    warn!("{}", message);
+                    config_warnings.push(message);
+                }
+            }
+
+            // with weekdays the day comes from the list, so at/time are a time of day
+            let unit = if entry.weekday_set.is_empty() { entry.interval.clone() } else { "d".to_string() };
+
+            // older configs count a monthly time from the day before the 1st, so it is moved to
+            // the same day in today's counting
+            if unit == "m" && entry.cron.is_none() && config.monthly_day_base != 1 {
+                if entry.time >= 24 * 60 {
+                    entry.time -= 24 * 60;
+                } else if entry.at.is_none() {
+                    let message = format!(
+                        "time = {} for monthly backup {} was before day 1 and never ran with monthly_day_base = 0. It now runs on the 1st at {:02}:{:02} {}.",
+                        entry.time,
+                        entry.description,
+                        entry.time / 60,
+                        entry.time % 60,
+                        timezone
+                    );
+                    warn!("{}", message);
+                    config_warnings.push(message);
+                }
+            }
+
+            let mut invalid_at = invalid_period;
+            if let (false, None, Some(at)) = (invalid_period, &entry.cron, &entry.at) {
+                match parse_backup_at(at, &unit) {
+                    Ok(time) => {
+                        if entry.time != 0 && entry.time != time {
+                            let message = format!(
+                                "Backup {} sets both at and time, using at = \"{}\"",
+                                entry.description, at
+                            );
+                            warn!("{}", message);
+                            config_warnings.push(message);
+                        }
+                        entry.time = time;
+                    }
+                    Err(e) => {
+                        let message = format!(
+                            "Invalid at \"{}\" for backup {}: {}. This backup will not run on a schedule.",
+                            at, entry.description, e
+                        );
+                        error!("{}", message);
+                        config_warnings.push(message);
+                        entry.interval.clear(); // an empty interval never fires, like an unknown one
+                        invalid_at = true;
+                    }
+                }
+            }
+
+            if entry.cron.is_none() && !invalid_at {
+                // custom runs from at/time on until midnight, so that is a time of day
+                let period = interval_period_minutes(&unit, entry.period_minutes)
+                    .map(|period| if unit == "custom" { 24 * 60 } else { period });
+                match period {
+                    None => {
+                        let message = format!(
+                            "Unknown interval \"{}\" for backup {}. Use \"h\", \"d\", \"w\", \"m\" or \"custom\". This backup will not run on a schedule.",
+                            entry.interval, entry.description
+                        );
+                        error!("{}", message);
+                        config_warnings.push(message);
+                    }
+                    Some(period) if entry.time >= period => {
+                        let normalized = entry.time % period;
+                        let message = format!(
+                            "time {} is beyond one period of interval \"{}\" for backup {}. Using {} ({} {}) instead.",
+                            entry.time,
+                            entry.interval,
+                            entry.description,
+                            normalized,
+                            describe_backup_time(normalized, &unit),
+                            timezone
+                        );
+                        warn!("{}", message);
+                        config_warnings.push(message);
+                        entry.time = normalized;
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            if let Some(expression) = &entry.cron {
+                match parse_cron(expression) {
+                    Ok(schedule) => entry.schedule = Some(schedule),
+                    Err(e) => {
+                        let message = format!(
+                            "Invalid cron expression \"{}\" for backup {}: {}. This backup will not run on a schedule.",
+                            expression, entry.description, e
+                        );
+                        error!("{}", message);
+                        config_warnings.push(message);
+                    }
+                }
+            }
+        }
+
+        let (_tx, rx) = std::sync::mpsc::channel();
+        let (outcome_tx, outcome_rx) = std::sync::mpsc::channel();
+        let (integrity_tx, integrity_rx) = std::sync::mpsc::channel();
+        let (test_restore_tx, test_restore_rx) = std::sync::mpsc::channel();
+        let (auth_test_tx, auth_test_rx) = std::sync::mpsc::channel();
+        let (alert_test_tx, alert_test_rx) = std::sync::mpsc::channel();
+
+        let mut app = Self {
+            uptime_url_settings: config.url_uptime_settings,
+            internal_log: vec![],
+            internal_log_format,
+            internal_log_written: (0, 0),
+            // clamped in case state.toml was edited by hand
+            log_panel_height: state.log_panel_height.unwrap_or(DEFAULT_LOG_PANEL_HEIGHT).clamp(*LOG_PANEL_HEIGHTS.start(), *LOG_PANEL_HEIGHTS.end()),
+            log_font_size: state.log_font_size.unwrap_or(DEFAULT_LOG_FONT_SIZE).clamp(*LOG_FONT_SIZES.start(), *LOG_FONT_SIZES.end()),
+            warning_settings: config.warning_settings,
+            uptime_urls: config.urls,
+            backups,
+            token: config.token,
+            secret: config.secret,
+            jwt_expiry: config.jwt_expiry,
+            payload: config.payload,
+            backup_enabled: state.backup_enabled.unwrap_or(config.backup_enabled_on_start),
+            backup_jitter_seconds: config.backup_jitter_seconds,
+            backup_enable_grace_minutes: config.backup_enable_grace_minutes,
+            backup_enabled_at: None,
+            timezone,
+            display_timezone,
+            run_on_start_pending: config.run_on_start,
+            backup_trigger_rx: rx,
+            max_concurrent_backups: config.max_concurrent_backups,
+            backup_queue: vec![],
+            running_backups: vec![],
+            backup_outcome_tx: outcome_tx,
+            backup_outcome_rx: outcome_rx,
+            integrity_check_hours: config.integrity_check_hours,
+            last_integrity_check: Utc::now(),
+            verifying_backups: vec![],
+            integrity_tx,
+            integrity_rx,
+            test_restoring: vec![],
+            test_restore_tx,
+            test_restore_rx,
+            auth_test_tx,
+            auth_test_rx,
+            shutting_down: Arc::new(AtomicBool::new(false)),
+            smtp_config: config.smtp,
+            instance: instance_label(&config.http),
+            http_settings: config.http,
+            maintenance_windows: config.maintenance_windows,
+            health,
+            heartbeat_url: health_settings.heartbeat_url.clone(),
+            heartbeat_minutes: health_settings.heartbeat_minutes,
+            last_heartbeat: None,
+            uptime_fails: 0,
+            uptime_sweep: None,
+            warnings_sent: HashMap::new(),
+            last_warning_sent: HashMap::new(),
+            last_sent_warning: None,
+            digest_events: state.digest_events,
+            pending_delete: None,
+            pending_restore: None,
+            restores_after_backup: vec![],
+            editing_description: None,
+            alert_test_results: vec![],
+            alert_test_tx,
+            alert_test_rx,
+            alert_settings_form: None,
+            startup_problems: Vec::new(),
+            import_path: String::new(),
+            import_result: None,
+            hostname: machine_hostname(),
+            run_id: Uuid::new_v4().to_string(),
+            redact_curl_token: true,
+            snooze_until: None,
+            snooze_minutes: 60,
+            paused_until: None,
+            pause_minutes: 60,
+            offline_since: None,
+            uptime_stats,
+        };
+
+        app.import_internal_log();
+
+        if let Err(e) = health::serve(&health_settings, app.health.clone()) {
+            let message = format!("Could not start the health endpoint: {}", e);
+            error!("{}", message);
+            config_warnings.push(message);
+        }
+
+        app.startup_problems = config_warnings.clone();
+        for message in config_warnings {
+            push_internal_log(&mut app.internal_log, InternalLogEntry::new(LogLevel::Warn, message));
+        }
+
+        app.refresh_free_space();
+        app.publish_status();
+
+        Ok(app)
+    }
+
+    /** runs a backup on its own thread, process_backup_queue picks up the outcome */
+    fn start_backup(&mut self, id: &str) {
+        let Some(i) = self.backups.iter().position(|backup| backup.id == id) else {
+            return;
+        };
+        info!("Attempting backup of {}", self.backups[i].description);
+
+        let Some(save_path) = self.backups[i].folder.clone() else {
+            let message = format!(
+                "Backup of {} skipped: it has no usable folder, see the startup log",
+                self.backups[i].description
+            );
+            error!("{}", message);
+            self.add_internal_log(LogLevel::Error, message);
+            return;
+        };
+
+        if !self.check_free_space(i) {
+            return;
+        }
+
+        let token = self.download_token(i);
+        let backup = self.backups[i].clone();
+        let http = self.http_settings.clone();
+        let outcome_tx = self.backup_outcome_tx.clone();
+        let connectivity_urls = self.connectivity_check_urls();
+        let shutting_down = self.shutting_down.clone();
+        let partition = backup_partition(&backup, Utc::now().with_timezone(&self.timezone));
+        self.running_backups.push(backup.id.clone());
+
+        thread::spawn(move || {
+            let started = Instant::now();
+            let target = match &partition {
+                Some(partition) => save_path.join(partition),
+                None => save_path.clone(),
+            };
+
+            let mut hook_log = Vec::new();
+            let pre_hook = match &backup.pre_command {
+                Some(command) => run_backup_hook(&backup, "pre_command", command, &[], &mut hook_log),
+                None => Ok(()),
+            };
+            let pre_hook_ok = pre_hook.is_ok();
+
+            let result = match (pre_hook, &backup.command) {
+                (Err(e), _) => Err(e.into()),
+                (Ok(()), Some(command)) => run_backup_command(&backup, command, &target).map(|filename| (filename, None)),
+                (Ok(()), None) => download_file(&backup, &target, &token, &http, &shutting_down),
+            };
+            let (result, server_checksum) = match result {
+                Ok((filename, checksum)) => (Ok(filename), checksum),
+                Err(e) => (Err(e), None),
+            };
+
+            let (result, archive) = match result {
+                Ok(filename) if backup.extract_archive => match unpack_backup(&target, &filename) {
+                    Ok((folder, format)) => (Ok(folder), Some(format)),
+                    Err(e) => (Err(e), None),
+                },
+                result => (result, None),
+            };
+
+            // log.toml stays in the backup folder, so it names partitioned files by their path from there
+            let result = result.map(|filename| match &partition {
+                Some(partition) => format!("{}/{}", partition, filename),
+                None => filename,
+            });
+
+            // the pre_command may have taken a lock, so this runs even when the backup failed. It
+            // doesn't run when the pre_command itself failed
+            if let Some(command) = backup.post_command.as_ref().filter(|_| pre_hook_ok) {
+                let env = [
+                    ("WSS_BACKUP_RESULT", if result.is_ok() { "success" } else { "failed" }.to_string()),
+                    ("WSS_BACKUP_FILE", result.as_ref().map(|filename| save_path.join(filename).display().to_string()).unwrap_or_default()),
+                ];
+                if let Err(e) = run_backup_hook(&backup, "post_command", command, &env, &mut hook_log) {
+                    hook_log.push((LogLevel::Warn, e));
+                }
+            }
+
+            // a backup that can't be hashed is still a backup, it just can't be verified later
+            let sha256 = result.as_ref().ok().and_then(|filename| {
+                integrity::hash_path(&save_path.join(filename))
+                    .map_err(|e| warn!("Could not hash {}: {}", filename, e))
+                    .ok()
+            });
+
+            let duration_ms = started.elapsed().as_millis() as u64;
+            // a failed download may only mean this host is offline, a local command doesn't depend on the network
+            let online = result.is_ok() || backup.command.is_some() || probe_connectivity(&connectivity_urls, &http);
+
+            let _ = outcome_tx.send(BackupOutcome {
+                id: backup.id,
+                result: result.map_err(|e| e.to_string()),
+                archive,
+                sha256,
+                server_checksum,
+                hook_log,
+                duration_ms,
+                online,
+            });
+        });
+    }
+
+    /** logs, rotates and warns about a backup that has finished on its thread */
+    fn finish_backup(&mut self, outcome: BackupOutcome) {
+        let Some(i) = self.backups.iter().position(|backup| backup.id == outcome.id) else {
+            return;
+        };
+        let Some(save_path) = self.backups[i].folder.clone() else {
+            return;
+        };
+        let duration_ms = outcome.duration_ms;
+
+        for (level, message) in outcome.hook_log {
+            self.add_internal_log(level, message);
+        }
+
+        match outcome.result {
+            Ok(filename) => {
+                info!("Backup downloaded: {} in {} ms", filename, duration_ms);
+                if self.backups[i].digest {
+                    let description = self.backups[i].description.clone();
+                    self.add_to_digest(format!("Backup of {} succeeded: {} in {} ms", description, filename, duration_ms));
+                }
+
+                // compared before the new file is in the log, so the newest entry is the previous backup
+                let shrunk = self.check_backup_shrink(i, &save_path.join(&filename));
+
+                let logged = add_to_backup_log(
+                    &filename,
+                    &save_path,
+                    duration_ms,
+                    outcome.archive,
+                    outcome.sha256,
+                    outcome.server_checksum,
+                );
+
+                if let (Ok(entry), true) = (&logged, self.backups[i].write_sidecar) {
+                    if let Err(e) = write_sidecar(&self.backups[i], &save_path, entry) {
+                        let message = format!("Could not write the metadata file for {}: {}", filename, e);
+                        warn!("{}", message);
+                        self.add_internal_log(LogLevel::Warn, message);
+                    }
+                }
+
+                if self.backups[i].latest_copy {
+                    let tracked = load_log(&save_path).map(|log| log.entries).unwrap_or_default();
+                    if let Err(e) = update_latest_copy(&save_path, &filename, &tracked) {
+                        let message = format!(
+                            "Could not update the latest copy for {}: {}",
+                            self.backups[i].description, e
+                        );
+                        warn!("{}", message);
+                        self.add_internal_log(LogLevel::Warn, message);
+                    }
+                }
+
+                // Re-read logs after successful backup
+                match load_log(&save_path) {
+                    Ok(log) => {
+                        self.backups[i].logs = log.entries;
+
+                        let id = self.backups[i].id.clone();
+
+                        if shrunk && self.backups[i].keep_on_shrink {
+                            let message = format!(
+                                "Not rotating old backups of {} because the new backup shrank (keep_on_shrink)",
+                                self.backups[i].description
+                            );
+                            self.add_internal_log(LogLevel::Warn, message);
+                            return;
+                        }
+
+                        debug!("Rotating old backups of {}", id);
+
+                        self.remove_backups_over_limit(&id);
+                    }
+                    Err(err) => {
+                        error!("Could not reload log after backup: {}", err);
+                        self.backups[i].logs = vec![];
+                    }
+                }
+            }
+            Err(err) => {
+                let error_message = match &self.backups[i].command {
+                    Some(_) => format!("Backup failed for {}. Error: {}", self.backups[i].description, err),
+                    None => format!("Backup failed for URL: {}. Error: {}", self.backups[i].url, err),
+                };
+                error!("{}", error_message);
+                self.add_internal_log(LogLevel::Error, error_message.clone());
+
+                if self.backups[i].command.is_none() {
+                    self.apply_connectivity(outcome.online);
+                    if !outcome.online {
+                        return;
+                    }
+                }
+
+                let warning = Warning {
+                    category: AlertCategory::Backup,
+                    subject: "Backup failed".to_string(),
+                    body: error_message.clone(),
+                    description: error_message, // Use the detailed error message
+                    logs: self.recent_log_lines(),
+                };
+
+                self.send_backup_warning(i, &warning);
+            }
+        }
+    }
+
+    /** warns when the new backup file is more than max_shrink_percent smaller than the previous
+    restore point, which usually means a broken or truncated dump. Returns true if it shrank */
+    fn check_backup_shrink(&mut self, i: usize, new_file: &Path) -> bool {
+        let Some(max_shrink) = self.backups[i].max_shrink_percent else {
+            return false;
+        };
+
+        let previous = self.backups[i]
+            .logs
+            .iter()
+            .filter_map(|entry| {
+                DateTime::parse_from_rfc3339(&entry.timestamp)
+                    .ok()
+                    .map(|timestamp| (timestamp, entry.size))
+            })
+            .max_by_key(|(timestamp, _)| *timestamp)
+            .map(|(_, size)| size);
+
+        let Some(previous) = previous else {
+            return false;
+        };
+        let size = archive::disk_size(new_file);
+
+        if previous == 0 || size >= previous {
+            return false;
+        }
+
+        let shrink_percent = (previous - size) * 100 / previous;
+        if shrink_percent <= max_shrink as u64 {
+            return false;
+        }
+
+        let message = format!(
+            "Backup of {} shrank by {}% ({:.1} KB, the previous one was {:.1} KB). It may be broken or truncated.",
+            self.backups[i].description,
+            shrink_percent,
+            size as f64 / 1000.0,
+            previous as f64 / 1000.0
+        );
+        warn!("{}", message);
+        self.add_internal_log(LogLevel::Warn, message.clone());
+
+        let warning = Warning {
+            category: AlertCategory::Backup,
+            subject: format!("Backup shrank: {}", self.backups[i].description),
+            body: message.clone(),
+            description: message,
+            logs: self.recent_log_lines(),
+        };
+        self.send_backup_warning(i, &warning);
+
+        true
+    }
+
+    /** applies the retention rules right away instead of waiting for the next backup */
+    fn clean_up_backup(&mut self, i: usize) {
+        let id = self.backups[i].id.clone();
+        let removed = self.remove_backups_over_limit(&id);
+
+        let message = match removed {
+            0 => format!("Clean up of {}: nothing to remove", self.backups[i].description),
+            1 => format!("Clean up of {}: removed 1 old backup", self.backups[i].description),
+            n => format!("Clean up of {}: removed {} old backups", self.backups[i].description, n),
+        };
+        info!("{}", message);
+        self.add_internal_log(LogLevel::Info, message);
+    }
+
+    /** the configured token, or a freshly signed JWT when no token is set */
+    fn bearer_token(&self) -> String {
+        if self.token.is_empty() {
+            match create_jwt(&self.payload, &self.secret, &self.jwt_expiry) {
+                Ok(jwt) => jwt,
+                Err(e) => {
+                    error!("Failed to create JWT: {}", e);
+                    String::new() // Use empty string if JWT creation fails
+                }
+            }
+        } else {
+            self.token.clone()
+        }
+    }
+
+    /** the token sent with a backup's downloads, empty unless the backup has send_auth */
+    fn download_token(&self, i: usize) -> String {
+        if self.backups[i].send_auth {
+            self.bearer_token()
+        } else {
+            String::new()
+        }
+    }
+
+    /** checks the credentials of a backup against its url on a worker thread, the result is
+    shown next to the backup by process_auth_tests */
+    fn test_backup_auth(&mut self, i: usize) {
+        let token = self.download_token(i);
+        let backup = self.backups[i].clone();
+        let http = self.http_settings.clone();
+        let auth_test_tx = self.auth_test_tx.clone();
+        self.backups[i].auth_testing = true;
+
+        thread::spawn(move || {
+            let result = match probe_backup_auth(&backup, &token, &http) {
+                Ok(status) if status.is_success() || backup.accept_statuses.contains(&status.as_u16()) => {
+                    (true, format!("Auth test for {}: accepted ({})", backup.description, status))
+                }
+                Ok(status) if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN => {
+                    (false, format!("Auth test for {}: rejected ({})", backup.description, status))
+                }
+                Ok(status) => (false, format!("Auth test for {}: unexpected status {}", backup.description, status)),
+                Err(e) => (false, format!("Auth test for {} failed: {}", backup.description, e)),
+            };
+            let _ = auth_test_tx.send((backup.id, result));
+        });
+    }
+
+    /** shows finished "Test auth" results next to their backups */
+    fn process_auth_tests(&mut self) {
+        let results: Vec<(String, (bool, String))> = self.auth_test_rx.try_iter().collect();
+
+        for (id, result) in results {
+            if let Some(backup) = self.backups.iter_mut().find(|backup| backup.id == id) {
+                backup.auth_testing = false;
+                backup.auth_test = Some(result);
+            }
+        }
+    }
+
+    /** opens the backup's folder in the OS file manager, creating it if no backup ran yet */
+    fn open_backup_folder(&mut self, i: usize) {
+        let Some(folder) = self.backups[i].folder.clone() else {
+            return;
+        };
+
+        let result = create_dir_all(&folder)
+            .map_err(|e| e.to_string())
+            .and_then(|_| opener::open(&folder).map_err(|e| e.to_string()));
+
+        if let Err(e) = result {
+            self.add_internal_log(
+                LogLevel::Warn,
+                format!("Could not open the folder {}: {}", folder.display(), e),
+            );
+        }
+    }
+
+    /** email subject with the instance label in front, so alerts from several installations
+    can be told apart */
+    fn alert_subject(&self, subject: &str) -> String {
+        format!("[{}] {}", self.instance, subject)
+    }
+
+    /** email body with where the alert came from at the end */
+    fn alert_body(&self, body: &str) -> String {
+        format!(
+            "{}\n\nInstance: {}\nHost: {}\nRun: {}",
+            body, self.instance, self.hostname, self.run_id
+        )
+    }
+
+    /** the JSON sent to post_request_routes */
+    fn alert_payload(&self, description: &str, logs: &[String]) -> JsonValue {
+        json!({
+            "time": Utc::now().to_rfc3339(),
+            "instance": self.instance,
+            "hostname": self.hostname,
+            "run_id": self.run_id,
+            "description": description,
+            "logs": logs
+        })
+    }
+
+    /** sends a sample email, not counted against daily_max */
+    fn send_test_email(&mut self) {
+        let (address, smtp) = (self.warning_settings.email.clone(), self.smtp_config.clone());
+        self.send_test_email_with(&address, &smtp);
+    }
+
+    /** sends a sample email with the given settings, used to try the settings panel before saving */
+    fn send_test_email_with(&mut self, address: &str, smtp: &SmtpConfig) {
+        let result = try_to_send_email(
+            address,
+            &self.alert_subject("WebSync Station test"),
+            &self.alert_body("This is a WebSync Station test. If you can read this, email warnings are working."),
+            smtp,
+        );
+
+        self.alert_test_results = vec![match result {
+            Ok(()) => (true, format!("Test email sent to {}", address)),
+            Err(e) => (false, format!("Test email to {} failed: {}", address, e)),
+        }];
+    }
+
+    /** SMTP and warning settings, editable without opening config.toml */
+    fn alert_settings_ui(&mut self, ui: &mut egui::Ui) {
+        let form = self.alert_settings_form.get_or_insert_with(|| AlertSettingsForm {
+            smtp: self.smtp_config.clone(),
+            email: self.warning_settings.email.clone(),
+            use_email: self.warning_settings.use_email,
+            send_post_request: self.warning_settings.send_post_request,
+            desktop_notifications: self.warning_settings.desktop_notifications,
+            post_request_routes: self.warning_settings.post_request_routes.join("\n"),
+            daily_max: self.warning_settings.daily_max,
+        });
+
+        egui::Grid::new("alert_settings").num_columns(2).show(ui, |ui| {
+            ui.label("SMTP server");
+            ui.text_edit_singleline(&mut form.smtp.server);
+            ui.end_row();
+
+            ui.label("Port");
+            ui.add(egui::DragValue::new(&mut form.smtp.port));
+            ui.end_row();
+
+            ui.label("Encryption");
+            egui::ComboBox::from_id_source("smtp_tls_mode")
+                .selected_text(form.smtp.tls_mode.clone())
+                .show_ui(ui, |ui| {
+                    for mode in ["starttls", "implicit", "none"] {
+                        ui.selectable_value(&mut form.smtp.tls_mode, mode.to_string(), mode);
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Username");
+            ui.text_edit_singleline(&mut form.smtp.username);
+            ui.end_row();
+
+            ui.label("Password");
+            ui.add(egui::TextEdit::singleline(&mut form.smtp.password).password(true));
+            ui.end_row();
+
+            ui.label("From");
+            ui.text_edit_singleline(&mut form.smtp.from);
+            ui.end_row();
+
+            ui.label("Send warnings to");
+            ui.text_edit_singleline(&mut form.email);
+            ui.end_row();
+
+            ui.label("Channels");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut form.use_email, "Email");
+                ui.checkbox(&mut form.send_post_request, "POST");
+                ui.checkbox(&mut form.desktop_notifications, "Desktop");
+            });
+            ui.end_row();
+
+            ui.label("POST routes (one per line)");
+            ui.text_edit_multiline(&mut form.post_request_routes);
+            ui.end_row();
+
+            ui.label("Warnings per day");
+            ui.add(egui::DragValue::new(&mut form.daily_max).clamp_range(0..=1000));
+            ui.end_row();
+        });
+
+        let (mut test, mut save, mut discard) = (false, false, false);
+        ui.horizontal(|ui| {
+            test = ui
+                .button("Send test email")
+                .on_hover_text("Uses the values above, even before they are saved")
+                .clicked();
+            save = ui
+                .button("Save")
+                .on_hover_text("Use these settings now and write them to config.toml")
+                .clicked();
+            discard = ui.button("Discard changes").clicked();
+        });
+
+        if test {
+            let form = form.clone();
+            self.send_test_email_with(&form.email, &form.smtp);
+        }
+        if save {
+            self.save_alert_settings();
+        }
+        if discard {
+            self.alert_settings_form = None;
+        }
+    }
+
+    /** "Import URLs": adds the URLs from a CSV or JSON file to config.toml and starts checking them */
+    fn import_urls(&mut self) {
+        let path = PathBuf::from(self.import_path.trim());
+
+        match import_url_file(&path, &self.uptime_urls) {
+            Ok((added, summary)) => {
+                self.uptime_urls.extend(added);
+                self.health.set_monitored_urls(self.uptime_urls.len());
+                self.add_internal_log(LogLevel::Info, summary.clone());
+                self.import_result = Some((true, summary));
+            }
+            Err(e) => {
+                let message = format!("Could not import URLs from {}: {}", path.display(), e);
+                self.add_internal_log(LogLevel::Error, message.clone());
+                self.import_result = Some((false, message));
+            }
+        }
+    }
+
+    /** applies the settings panel and writes it to config.toml, keeping comments in place */
+    fn save_alert_settings(&mut self) {
+        let Some(form) = self.alert_settings_form.clone() else {
+            return;
+        };
+
+        let routes: Vec<String> = form
+            .post_request_routes
+            .lines()
+            .map(str::trim)
+            .filter(|route| !route.is_empty())
+            .map(String::from)
+            .collect();
+
+        let result = set_config_table_values(&[
+            ("smtp", "server", toml_edit::Value::from(form.smtp.server.as_str())),
+            ("smtp", "port", toml_edit::Value::from(form.smtp.port as i64)),
+            ("smtp", "tls_mode", toml_edit::Value::from(form.smtp.tls_mode.as_str())),
+            ("smtp", "username", toml_edit::Value::from(form.smtp.username.as_str())),
+            ("smtp", "password", toml_edit::Value::from(form.smtp.password.as_str())),
+            ("smtp", "from", toml_edit::Value::from(form.smtp.from.as_str())),
+            ("warning_settings", "email", toml_edit::Value::from(form.email.as_str())),
+            ("warning_settings", "use_email", toml_edit::Value::from(form.use_email)),
+            ("warning_settings", "send_post_request", toml_edit::Value::from(form.send_post_request)),
+            ("warning_settings", "desktop_notifications", toml_edit::Value::from(form.desktop_notifications)),
+            ("warning_settings", "post_request_routes", toml_edit::Value::Array(routes.iter().collect())),
+            ("warning_settings", "daily_max", toml_edit::Value::from(form.daily_max as i64)),
+        ]);
+
+        self.smtp_config = form.smtp;
+        self.warning_settings.email = form.email;
+        self.warning_settings.use_email = form.use_email;
+        self.warning_settings.send_post_request = form.send_post_request;
+        self.warning_settings.desktop_notifications = form.desktop_notifications;
+        self.warning_settings.post_request_routes = routes;
+        self.warning_settings.daily_max = form.daily_max;
+
+        match result {
+            Ok(()) => {
+                self.alert_test_results = vec![(true, "Alert settings saved to config.toml".to_string())];
+                self.add_internal_log(LogLevel::Info, "Alert settings changed in the settings panel".to_string());
+            }
+            Err(e) => {
+                let message = format!("Alert settings are used now, but could not be saved to config.toml: {}", e);
+                self.alert_test_results = vec![(false, message.clone())];
+                self.add_internal_log(LogLevel::Warn, message);
+            }
+        }
+    }
+
+    /** sends a sample warning to every POST route, not counted against daily_max. The results
+    show up once process_alert_test_results picks them up */
+    fn send_test_webhooks(&mut self) {
+        self.alert_test_results.clear();
+        if self.warning_settings.post_request_routes.is_empty() {
+            self.alert_test_results
+                .push((false, "No post_request_routes configured".to_string()));
+            return;
+        }
+
+        let payload = self.alert_payload("This is a WebSync Station test", &[]);
+        let alert_test_tx = self.alert_test_tx.clone();
+        self.post_in_background(&payload, move |results| {
+            let _ = alert_test_tx.send(results);
+        });
+    }
+
+    /** shows the results of test or replayed POSTs that finished on their thread */
+    fn process_alert_test_results(&mut self) {
+        for results in self.alert_test_rx.try_iter() {
+            self.alert_test_results.extend(results);
+        }
+    }
+
+    /** mutes or unmutes the alerts of URL i and remembers it in state.toml */
+    fn toggle_url_mute(&mut self, i: usize) {
+        let entry = &mut self.uptime_urls[i];
+        entry.muted = !entry.muted;
+        // no recovery notice for an outage that was muted halfway through
+        entry.alerted_down = false;
+
+        let message = if entry.muted {
+            format!("Muted alerts for {}", entry.description)
+        } else {
+            format!("Unmuted alerts for {}", entry.description)
+        };
+        info!("{}", message);
+        self.add_internal_log(LogLevel::Info, message);
+        self.save_state();
+    }
+
+    fn save_state(&self) {
+        let state = AppState {
+            paused_backups: self
+                .backups
+                .iter()
+                .filter(|backup| !backup.enabled)
+                .map(|backup| backup.id.clone())
+                .collect(),
+            muted_urls: self
+                .uptime_urls
+                .iter()
+                .filter(|entry| entry.muted)
+                .map(|entry| entry.url.clone())
+                .collect(),
+            digest_events: self.digest_events.clone(),
+            backup_enabled: Some(self.backup_enabled),
+            log_panel_height: Some(self.log_panel_height),
+            log_font_size: Some(self.log_font_size),
+            last_test_restores: self
+                .backups
+                .iter()
+                .filter(|backup| backup.test_restore_hours.is_some())
+                .map(|backup| (backup.id.clone(), backup.last_test_restore.to_rfc3339()))
+                .collect(),
+        };
+
+        if let Err(e) = write_state(&state) {
+            error!("Failed to write state file: {}", e);
+        }
+    }
+
+    /** moves a URL or backup entry and writes the new order to config.toml */
+    fn move_entry(&mut self, kind: EntryKind, from: usize, to: usize) {
+        let len = match kind {
+            EntryKind::Url => self.uptime_urls.len(),
+            EntryKind::Backup => self.backups.len(),
+        };
+
+        if from >= len || to >= len {
+            return;
+        }
+
+        if let Err(e) = swap_config_entries(kind.config_key(), from, to) {
+            self.add_internal_log(LogLevel::Error, format!("Could not save the new order to config.toml: {}", e));
+            return;
+        }
+
+        match kind {
+            EntryKind::Url => self.uptime_urls.swap(from, to),
+            EntryKind::Backup => self.backups.swap(from, to),
+        }
+    }
+
+    /** renames a URL or backup entry in config.toml. Backup folders are named after the id,
+    so they stay where they are */
+    fn rename_entry(&mut self, kind: EntryKind, i: usize, new_description: &str) {
+        let new_description = new_description.trim().to_string();
+
+        let descriptions: Vec<&String> = match kind {
+            EntryKind::Url => self.uptime_urls.iter().map(|entry| &entry.description).collect(),
+            EntryKind::Backup => self.backups.iter().map(|entry| &entry.description).collect(),
+        };
+
+        let Some(old_description) = descriptions.get(i).map(|description| description.to_string()) else {
+            return;
+        };
+
+        if new_description == old_description {
+            return;
+        }
+
+        if new_description.is_empty() {
+            self.add_internal_log(LogLevel::Warn, "Rename failed: the description can't be empty".to_string());
+            return;
+        }
+
+        if descriptions.iter().any(|description| **description == new_description) {
+            self.add_internal_log(LogLevel::Warn, format!("Rename failed: \"{}\" is already in use", new_description));
+            return;
+        }
+
+        if let Err(e) = set_config_entry_value(kind.config_key(), i, "description", &new_description) {
+            self.add_internal_log(LogLevel::Error, format!("Rename failed: could not update config.toml: {}", e));
+            return;
+        }
+
+        match kind {
+            EntryKind::Url => self.uptime_urls[i].description = new_description.clone(),
+            EntryKind::Backup => self.backups[i].description = new_description.clone(),
+        }
+
+        self.add_internal_log(LogLevel::Info, format!("Renamed {} to {}", old_description, new_description));
+    }
+
+    /** draws move up/down and rename controls for one entry, or the rename field while editing */
+    fn entry_controls(
+        &mut self,
+        ui: &mut egui::Ui,
+        kind: EntryKind,
+        i: usize,
+        len: usize,
+        action: &mut Option<EntryAction>,
+    ) {
+        let mut finished = None;
+
+        if let Some((editing_kind, editing_index, text)) = &mut self.editing_description {
+            if *editing_kind == kind && *editing_index == i {
+                let response = ui.text_edit_singleline(text);
+                let submitted = response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+
+                if ui.small_button("Save").clicked() || submitted {
+                    finished = Some(Some(text.clone()));
+                }
+                if ui.small_button("Cancel").clicked() {
+                    finished = Some(None);
+                }
+            }
+        }
+
+        match finished {
+            Some(new_description) => {
+                self.editing_description = None;
+                if let Some(new_description) = new_description {
+                    *action = Some(EntryAction::Rename(kind, i, new_description));
+                }
+                return;
+            }
+            None if self.editing_description.as_ref().is_some_and(|(k, j, _)| *k == kind && *j == i) => return,
+            None => {}
+        }
+
+        if ui.add_enabled(i > 0, egui::Button::new("⬆").small()).on_hover_text("Move up").clicked() {
+            *action = Some(EntryAction::Move(kind, i, i - 1));
+        }
+        if ui.add_enabled(i + 1 < len, egui::Button::new("⬇").small()).on_hover_text("Move down").clicked() {
+            *action = Some(EntryAction::Move(kind, i, i + 1));
+        }
+        if ui.small_button("✏").on_hover_text("Rename").clicked() {
+            let description = match kind {
+                EntryKind::Url => self.uptime_urls[i].description.clone(),
+                EntryKind::Backup => self.backups[i].description.clone(),
+            };
+            self.editing_description = Some((kind, i, description));
+        }
+    }
+
+    fn apply_entry_action(&mut self, action: EntryAction) {
+        match action {
+            EntryAction::Move(kind, from, to) => self.move_entry(kind, from, to),
+            EntryAction::Rename(kind, i, new_description) => self.rename_entry(kind, i, &new_description),
+        }
+    }
+
+    /** where restore point j of backup i is stored */
+    fn restore_point_path(&self, i: usize, j: usize) -> String {
+        let backup = &self.backups[i];
+        backup
+            .folder
+            .clone()
+            .unwrap_or_default()
+            .join(&backup.logs[j].filename)
+            .display()
+            .to_string()
+    }
+
+    /** uploads restore point j of backup i to its restore targets and logs the result */
+    fn restore_point(&mut self, i: usize, j: usize) {
+        let path = self.restore_point_path(i, j);
+
+        let token_to_use = self.bearer_token();
+
+        let restore_attempt = restore_backup(
+            &self.backups[i],
+            &path,
+            self.backups[i].logs[j].archive.as_deref().and_then(ArchiveFormat::parse),
+            &token_to_use,
+            &self.http_settings,
+        );
+
+        match restore_attempt {
+            Ok((target, failed_targets)) => {
+                for failed in failed_targets {
+                    push_internal_log(&mut self.internal_log, InternalLogEntry::new(
+                        LogLevel::Warn,
+                        format!("Restore of {} failed, tried the next target: {}", self.backups[i].logs[j].filename, failed),
+                    ));
+                }
+
+                //the upload was accepted, check it was applied if we know how
+                let verification = match &self.backups[i].verify_url {
+                    Some(verify_url) => send_request(
+                        verify_url,
+                        basic_auth(&self.backups[i].username, &self.backups[i].password),
+                        None,
+                        None,
+                        &self.http_settings,
+                    )
+                        .map(|_| format!(" (verified at {})", verify_url)),
+                    None => Ok(String::new()),
+                };
+
+                let (level, message) = match verification {
+                    Ok(verified) => {
+                        info!("Restored file successfully");
+                        let message = format!(
+                            "Successfully restored file {} from {} to {}{}",
+                            self.backups[i].logs[j].filename,
+                            self.backups[i].description,
+                            target,
+                            verified
+                        );
+                        (LogLevel::Info, message)
+                    }
+                    Err(err) => {
+                        error!("Restore verification failed: {}", err);
+                        let message = format!(
+                            "Restore of {} from {} uploaded but verification failed: {}",
+                            self.backups[i].logs[j].filename,
+                            self.backups[i].description,
+                            err
+                        );
+                        (LogLevel::Error, message)
+                    }
+                };
+
+                //add the restored file to the internal log
+
+                let log_entry = InternalLogEntry::new(level, message);
+
+                push_internal_log(&mut self.internal_log, log_entry);
+            }
+            Err(err) => {
+                error!("Restore failed: {}", err);
+
+                //add the error to the internal log
+
+                let log_entry = InternalLogEntry::new(
+                    LogLevel::Error,
+                    format!(
+                        "Failed to restore file {} from {}: {}",
+                        self.backups[i].logs[j].filename,
+                        self.backups[i].description,
+                        err
+                    ),
+                );
+
+                push_internal_log(&mut self.internal_log, log_entry);
+            }
+        }
+
+        self.save_internal_log();
+
+        debug!(
+            "Restoring {}",
+            self.backups[i].logs[j].filename
+        )
+    }
+
+    /** takes a safety backup of the current state and restores restore point j of backup i
+    once it has finished, so the overwritten data can be restored in turn */
+    fn restore_with_safety_backup(&mut self, i: usize, j: usize) {
+        let backup = &self.backups[i];
+        let filename = backup.logs[j].filename.clone();
+        let message = format!(
+            "Backing up {} before restoring {}, the restore starts when the backup is done",
+            backup.description, filename
+        );
+        info!("{}", message);
+
+        self.restores_after_backup.push((backup.id.clone(), filename));
+        self.add_internal_log(LogLevel::Info, message);
+        self.queue_backup(i, Utc::now());
+    }
+
+    /** runs the restores that waited for a safety backup of `id`. They are dropped when that
+    backup failed, restoring without it would leave nothing to go back to */
+    fn restore_after_backup(&mut self, id: &str, backed_up: bool) {
+        let waiting: Vec<String> = self
+            .restores_after_backup
+            .iter()
+            .filter(|(backup_id, _)| backup_id == id)
+            .map(|(_, filename)| filename.clone())
+            .collect();
+        if waiting.is_empty() {
+            return;
+        }
+        self.restores_after_backup.retain(|(backup_id, _)| backup_id != id);
+
+        let Some(i) = self.backups.iter().position(|backup| backup.id == id) else {
+            return;
+        };
+
+        for filename in waiting {
+            if !backed_up {
+                let message = format!(
+                    "Restore of {} to {} cancelled: the safety backup failed, nothing was overwritten",
+                    filename, self.backups[i].description
+                );
+                error!("{}", message);
+                self.add_internal_log(LogLevel::Error, message);
+                continue;
+            }
+
+            match self.backups[i].logs.iter().position(|entry| entry.filename == filename) {
+                Some(j) => self.restore_point(i, j),
+                None => {
+                    let message = format!(
+                        "Restore of {} to {} cancelled: it is no longer in the backup log",
+                        filename, self.backups[i].description
+                    );
+                    error!("{}", message);
+                    self.add_internal_log(LogLevel::Error, message);
+                }
+            }
+        }
+
+        // the restored file was kept out of the rotation while it waited
+        self.remove_backups_over_limit(id);
+    }
+
+    /** (backup index, log index) of a restore point, None once it has been deleted or rotated out */
+    fn find_restore_point(&self, id: &str, filename: &str) -> Option<(usize, usize)> {
+        let i = self.backups.iter().position(|backup| backup.id == id)?;
+        let j = self.backups[i].logs.iter().position(|entry| entry.filename == filename)?;
+        Some((i, j))
+    }
+
+    /** deletes a single restore point from disk and from the backup log */
+    fn delete_restore_point(&mut self, i: usize, j: usize) {
+        let backup = &mut self.backups[i];
+        let filename = backup.logs[j].filename.clone();
+        let Some(folder) = backup.folder.clone() else {
+            return;
+        };
+
+        let (level, message) = match delete_file(&filename, &folder) {
+            Ok(()) => {
+                backup.logs.remove(j);
+                write_backup_log(&folder, &backup.logs);
+
+                (LogLevel::Info, format!("Deleted restore point {} from {}", filename, backup.description))
+            }
+            Err(err) => (
+                LogLevel::Error,
+                format!("Failed to delete restore point {} from {}: {}", filename, backup.description, err),
+            ),
+        };
+
+        info!("{}", message);
+        push_internal_log(&mut self.internal_log, InternalLogEntry::new(level, message));
+
+        self.save_internal_log();
+    }
+
+    /** applies the retention rules of a backup, returns the number of restore points removed */
+    fn remove_backups_over_limit(&mut self, id: &str) -> usize {
+        let mut removed = 0;
+
+        for backup in &mut self.backups {
+            if backup.id == id {
+                let Some(folder) = backup.folder.clone() else {
+                    continue;
+                };
+
+                // a restore point waiting to be restored after a safety backup, or being test
+                // restored right now, stays until then
+                let expired: Vec<(String, String)> = retention::expired_entries(backup, Utc::now())
+                    .into_iter()
+                    .filter(|(filename, _)| {
+                        !self
+                            .restores_after_backup
+                            .iter()
+                            .chain(&self.test_restoring)
+                            .any(|(id, in_use)| *id == backup.id && in_use == filename)
+                    })
+                    .collect();
+
+                if !expired.is_empty() {
+                    info!("There are {} backups to rotate out", expired.len());
+                }
+
+                for (filename, reason) in expired {
+                    let delete_attempt = delete_file(&filename, &folder);
+
+                    match delete_attempt {
+                        Ok(()) => info!("Deleted {} from {} ({})", filename, backup.description, reason),
+                        Err(err) => {
+                            warn!("file delete fail: {}", err);
+
+                            // a file that is already gone only needs its log entry removed,
+                            // anything else is retried on the next rotation
+                            if folder.join(&filename).exists() {
+                                continue;
+                            }
+                        }
+                    }
+
+                    backup.logs.retain(|entry| entry.filename != filename);
+                    removed += 1;
+
+                    //save the log file again, after each delete so a crash leaves a consistent log
+                    write_backup_log(&folder, &backup.logs);
+                }
+            }
+        }
+
+        removed
+    }
+}
+
+#[cfg(test)]
+mod rotation_tests {
+    use super::*;
+
+    #[test]
+    fn lowering_max_deletes_every_backup_over_it_in_one_pass() {
+        let folder = std::env::temp_dir().join(format!("wss-rotation-{}", Uuid::new_v4()));
+        create_dir_all(&folder).unwrap();
+
+        let first = Utc::now() - chrono::Duration::hours(20);
+        let logs: Vec<LogEntry> = (0..20)
+            .map(|n| {
+                let filename = format!("backup_{:02}.sql", n);
+                write(folder.join(&filename), "dump").unwrap();
+                LogEntry {
+                    filename,
+                    timestamp: (first + chrono::Duration::hours(n)).to_rfc3339(),
+                    size: 4,
+                    duration_ms: None,
+                    archive: None,
+                    sha256: None,
+                    server_checksum: None,
+                }
+            })
+            .collect();
+
+        let mut app = StatusChecker {
+            backups: vec![BackupEntry {
+                description: "Rotation".to_string(),
+                id: "rotation".to_string(),
+                max: 20,
+                logs,
+                folder: Some(folder.clone()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(app.remove_backups_over_limit("rotation"), 0);
+
+        app.backups[0].max = 5;
+        let removed = app.remove_backups_over_limit("rotation");
+        let kept: Vec<String> = app.backups[0].logs.iter().map(|entry| entry.filename.clone()).collect();
+        let on_disk = (0..20).filter(|n| folder.join(format!("backup_{:02}.sql", n)).exists()).count();
+        let logged = load_log(&folder).map(|log| log.entries.len());
+        let _ = remove_dir_all(&folder);
+
+        assert_eq!(removed, 15);
+        assert_eq!(kept, (15..20).map(|n| format!("backup_{:02}.sql", n)).collect::<Vec<_>>());
+        assert_eq!(on_disk, 5);
+        assert_eq!(logged.ok(), Some(5));
+    }
+}
+
+#[derive(Deserialize)]
+struct Config {
+    url_uptime_settings: UptimeUrlSettings,
+    warning_settings: WarningSettings,
+    #[serde(default)] // If "urls" is missing, it will default to an empty Vec<UrlEntry>
+    urls: Vec<UrlEntry>,
+    #[serde(default)] // If "backups" is missing, it will default to an empty Vec<BackupEntry>
+    backups: Vec<BackupEntry>,
+    token: String,
+    secret: String,
+    jwt_expiry: u64,
+    #[serde(default)] // Keeps the order the claims were written in
+    payload: TomlTable,
+    smtp: SmtpConfig,
+    #[serde(default)] // used until the toggle has been changed once, see state.toml
+    backup_enabled_on_start: bool,
+    #[serde(default)]
+    data_dir: String, // base for the state files and a relative backup_root, "" is the working directory
+    #[serde(default = "default_backup_root")]
+    backup_root: String, // every backup folder lives below this directory
+    #[serde(default)]
+    backup_jitter_seconds: u32, // scheduled backups start up to this many seconds into their minute
+    #[serde(default)]
+    backup_enable_grace_minutes: u32, // scheduled backups don't run this soon after enabling the schedule
+    #[serde(default)]
+    run_on_start: bool, // one uptime sweep and the overdue backups right after launch
+    #[serde(default = "default_max_concurrent_backups")]
+    max_concurrent_backups: usize, // backups running at the same time, the rest wait in a queue
+    #[serde(default)]
+    integrity_check_hours: Option<u32>, // re-hash stored backups and compare with log.toml this often
+    #[serde(default)]
+    timezone: Option<String>, // IANA name used for backup schedules and shown times, unset is UTC and the system's zone
+    #[serde(default)]
+    monthly_day_base: u32, // 1: a monthly time counts from the 1st, 0 (older configs): from the day before
+    #[serde(default = "default_internal_log_format")]
+    internal_log_format: String, // "toml" or "jsonl"
+    #[serde(default)]
+    http: HttpSettings,
+    #[serde(default)]
+    maintenance_windows: Vec<MaintenanceWindow>,
+    #[serde(default)]
+    health: HealthSettings,
+}
+
+
+
+fn main() -> eframe::Result<()> {
+    // RUST_LOG controls verbosity, e.g. RUST_LOG=debug or RUST_LOG=websync_station=warn
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
+
+    let mut headless = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--headless" => headless = true,
+            "--data-dir" => {
+                let Some(dir) = args.next() else {
+                    error!("--data-dir needs a folder");
+                    return Ok(());
+                };
+                if let Err(e) = set_data_dir(&dir) {
+                    error!("Could not create the data folder {}: {}", dir, e);
+                    return Ok(());
+                }
+            }
+            "--import-urls" => {
+                let Some(file) = args.next() else {
+                    error!("--import-urls needs a CSV or JSON file");
+                    return Ok(());
+                };
+                let imported = load_config()
+                    .map_err(Box::from)
+                    .and_then(|config| import_url_file(Path::new(&file), &config.urls));
+                match imported {
+                    Ok((_, summary)) => info!("{}", summary),
+                    Err(e) => error!("Could not import URLs from {}: {}", file, e),
+                }
+                return Ok(());
+            }
+            "--print-systemd-unit" => {
+                match service::systemd_unit() {
+                    Ok(unit) => print!("{}", unit),
+                    Err(e) => error!("Could not build the systemd unit: {}", e),
+                }
+                return Ok(());
+            }
+            #[cfg(windows)]
+            "--install-service" => {
+                match service::install() {
+                    Ok(()) => info!("Installed the '{}' service", service::SERVICE_NAME),
+                    Err(e) => error!("Could not install the service: {}", e),
+                }
+                return Ok(());
+            }
+            #[cfg(windows)]
+            "--uninstall-service" => {
+                match service::uninstall() {
+                    Ok(()) => info!("Removed the '{}' service", service::SERVICE_NAME),
+                    Err(e) => error!("Could not remove the service: {}", e),
+                }
+                return Ok(());
+            }
+            #[cfg(windows)]
+            "--service" => {
+                // started by the service manager, which uses System32 as the working directory
+                if let Err(e) = service::enter_exe_dir() {
+                    error!("Could not switch to the program folder: {}", e);
+                }
+                if let Err(e) = service::run() {
+                    error!("Could not start as a service: {}", e);
+                }
+                return Ok(());
+            }
+            other => {
+                error!("Unknown argument '{}'", other);
+                return Ok(());
+            }
+        }
+    }
+
+    let config_path = Path::new("config.toml");
+    let mut created_default = false;
+
+    if let Err(e) = load_config() {
+        warn!("Could not load 'config.toml': {}", e);
+
+        if matches!(&e, ConfigError::Missing(path) if path == config_path) {
+            warn!("'config.toml' not found. Attempting to create a default one.");
+            match write(config_path, default_config::DEFAULT_CONFIG_TOML) {
+                Ok(_) => {
+                    info!("Successfully created 'config.toml' with default settings.");
+                    info!("Please review and edit 'config.toml' then restart the application.");
+                    if headless {
+                        return Ok(());
+                    }
+                    created_default = true;
+                }
+                Err(e) => {
+                    error!("Could not write default 'config.toml': {}", e);
+                }
+            }
+        }
+    }
+
+    if headless {
+        let mut app = match StatusChecker::headless() {
+            Ok(app) => app,
+            Err(e) => {
+                error!("Failed to load config: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+        // Ctrl+C and SIGTERM (systemctl stop) shut down like closing the window does
+        let shutting_down = app.shutting_down.clone();
+        if let Err(e) = ctrlc::set_handler(move || shutting_down.store(true, Ordering::SeqCst)) {
+            warn!("Could not install the shutdown handler: {}", e);
+        }
+
+        app.run_headless();
+        return Ok(());
+    }
+ 
+
+
+    let options = eframe::NativeOptions {
+        viewport: ViewportBuilder::default().with_inner_size(Vec2::new(800.0, 600.0)),
+        ..Default::default()
+    };
+
+    eframe::run_native(
+        "WebSync Station",
+        options,
+        Box::new(move |_cc| {
+            // the console is hidden on Windows, so anything main() logs has to be shown in the window too
+            if created_default {
+                return Box::new(StartupNotice {
+                    heading: "A default config.toml was created".to_string(),
+                    message: "There was no config.toml, so one with example settings was created. \
+                              Edit it to list your own URLs and backups, then start WebSync Station again."
+                        .to_string(),
+                    is_error: false,
+                });
+            }
+
+            let mut app = match StatusChecker::from_config() {
+                Ok(app) => app,
+                Err(err) => {
+                    error!("Failed to load config: {}", err);
+                    return Box::new(StartupNotice {
+                        heading: "WebSync Station could not load config.toml".to_string(),
+                        message: format!("{}\n\nFix the problem and start WebSync Station again.", err),
+                        is_error: true,
+                    });
+                }
+            };
+
+
+
+            if app.internal_log.is_empty(){
+                app.internal_log.push(InternalLogEntry::new(
+                    LogLevel::Info,
+                    "Welcome to WebSync Station. If this is your first time using WWS remember to edit the config.toml file and then restart the app.".to_string(),
+                ));
+            }
+
+
+            app.backup_trigger_rx = spawn_ticker(app.shutting_down.clone());
+
+            Box::new(app)
+        }),
+    )
+}
+
+/** shown instead of the main window when config.toml can't be loaded or was just created */
+struct StartupNotice {
+    heading: String,
+    message: String,
+    is_error: bool,
+}
+
+impl eframe::App for StartupNotice {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading(&self.heading);
+            ui.add_space(10.0);
+
+            egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                let message = RichText::new(&self.message).monospace();
+                ui.label(if self.is_error { message.color(Color32::LIGHT_RED) } else { message });
+            });
+
+            ui.add_space(10.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("Open config folder").clicked() {
+                    if let Err(e) = open_config_folder() {
+                        self.message = format!("{}\n\nCould not open the config folder: {}", self.message, e);
+                    }
+                }
+                if ui.button("Close").clicked() {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            });
+        });
+    }
+}
+
+/** opens the folder config.toml is read from (the working directory) in the file manager */
+fn open_config_folder() -> Result<(), String> {
+    let folder = std::env::current_dir().map_err(|e| e.to_string())?;
+    opener::open(&folder).map_err(|e| e.to_string())
+}
+
+impl eframe::App for StatusChecker {
+    //this runs several times a second
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if ctx.input(|i| i.viewport().close_requested()) {
+            // no new backups are started, on_exit waits for the running ones
+            self.shutting_down.store(true, Ordering::SeqCst);
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                let ticks: Vec<DateTime<Utc>> = self.backup_trigger_rx.try_iter().collect();
+
+                if !ticks.is_empty() && !self.shutting_down.load(Ordering::SeqCst) {
+                    self.handle_ticks(&ticks);
+                }
+
+                // wait for the first frame to be drawn, so the window isn't blank while this runs
+                if self.run_on_start_pending && ctx.frame_nr() > 0 && !self.shutting_down.load(Ordering::SeqCst) {
+                    self.run_on_start_pending = false;
+                    self.run_on_start();
+                }
+
+                self.process_backup_queue();
+                self.process_integrity_reports();
+                self.process_test_restores();
+                self.process_auth_tests();
+                self.process_alert_test_results();
+                self.process_uptime_results();
+
+                ctx.request_repaint_after(Duration::from_secs(1)); // keep UI responsive
+
+                // if ui.button("Test autobackup").clicked() {
+                //     self.auto_backup();
+                // }
+
+                // if ui.button("Test internal log").clicked() {
+                //     let entry = InternalLogEntry {
+                //         message: "Test message".to_string(),
+                //         timestamp: Utc::now().to_rfc3339(),
+                //     };
+
+                //     self.internal_log.push(entry);
+
+                //     print_to_internal_log_file(InternalLog {
+                //         entries: self.internal_log.clone(),
+                //     });
+                // }
+
+                //This is inside the closure, and it is the main loop of the app.
+
+                ui.heading("WebSync Station");
+
+                if let Some(since) = self.offline_since {
+                    ui.label(
+                        RichText::new(format!(
+                            "Host appears offline since {}: per-URL alerts are paused",
+                            since.format("%H:%M UTC")
+                        ))
+                        .color(Color32::RED)
+                        .strong(),
+                    );
+                }
+
+                if let Some(window) = self.active_maintenance() {
+                    ui.label(
+                        RichText::new(format!(
+                            "Maintenance mode active ({}): failures are logged but no warnings are sent",
+                            window.description
+                        ))
+                        .color(Color32::YELLOW)
+                        .strong(),
+                    );
+                }
+
+                ui.add_space(10.0);
+                let url_length = self.uptime_urls.len();
+                let mut entry_action = None;
+
+                ui.horizontal(|ui| {
+                    ui.vertical(|ui| {
+                        let mut i = 0;
+
+                        loop {
+
+                            if i >= url_length {
+                                break;
+                            }
+
+
+                            ui.horizontal(|ui| {
+                                // ui.add_space(ui.available_width() - 80.0); // push the button right
+
+                                ui.add_space(10.0);
+
+                                if let Some(error) = &self.uptime_urls[i].invalid {
+                                    ui.add(egui::Button::new("?").fill(Color32::GRAY))
+                                        .on_hover_text(format!("Invalid URL, not checked: {}", error));
+                                } else {
+                                    let color = if self.uptime_urls[i].is_ok {
+                                        Color32::from_rgb(0, 200, 0) // Green
+                                    } else {
+                                        Color32::from_rgb(200, 0, 0) // Red
+                                    };
+
+                                    let text = if self.uptime_urls[i].is_ok {
+                                        "✅"
+                                    } else {
+                                        "❌"
+                                    };
+                                    let button = egui::Button::new(text).fill(color);
+
+                                    ui.add(button);
+                                }
+
+                                let (mute_icon, mute_hover) = if self.uptime_urls[i].muted {
+                                    ("🔇", "Alerts muted: still checked, but never warned about. Click to unmute")
+                                } else {
+                                    ("🔔", "Mute alerts for this URL, e.g. a flaky third-party endpoint")
+                                };
+                                if ui.small_button(mute_icon).on_hover_text(mute_hover).clicked() {
+                                    self.toggle_url_mute(i);
+                                }
+
+                                let is_editing = self
+                                    .editing_description
+                                    .as_ref()
+                                    .is_some_and(|(kind, j, _)| *kind == EntryKind::Url && *j == i);
+                                if !is_editing {
+                                    ui.label(self.uptime_urls[i].description.to_string());
+                                }
+
+                                if let Some(percent) = self.uptime_stats.uptime_percent(&self.uptime_urls[i].url) {
+                                    ui.label(
+                                        RichText::new(format!(
+                                            "{:.1}% ({} d)",
+                                            percent,
+                                            self.uptime_url_settings.stats_window_days()
+                                        ))
+                                        .weak(),
+                                    )
+                                    .on_hover_text("Share of successful checks, see stats.toml");
+                                }
+
+                                if let Some(expires) = self.uptime_urls[i].cert_expires {
+                                    let days_left = (expires - Utc::now()).num_days();
+                                    let text = RichText::new(format!("cert {} d", days_left.max(0)));
+                                    let text = if days_left < self.uptime_url_settings.cert_warn_days() as i64 {
+                                        text.color(LogLevel::Warn.color())
+                                    } else {
+                                        text.weak()
+                                    };
+                                    ui.label(text).on_hover_text(format!(
+                                        "TLS certificate expires {}",
+                                        expires.format("%Y-%m-%d %H:%M UTC")
+                                    ));
+                                }
+
+                                self.entry_controls(ui, EntryKind::Url, i, url_length, &mut entry_action);
+                            });
+
+                            i += 1;
+                            if i >= url_length {
+                                break;
+                            };
+                        }
+                    });
+
+                    ui.add_space(10.0);
+
+                    ui.horizontal(|ui| {
+                        ui.label("Log height");
+                        let height = ui.add(egui::Slider::new(&mut self.log_panel_height, LOG_PANEL_HEIGHTS).suffix(" px"));
+                        ui.label("Font size");
+                        let font = ui.add(egui::Slider::new(&mut self.log_font_size, LOG_FONT_SIZES).step_by(1.0));
+                        let reset = ui.button("Reset").on_hover_text("Back to the default height and font size").clicked();
+                        if reset {
+                            self.log_panel_height = DEFAULT_LOG_PANEL_HEIGHT;
+                            self.log_font_size = DEFAULT_LOG_FONT_SIZE;
+                        }
+
+                        // saved once a drag ends, not on every frame of it
+                        let changed = |response: &egui::Response| response.changed() && !response.dragged();
+                        if reset || changed(&height) || height.drag_stopped() || changed(&font) || font.drag_stopped() {
+                            self.save_state();
+                        }
+                    });
+
+                    Frame::none()
+                        .fill(Color32::from_rgb(30, 30, 30))
+                        .stroke(Stroke::new(1.0, Color32::WHITE))
+                        .rounding(Rounding::same(4.0))
+                        .inner_margin(Vec2::splat(6.0))
+                        .show(ui, |ui_frame| {
+                            let dynamic_content_width = ui_frame.available_width();
+                            let desired_scroll_area_size = egui::vec2(dynamic_content_width, self.log_panel_height);
+
+                            ui_frame.allocate_ui_with_layout(
+                                desired_scroll_area_size,
+                                Layout::top_down(Align::Min),
+                                |ui_for_scroll_area| {
+                                    // Start building the ScrollArea
+                                    let scroll_area_builder = ScrollArea::vertical()
+                                        .auto_shrink([false, false])
+                                        .stick_to_bottom(true);
+
+                                    // Now show the (potentially modified) ScrollArea
+                                    scroll_area_builder.show(
+                                        ui_for_scroll_area,
+                                        |ui_scroll_content| {
+                                            let interal_log_length = self.internal_log.len();
+                                            for i in 0..interal_log_length {
+                                                ui_scroll_content.add(
+                                                    Label::new(
+                                                        RichText::new(format!(
+                                                            "{} - {}",
+                                                            format_log_timestamp(
+                                                                &self.internal_log[i].timestamp,
+                                                                self.display_timezone
+                                                            ),
+                                                            self.internal_log[i].text()
+                                                        ))
+                                                        .monospace()
+                                                        .size(self.log_font_size)
+                                                        .color(self.internal_log[i].level.color()),
+                                                    )
+                                                    .wrap(true),
+                                                );
+                                            }
+                                        },
+                                    );
+                                },
+                            );
+                        });
+                });
+
+                /* Add a button for manually checking all the URLs */
+
+                ui.add_space(10.0);
+
+                ui.horizontal(|ui| {
+                    let checking = self.uptime_sweep.is_some();
+
+                    if ui
+                        .add_enabled(!checking, Button::new("Manually check all urls"))
+                        .on_disabled_hover_text("A check is running")
+                        .clicked()
+                    {
+                        self.uptime_check();
+                    }
+
+                    let failing = self.failing_urls();
+                    if ui
+                        .add_enabled(!checking && !failing.is_empty(), Button::new("Recheck failing only"))
+                        .on_hover_text("Check just the URLs that are down, e.g. to confirm they recovered")
+                        .on_disabled_hover_text(if checking { "A check is running" } else { "No URL is down" })
+                        .clicked()
+                    {
+                        self.uptime_check_urls(&failing);
+                    }
+
+                    if ui
+                        .add_enabled(!failing.is_empty(), Button::new("Copy failing URLs"))
+                        .on_hover_text("Copy the description and URL of every URL that is down")
+                        .on_disabled_hover_text("No URL is down")
+                        .clicked()
+                    {
+                        let lines: Vec<String> = failing
+                            .iter()
+                            .map(|&i| format!("{} {}", self.uptime_urls[i].description, self.uptime_urls[i].url))
+                            .collect();
+                        ui.output_mut(|o| o.copied_text = lines.join("\n"));
+                    }
+
+                    ui.add_space(10.0);
+
+                    if let Some(sweep) = &self.uptime_sweep {
+                        ui.spinner();
+                        ui.label(format!("Checked {}/{}", sweep.done, sweep.total));
+                    }
+
+                    if ui.button("Test email").clicked() {
+                        self.send_test_email();
+                    }
+
+                    if ui.button("Test webhook").clicked() {
+                        self.send_test_webhooks();
+                    }
+
+                    let last_warning = self.last_sent_warning.as_ref().map(|last| {
+                        format!(
+                            "Send \"{}\" from {} again, marked as a replay and not counted against daily_max",
+                            last.subject,
+                            format_in_zone(last.sent_at, self.display_timezone, "%H:%M")
+                        )
+                    });
+                    if ui
+                        .add_enabled(last_warning.is_some(), Button::new("Replay last warning"))
+                        .on_hover_text(last_warning.unwrap_or_default())
+                        .on_disabled_hover_text("No warning has been sent since WSS started")
+                        .clicked()
+                    {
+                        self.replay_last_warning();
+                    }
+                });
+
+                for (success, message) in &self.alert_test_results {
+                    let color = if *success {
+                        Color32::GREEN
+                    } else {
+                        Color32::RED
+                    };
+                    ui.label(RichText::new(message).color(color));
+                }
+
+                egui::CollapsingHeader::new("Import URLs (CSV or JSON)").show(ui, |ui| {
+                    ui.label("One \"description,url\" pair per line, or a JSON array of {\"description\", \"url\"} objects");
+                    ui.horizontal(|ui| {
+                        ui.label("File");
+                        ui.text_edit_singleline(&mut self.import_path);
+                        if ui
+                            .add_enabled(!self.import_path.trim().is_empty(), Button::new("Import URLs"))
+                            .on_hover_text("Adds the new URLs to config.toml and starts checking them. URLs already monitored are skipped")
+                            .clicked()
+                        {
+                            self.import_urls();
+                        }
+                    });
+
+                    if let Some((success, message)) = &self.import_result {
+                        let color = if *success { Color32::GREEN } else { Color32::RED };
+                        ui.label(RichText::new(message).color(color));
+                    }
+                });
+
+                egui::CollapsingHeader::new("Alert settings (email and webhooks)")
+                    .show(ui, |ui| self.alert_settings_ui(ui));
+
+                self.expire_snooze();
+
+                ui.horizontal(|ui| match self.snooze_until {
+                    Some(until) => {
+                        let remaining = (until - Utc::now()).num_minutes() + 1;
+                        ui.label(
+                            RichText::new(format!("Alerts snoozed, {} min left", remaining))
+                                .color(Color32::YELLOW),
+                        );
+
+                        if ui.button("Cancel snooze").clicked() {
+                            self.snooze_until = None;
+                            self.add_internal_log(LogLevel::Info, "Alert snooze cancelled".to_string());
+                        }
+                    }
+                    None => {
+                        if ui
+                            .button(format!("Snooze alerts for {} minutes", self.snooze_minutes))
+                            .clicked()
+                        {
+                            let until = Utc::now() + chrono::Duration::minutes(self.snooze_minutes as i64);
+                            self.snooze_until = Some(until);
+                            self.add_internal_log(LogLevel::Info, format!(
+                                "Alerts snoozed until {}",
+                                until.format("%H:%M UTC")
+                            ));
+                        }
+
+                        ui.add(egui::DragValue::new(&mut self.snooze_minutes).clamp_range(1..=1440).suffix(" min"));
+                    }
+                });
+
+                self.expire_pause();
+
+                ui.horizontal(|ui| match self.paused_until {
+                    Some(until) => {
+                        let remaining = until - Utc::now();
+                        ui.label(
+                            RichText::new(format!(
+                                "Everything paused, resumes in {}:{:02}",
+                                remaining.num_minutes(),
+                                remaining.num_seconds() % 60
+                            ))
+                            .color(Color32::YELLOW)
+                            .strong(),
+                        );
+
+                        if ui.button("Resume now").clicked() {
+                            self.resume_all("Pause cancelled");
+                        }
+                    }
+                    None => {
+                        if ui
+                            .button(format!("Pause everything for {} minutes", self.pause_minutes))
+                            .on_hover_text("No uptime checks or scheduled backups until the time is up. Manual actions still work")
+                            .clicked()
+                        {
+                            self.pause_all(self.pause_minutes);
+                        }
+
+                        ui.add(egui::DragValue::new(&mut self.pause_minutes).clamp_range(1..=1440).suffix(" min"));
+                    }
+                });
+
+                //for testing and making the compliler shut up...
+
+                // let jwt_string: String;
+
+                // if ui.button("Click to make JWT").clicked() {
+                //     let jwt_result = create_jwt(&self.payload, &self.secret, &self.jwt_expiry);
+
+                //     match jwt_result {
+                //         Ok(jwt) => {
+                //             jwt_string = jwt;
+                //         }
+
+                //         Err(_err) => {
+                //             jwt_string = String::from("error");
+                //         }
+                //     }
+
+                //     println!("{}", &jwt_string)
+                // }
+
+                ui.add_space(10.0);
+
+                ui.separator();
+
+                ui.add_space(10.0);
+
+                ui.horizontal(|ui| {
+                    ui.heading("Backup system");
+
+                    let enable_caption = if self.backup_enabled {
+                        "Disable backup schedule"
+                    } else {
+                        "Enable backup schedule"
+                    };
+
+                    if ui.button(enable_caption).clicked() {
+                        self.backup_enabled = !self.backup_enabled;
+                        if self.backup_enabled {
+                            self.backup_enabled_at = Some(Utc::now());
+                        }
+                        self.save_state();
+                    }
+
+                    let caption = if self.backup_enabled {
+                        RichText::new("Backup schedules enabled").color(Color32::GREEN)
+                    } else {
+                        RichText::new("Backup schedules disabled").color(Color32::RED)
+                    };
+
+                    ui.add_space(10.0);
+
+                    ui.label(caption);
+
+                    if !self.running_backups.is_empty() || !self.backup_queue.is_empty() {
+                        ui.add_space(10.0);
+                        ui.label(format!(
+                            "{} running, {} queued (max {} at once)",
+                            self.running_backups.len(),
+                            self.backup_queue.len(),
+                            self.max_concurrent_backups.max(1)
+                        ));
+                    }
+                });
+
+                ui.separator();
+                //Backup system ui
+
+                let backup_length = self.backups.len();
+                let max_backups = 10;
+                // println!("There are {} backups", backup_length);
+                let mut i = 0;
+
+                loop {
+                    if i >= backup_length || i > max_backups {
+                        break;
+                    }
+
+                    let log_entries_length = self.backups[i].logs.len();
+
+                    ui.horizontal(|ui| {
+                        let is_editing = self
+                            .editing_description
+                            .as_ref()
+                            .is_some_and(|(kind, j, _)| *kind == EntryKind::Backup && *j == i);
+                        if !is_editing {
+                            ui.label(RichText::new(&self.backups[i].description).strong());
+                        }
+
+                        self.entry_controls(ui, EntryKind::Backup, i, backup_length, &mut entry_action);
+
+                        ui.add_space(10.0);
+
+                        ui.label(format!("Restore points available: {}", log_entries_length));
+                        ui.add_space(10.0);
+
+                        let last_success = match last_backup_success(&self.backups[i]) {
+                            Some(last) => format_timestamp(&last.to_rfc3339(), self.display_timezone),
+                            None => "never".to_string(),
+                        };
+                        ui.label(format!("Last success: {}", last_success));
+
+                        if is_backup_stale(&self.backups[i], Utc::now()) {
+                            ui.label(RichText::new("STALE").color(Color32::RED).strong())
+                                .on_hover_text("No successful backup within max_staleness_hours");
+                        }
+
+                        if let Some(free) = self.backups[i].free_space {
+                            let text = format!("Free: {}", format_bytes(free));
+                            match self.backups[i].min_free_bytes {
+                                Some(min_free) if free < min_free => {
+                                    ui.label(RichText::new(text).color(Color32::RED))
+                                        .on_hover_text(format!("Less than min_free_bytes ({})", format_bytes(min_free)));
+                                }
+                                _ => {
+                                    ui.label(text).on_hover_text("Free space on the disk of the backup folder");
+                                }
+                            }
+                        }
+                        ui.add_space(10.0);
+
+                        let pending = self.is_backup_pending(&self.backups[i].id);
+                        if ui
+                            .add_enabled(!pending, Button::new("Backup manually now"))
+                            .on_disabled_hover_text("This backup is already queued or running")
+                            .clicked()
+                        {
+                            self.queue_backup(i, Utc::now());
+                        };
+
+                        if self.running_backups.contains(&self.backups[i].id) {
+                            ui.label(RichText::new("Running").color(Color32::LIGHT_BLUE));
+                        } else if pending {
+                            ui.label(RichText::new("Queued").color(Color32::YELLOW));
+                        }
+
+                        if ui
+                            .add_enabled(
+                                self.backups[i].command.is_none() && !self.backups[i].auth_testing,
+                                Button::new(if self.backups[i].auth_testing { "Testing auth..." } else { "Test auth" }),
+                            )
+                            .on_hover_text("Send a HEAD request with the credentials a backup would use, without downloading anything")
+                            .on_disabled_hover_text("This backup runs a command, there is no url to test")
+                            .clicked()
+                        {
+                            self.test_backup_auth(i);
+                        }
+
+                        let verifying = self.verifying_backups.contains(&self.backups[i].id);
+                        if ui
+                            .add_enabled(!verifying && self.backups[i].folder.is_some(), Button::new("Verify now"))
+                            .on_hover_text("Re-hash the stored restore points and compare them with the hashes in log.toml")
+                            .on_disabled_hover_text("Already verifying, or this backup has no usable folder")
+                            .clicked()
+                        {
+                            self.verify_backup(i);
+                        }
+
+                        if verifying {
+                            ui.label(RichText::new("Verifying").color(Color32::LIGHT_BLUE));
+                        } else if let Some((intact, summary)) = &self.backups[i].integrity {
+                            let color = if *intact { Color32::GREEN } else { Color32::RED };
+                            ui.label(RichText::new(if *intact { "Intact" } else { "DAMAGED" }).color(color))
+                                .on_hover_text(summary);
+                        }
+
+                        if self.backups[i].test_restore_url.is_some() {
+                            let test_restoring = self.is_test_restoring(&self.backups[i].id);
+                            if ui
+                                .add_enabled(!test_restoring, Button::new("Test restore now"))
+                                .on_hover_text("Restore the newest restore point to test_restore_url and check test_restore_health_url, without waiting for test_restore_hours")
+                                .on_disabled_hover_text("A test restore of this backup is already running")
+                                .clicked()
+                            {
+                                self.test_restore_backup(i);
+                            }
+
+                            if test_restoring {
+                                ui.label(RichText::new("Test restoring").color(Color32::LIGHT_BLUE));
+                            } else if let Some((passed, summary)) = &self.backups[i].test_restore {
+                                let color = if *passed { Color32::GREEN } else { Color32::RED };
+                                ui.label(RichText::new(if *passed { "Restorable" } else { "RESTORE FAILED" }).color(color))
+                                    .on_hover_text(summary);
+                            }
+                        }
+
+                        if ui
+                            .add_enabled(self.backups[i].folder.is_some(), Button::new("Open folder"))
+                            .on_hover_text("Open the folder this backup is stored in")
+                            .on_disabled_hover_text("This backup has no usable folder, see the config warnings")
+                            .clicked()
+                        {
+                            self.open_backup_folder(i);
+                        }
+
+                        if ui
+                            .button("Clean up old backups")
+                            .on_hover_text("Remove the backups that max, max_age_days, max_total_bytes or gfs no longer keep, without downloading a new one")
+                            .clicked()
+                        {
+                            self.clean_up_backup(i);
+                        }
+
+                        ui.add_space(10.0);
+
+                        let pause_caption = if self.backups[i].enabled {
+                            "Pause schedule"
+                        } else {
+                            "Resume schedule"
+                        };
+
+                        if ui.button(pause_caption).clicked() {
+                            self.backups[i].enabled = !self.backups[i].enabled;
+                            self.save_state();
+                        }
+
+                        if !self.backups[i].enabled {
+                            ui.label(RichText::new("Paused").color(Color32::YELLOW));
+                        }
+                    });
+
+                    if let Some((success, message)) = &self.backups[i].auth_test {
+                        let color = if *success { Color32::GREEN } else { Color32::RED };
+                        ui.label(RichText::new(message).color(color));
+                    }
+
+                    ui.horizontal(|ui| {
+                        if log_entries_length > 0 {
+                            ui.collapsing(
+                                format!("Restore {}", self.backups[i].description),
+                                |ui| {
+                                    ui.checkbox(&mut self.redact_curl_token, "Redact token in copied curl commands");
+
+                                    // only the rows in view are built, so hundreds of restore points stay cheap
+                                    let row_height = ui.spacing().interact_size.y;
+                                    let rows = self.backups[i].logs.len();
+
+                                    ScrollArea::vertical()
+                                        .id_source(("restore_points", &self.backups[i].id))
+                                        .max_height(300.0)
+                                        .show_rows(ui, row_height, rows, |ui, row_range| {
+                                            for j in row_range {
+                                                ui.horizontal(|ui| {
+                                                    let time_stamp = format_timestamp(
+                                                        &self.backups[i].logs[j].timestamp,
+                                                        self.display_timezone,
+                                                    );
+
+                                                    let size_kb =
+                                                        self.backups[i].logs[j].size as f64 / 1000.0;
+                                                    let mut size_str = format!("{:.1} KB", size_kb);
+
+                                                    if let Some(duration_ms) = self.backups[i].logs[j].duration_ms {
+                                                        let seconds = duration_ms as f64 / 1000.0;
+                                                        size_str.push_str(&format!(" in {:.1} s", seconds));
+
+                                                        if seconds > 0.0 {
+                                                            size_str.push_str(&format!(" ({:.1} KB/s)", size_kb / seconds));
+                                                        }
+                                                    }
+
+                                                    ui.label(format!("{}- Size:{}", time_stamp, size_str));
+
+                                                    let has_restore = !self.backups[i].restore.urls().is_empty();
+
+                                                    if ui
+                                                        .add_enabled(has_restore, Button::new("Restore"))
+                                                        .on_disabled_hover_text("No restore route is set for this backup")
+                                                        .clicked()
+                                                    {
+                                                        self.pending_restore = Some((
+                                                            self.backups[i].id.clone(),
+                                                            self.backups[i].logs[j].filename.clone(),
+                                                            self.backups[i].backup_before_restore,
+                                                        ));
+                                                    }
+
+                                                    if has_restore && ui
+                                                        .small_button("Copy curl")
+                                                        .on_hover_text("Copy a curl command that sends the same restore request")
+                                                        .clicked()
+                                                    {
+                                                        let path = self.restore_point_path(i, j);
+                                                        let token = if self.redact_curl_token {
+                                                            "<token>".to_string()
+                                                        } else {
+                                                            self.bearer_token()
+                                                        };
+
+                                                        let command = restore_curl_command(
+                                                            &self.backups[i],
+                                                            &path,
+                                                            self.backups[i].logs[j].archive.as_deref().and_then(ArchiveFormat::parse),
+                                                            &token,
+                                                            &self.http_settings,
+                                                        );
+                                                        ui.output_mut(|o| o.copied_text = command);
+                                                    }
+
+                                                    if ui.small_button("Delete").clicked() {
+                                                        self.pending_delete =
+                                                            Some((self.backups[i].id.clone(), self.backups[i].logs[j].filename.clone()));
+                                                    }
+                                                });
+                                            }
+                                        });
+                                },
+                            );
+                        }
+
+                        ui.add_space(10.0);
+
+                        let time_left = if self.backups[i].cron.is_some() {
+                            match &self.backups[i].schedule {
+                                Some(schedule) => calc_time_to_cron_backup(schedule, self.timezone),
+                                None => "never (invalid cron expression).".to_string(),
+                            }
+                        } else if interval_period_minutes(&self.backups[i].interval, self.backups[i].period_minutes).is_some() {
+                            format!(
+                                "{} ({} {})",
+                                calc_time_to_backup(&self.backups[i], self.timezone),
+                                describe_backup_schedule(&self.backups[i]),
+                                self.timezone
+                            )
+                        } else {
+                            "never (unknown interval).".to_string()
+                        };
+
+                        if self.backups[i].enabled {
+                            ui.vertical(|ui| ui.label(format!("Next backup in {}", time_left)));
+                        } else {
+                            ui.vertical(|ui| ui.label("Schedule paused"));
+                        }
+                    });
+
+                    ui.add_space(10.0);
+                    ui.separator();
+                    ui.add_space(10.0);
+
+                    i += 1;
+                }
+
+                if let Some(action) = entry_action {
+                    self.apply_entry_action(action);
+                }
+            });
+
+            //confirmation dialog for deleting a restore point
+            if let Some((id, filename)) = self.pending_delete.clone() {
+                // looked up again every frame, a backup finishing meanwhile may have rotated it out
+                let Some((i, j)) = self.find_restore_point(&id, &filename) else {
+                    self.pending_delete = None;
+                    self.add_internal_log(LogLevel::Warn, format!("{} was removed before the delete was confirmed, nothing was deleted", filename));
+                    return;
+                };
+
+                let mut confirmed = false;
+                let mut cancelled = false;
+
+                egui::Window::new("Delete restore point?")
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                    .show(ctx, |ui| {
+                        ui.label(format!(
+                            "This will permanently delete {} ({}) from {}.",
+                            self.backups[i].logs[j].filename,
+                            format_timestamp(&self.backups[i].logs[j].timestamp, self.display_timezone),
+                            self.backups[i].description
+                        ));
+
+                        ui.add_space(10.0);
+
+                        ui.horizontal(|ui| {
+                            if ui
+                                .button(RichText::new("Delete").color(Color32::RED))
+                                .clicked()
+                            {
+                                confirmed = true;
+                            }
+                            if ui.button("Cancel").clicked() {
+                                cancelled = true;
+                            }
+                        });
+                    });
+
+                if confirmed {
+                    self.delete_restore_point(i, j);
+                }
+                if confirmed || cancelled {
+                    self.pending_delete = None;
+                }
+            }
+
+            //confirmation dialog for restoring, which overwrites the data at the restore targets
+            if let Some((id, filename, back_up_first)) = self.pending_restore.clone() {
+                let Some((i, j)) = self.find_restore_point(&id, &filename) else {
+                    self.pending_restore = None;
+                    self.add_internal_log(LogLevel::Warn, format!("{} was removed before the restore was confirmed, nothing was restored", filename));
+                    return;
+                };
+
+                let mut back_up_first = back_up_first;
+                let mut confirmed = false;
+                let mut cancelled = false;
+
+                egui::Window::new("Restore backup?")
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                    .show(ctx, |ui| {
+                        ui.label(format!(
+                            "This will overwrite the data at {} with {} ({}).",
+                            self.backups[i].restore.urls().join(", "),
+                            self.backups[i].logs[j].filename,
+                            format_timestamp(&self.backups[i].logs[j].timestamp, self.display_timezone)
+                        ));
+
+                        ui.checkbox(&mut back_up_first, "Back up the current state first")
+                            .on_hover_text("Runs a backup of this entry and only restores once it has succeeded, so the restore can be undone");
+
+                        ui.add_space(10.0);
+
+                        ui.horizontal(|ui| {
+                            if ui
+                                .button(RichText::new("Restore").color(Color32::RED))
+                                .clicked()
+                            {
+                                confirmed = true;
+                            }
+                            if ui.button("Cancel").clicked() {
+                                cancelled = true;
+                            }
+                        });
+                    });
+
+                self.pending_restore = Some((id, filename, back_up_first));
+
+                if confirmed {
+                    if back_up_first {
+                        self.restore_with_safety_backup(i, j);
+                    } else {
+                        self.restore_point(i, j);
+                    }
+                }
+                if confirmed || cancelled {
+                    self.pending_restore = None;
+                }
+            }
+
+            if !self.startup_problems.is_empty() {
+                let mut dismissed = false;
+
+                egui::Window::new("Problems in config.toml")
+                    .collapsible(false)
+                    .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                    .show(ctx, |ui| {
+                        ui.label("WebSync Station started, but these settings were ignored or replaced:");
+                        ui.add_space(5.0);
+
+                        egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                            for problem in &self.startup_problems {
+                                ui.label(RichText::new(format!("• {}", problem)).color(Color32::YELLOW));
+                            }
+                        });
+
+                        ui.add_space(10.0);
+                        ui.label("Edit config.toml and restart to fix them. They are also in the internal log.");
+
+                        ui.horizontal(|ui| {
+                            if ui.button("Open config folder").clicked() {
+                                if let Err(e) = open_config_folder() {
+                                    warn!("Could not open the config folder: {}", e);
+                                }
+                            }
+                            if ui.button("Dismiss").clicked() {
+                                dismissed = true;
+                            }
+                        });
+                    });
+
+                if dismissed {
+                    self.startup_problems.clear();
+                }
+            }
+        });
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.shut_down();
+    }
+}
+
+/// Every client starts here so the shared http settings apply everywhere. gzip, deflate and
+/// brotli responses are decoded automatically.
+/** percent-encodes what a header value can't hold, EX: "Bærum" => "B%C3%A6rum". Alerts keep
+the name as written */
+fn header_safe(value: &str) -> String {
+    let mut safe = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'%' => safe.push_str("%25"),
+            b' '..=b'~' => safe.push(byte as char),
+            _ => safe.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    safe
+}
+
+fn client_builder(http: &HttpSettings, timeout: Duration) -> Result<ClientBuilder, Box<dyn Error>> {
+    let mut headers = HeaderMap::new();
+    if !http.instance_name.is_empty() {
+        headers.insert("X-WebSync-Instance", HeaderValue::from_str(&header_safe(&http.instance_name))?);
+    }
+
+    let redirect_policy = match http.follow_redirects.limit() {
+        0 => Policy::none(),
+        max => Policy::limited(max),
+    };
+
+    let mut builder = Client::builder()
+        .user_agent(http.user_agent.as_str())
+        .default_headers(headers)
+        .redirect(redirect_policy)
+        .gzip(true)
+        .deflate(true)
+        .brotli(true)
+        .timeout(timeout);
+
+    // trusted on top of the system store
+    for cert in &http.ca_certs {
+        builder = builder.add_root_certificate(cert.clone());
+    }
+
+    if http.danger_accept_invalid_certs {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    Ok(builder)
+}
+
+#[cfg(test)]
+mod header_safe_tests {
+    use super::*;
+
+    #[test]
+    fn non_ascii_instance_names_still_build_a_client() {
+        assert_eq!(header_safe("Bærum 100%"), "B%C3%A6rum 100%25");
+
+        let http = HttpSettings { instance_name: "Bærum".to_string(), ..Default::default() };
+        assert!(client_builder(&http, Duration::from_secs(5)).is_ok_and(|builder| builder.build().is_ok()));
+    }
+}
+
+/** every certificate in a PEM file, which may hold a whole chain or bundle */
+fn load_ca_certs(path: &str) -> Result<Vec<Certificate>, Box<dyn Error>> {
+    let certs = Certificate::from_pem_bundle(&std::fs::read(path)?)?;
+    if certs.is_empty() {
+        return Err("no PEM certificates found in the file".into());
+    }
+    Ok(certs)
+}
+
+fn build_client(http: &HttpSettings, timeout: Duration) -> Result<Client, Box<dyn Error>> {
+    Ok(client_builder(http, timeout)?.build()?)
+}
+
+/** sends the request again when the server answers 429 or 503 with a Retry-After header,
+after waiting as long as it asks (at least RETRY_AFTER_MIN_DELAY). Gives up (returning the last
+response) after RETRY_AFTER_MAX_ATTEMPTS tries or once more than `max_wait` would have passed
+since the first one, so a server can't keep the caller busy forever. Also gives up when
+`shutting_down` is set during the wait */
+fn send_with_retry_after(
+    request: RequestBuilder,
+    url: &str,
+    max_wait: Duration,
+    shutting_down: &AtomicBool,
+) -> reqwest::Result<Response> {
+    let started = Instant::now();
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+        // requests with a streamed body can't be repeated
+        let Some(retry) = request.try_clone() else {
+            return request.send();
+        };
+
+        let response = retry.send()?;
+        let status = response.status();
+        if status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::SERVICE_UNAVAILABLE {
+            return Ok(response);
+        }
+
+        let delay = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, Utc::now()));
+
+        let Some(delay) = delay else {
+            return Ok(response);
+        };
+        // "Retry-After: 0" or a past date would otherwise retry in a tight loop
+        let delay = delay.max(RETRY_AFTER_MIN_DELAY);
+
+        if attempt >= RETRY_AFTER_MAX_ATTEMPTS || started.elapsed() + delay > max_wait {
+            warn!(
+                "{} asked to retry after {} s ({}), giving up after {} attempts in {} s",
+                url,
+                delay.as_secs(),
+                status,
+                attempt,
+                started.elapsed().as_secs()
+            );
+            return Ok(response);
+        }
+
+        info!("{} answered {}, retrying in {} s", url, status, delay.as_secs());
+        let retry_at = Instant::now() + delay;
+        while Instant::now() < retry_at {
+            if shutting_down.load(Ordering::SeqCst) {
+                info!("Shutting down, not retrying {}", url);
+                return Ok(response);
+            }
+            thread::sleep(RETRY_AFTER_SLEEP_STEP.min(retry_at - Instant::now()));
+        }
+    }
+}
+
+/** a Retry-After value, either seconds or an HTTP date. Dates in the past mean right away */
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let at = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    Some((at - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+/** username and password when both are set */
+fn basic_auth<'a>(username: &'a Option<String>, password: &'a Option<String>) -> Option<(&'a str, &'a str)> {
+    match (username, password) {
+        (Some(username), Some(password)) => Some((username, password)),
+        _ => None,
+    }
+}
+
+/** basic auth needs both fields. With only one of them set, both are dropped and a warning is returned */
+fn check_basic_auth(
+    username: &mut Option<String>,
+    password: &mut Option<String>,
+    description: &str,
+) -> Option<String> {
+    if username.is_some() == password.is_some() {
+        return None;
+    }
+
+    *username = None;
+    *password = None;
+
+    Some(format!(
+        "{} sets only one of username and password. Both are needed for basic auth, ignoring them.",
+        description
+    ))
+}
+
+/** GETs the URL and fails unless it answers with a 2xx status. Returns when the server's TLS
+certificate expires, None for plain http */
+/** the checks need an absolute http(s) URL with a host. The error says what is wrong with it */
+fn check_url_syntax(url: &str) -> Result<(), String> {
+    let parsed = Url::parse(url.trim()).map_err(|e| format!("\"{}\" is not a valid URL ({})", url, e))?;
+
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("\"{}\" must start with http:// or https://", url));
+    }
+    if parsed.host_str().is_none_or(str::is_empty) {
+        return Err(format!("\"{}\" has no host", url));
+    }
+    Ok(())
+}
+
+fn send_request(
+    url: &str,
+    auth: Option<(&str, &str)>,
+    query_token: Option<(&str, &str)>, // (parameter, token)
+    expect_final_url: Option<&str>, // pattern the URL must match after redirects
+    http: &HttpSettings,
+) -> Result<Option<DateTime<Utc>>, Box<dyn Error>> {
+    let client = client_builder(http, Duration::from_secs(10))?.tls_info(true).build()?;
+    debug!("Uptime check GET {}", url);
+
+    let mut request = match query_token {
+        Some((param, token)) => client.get(url_with_query_token(url, param, token)?),
+        None => client.get(url),
+    };
+    if let Some((username, password)) = auth {
+        request = request.basic_auth(username, Some(password));
+    }
+    // errors name the url, which would put the token in the logs
+    let response = request.send().map_err(|e| e.without_url())?;
+
+    if response.status().is_redirection() {
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("an unknown location");
+        return Err(format!("Request to {} was redirected ({}) to {}", url, response.status(), location).into());
+    }
+
+    if !response.status().is_success() {
+        return Err(format!("Request to {} failed with status: {}", url, response.status()).into());
+    }
+
+    if let Some(pattern) = expect_final_url {
+        let mut final_url = response.url().clone();
+        if !matches_url_pattern(pattern, final_url.as_str()) {
+            if query_token.is_some() {
+                final_url.set_query(None); // may still hold the token
+            }
+            return Err(format!("Request to {} ended up at {}, expected {}", url, final_url, pattern).into());
+        }
+    }
+
+    let cert_expires = response
+        .extensions()
+        .get::<TlsInfo>()
+        .and_then(|info| info.peer_certificate())
+        .and_then(certificate_expiry);
+
+    Ok(cert_expires)
+}
+
+/** whether `url` matches `pattern`, where each `*` stands for any text (also none).
+EX: "https://www.example.com/app*" matches every page below /app */
+fn matches_url_pattern(pattern: &str, url: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = url.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty(); // no `*`, the whole URL has to match
+    };
+
+    for part in parts {
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+/** `url` with the token added as a query parameter, for endpoints that take it there instead
+of in an Authorization header */
+fn url_with_query_token(url: &str, param: &str, token: &str) -> Result<Url, url::ParseError> {
+    let mut url = Url::parse(url)?;
+    url.query_pairs_mut().append_pair(param, token);
+    Ok(url)
+}
+
+/** the request with the backup's credentials: basic auth when username and password are set,
+otherwise the token, either as a query parameter (token_query_param) or a Bearer header */
+fn with_backup_auth(client: &Client, method: reqwest::Method, backup: &BackupEntry, token: &str) -> Result<RequestBuilder, Box<dyn Error>> {
+    let request = match (&backup.token_query_param, token.is_empty()) {
+        (Some(param), false) => client.request(method, url_with_query_token(&backup.url, param, token)?),
+        _ => client.request(method, &backup.url),
+    };
+
+    let request = match basic_auth(&backup.username, &backup.password) {
+        Some((username, password)) => request.basic_auth(username, Some(password)),
+        None if !token.is_empty() && backup.token_query_param.is_none() => {
+            request.header(AUTHORIZATION, format!("Bearer {}", token))
+        }
+        None => request,
+    };
+
+    Ok(request)
+}
+
+/** notAfter of a DER encoded certificate */
+fn certificate_expiry(der: &[u8]) -> Option<DateTime<Utc>> {
+    let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+    DateTime::from_timestamp(cert.validity().not_after.timestamp(), 0)
+}
+
+/** true if any of the URLs answers at all, whatever the status. An empty list counts as online */
+fn probe_connectivity(urls: &[String], http: &HttpSettings) -> bool {
+    if urls.is_empty() {
+        return true;
+    }
+
+    let client = match build_client(http, Duration::from_secs(5)) {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Could not build client for the connectivity check: {}", e);
+            return true;
+        }
+    };
+
+    urls.iter().any(|url| {
+        debug!("Connectivity check GET {}", url);
+        client.get(url).send().is_ok()
+    })
+}
+
+/** why config.toml (or a file it includes) could not be loaded. Each message says what to do
+about it, they are shown in the window as well as logged */
+#[derive(Debug)]
+enum ConfigError {
+    Missing(PathBuf),
+    IsDirectory(PathBuf),
+    PermissionDenied(PathBuf),
+    Unreadable(PathBuf, std::io::Error),
+    Parse(PathBuf, String), // not valid TOML
+    Invalid(String),        // valid TOML, but a setting is missing or has the wrong type
+    Include(String),
+    Env(String), // .env could not be read
+    UnsetVariables(Vec<String>), // "${NAME} in path.to.key"
+}
+
+impl ConfigError {
+    fn from_io(path: &Path, error: std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::NotFound => ConfigError::Missing(path.to_path_buf()),
+            std::io::ErrorKind::PermissionDenied => ConfigError::PermissionDenied(path.to_path_buf()),
+            _ => ConfigError::Unreadable(path.to_path_buf(), error),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Missing(path) => write!(f, "{} does not exist.", path.display()),
+            ConfigError::IsDirectory(path) => write!(
+                f,
+                "{} is a folder, not a file. Move or rename the folder, WSS then creates a default config file on the next start.",
+                path.display()
+            ),
+            ConfigError::PermissionDenied(path) => write!(
+                f,
+                "No permission to read {}. Give the user WSS runs as read and write access to it.",
+                path.display()
+            ),
+            ConfigError::Unreadable(path, e) => write!(f, "Could not read {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(
+                f,
+                "{} is not valid TOML. Fix the place shown below, or move the file away to get a fresh default.\n\n{}",
+                path.display(),
+                e
+            ),
+            ConfigError::Invalid(e) => write!(f, "A setting in config.toml is missing or has the wrong type.\n\n{}", e),
+            ConfigError::Include(e) => write!(f, "{}", e),
+            ConfigError::Env(e) => write!(f, "{}", e),
+            ConfigError::UnsetVariables(missing) => write!(
+                f,
+                "config.toml uses environment variables that are not set. Set them, or add them to a .env file next to config.toml.\n\n{}",
+                missing.join("\n")
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+fn load_config() -> Result<Config, ConfigError> {
+    env_vars::load_dotenv(Path::new(".env")).map_err(ConfigError::Env)?;
+
+    let mut table = load_config_table(Path::new("config.toml"), &mut Vec::new())?;
+    env_vars::expand_table(&mut table).map_err(ConfigError::UnsetVariables)?;
+
+    TomlValue::Table(table)
+        .try_into()
+        .map_err(|e: toml::de::Error| ConfigError::Invalid(e.to_string()))
+}
+
+/** reads a config file. With `include = "base.toml"` (relative to the including file) the
+file is merged on top of that base, and bases may include further bases */
+fn load_config_table(path: &Path, included: &mut Vec<PathBuf>) -> Result<TomlTable, ConfigError> {
+    if path.is_dir() {
+        return Err(ConfigError::IsDirectory(path.to_path_buf()));
+    }
+
+    let resolved = path.canonicalize().map_err(|e| ConfigError::from_io(path, e))?;
+    if included.contains(&resolved) {
+        return Err(ConfigError::Include(format!("{} includes itself (through include)", path.display())));
+    }
+    included.push(resolved);
+
+    let content = read_to_string(path).map_err(|e| ConfigError::from_io(path, e))?;
+    let mut table: TomlTable =
+        toml::from_str(&content).map_err(|e| ConfigError::Parse(path.to_path_buf(), e.to_string()))?;
+
+    let Some(include) = table.remove("include") else {
+        return Ok(table);
+    };
+    let include = include
+        .as_str()
+        .ok_or_else(|| ConfigError::Include(format!("include in {} must be a file name", path.display())))?;
+
+    let base_path = path.parent().unwrap_or(Path::new("")).join(include);
+    let mut base = load_config_table(&base_path, included)?;
+    merge_config_tables(&mut base, table);
+    Ok(base)
+}
+
+/** override values win. Tables are merged key by key, anything else (arrays and
+[[backups]]/[[urls]] lists included) is replaced as a whole */
+fn merge_config_tables(base: &mut TomlTable, overrides: TomlTable) {
+    for (key, value) in overrides {
+        match value {
+            TomlValue::Table(override_table) if base.get(&key).is_some_and(TomlValue::is_table) => {
+                if let Some(TomlValue::Table(base_table)) = base.get_mut(&key) {
+                    merge_config_tables(base_table, override_table);
+                }
+            }
+            value => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/** config.toml followed by the files it includes, in the order load_config_table merges them
+(the first one wins). This is where the writers below look for the setting they change */
+fn config_files() -> Result<Vec<(PathBuf, toml_edit::DocumentMut)>, Box<dyn Error>> {
+    let mut files: Vec<(PathBuf, toml_edit::DocumentMut)> = Vec::new();
+    let mut seen = Vec::new();
+    let mut path = PathBuf::from("config.toml");
+
+    loop {
+        seen.push(path.canonicalize()?);
+        let document: toml_edit::DocumentMut = read_to_string(&path)?.parse()?;
+        let include = document
+            .get("include")
+            .and_then(|item| item.as_str())
+            .map(|include| path.parent().unwrap_or(Path::new("")).join(include));
+        files.push((path, document));
+
+        match include {
+            // load_config refuses include loops, this only keeps a file changed since from spinning
+            Some(next) if !next.canonicalize().is_ok_and(|next| seen.contains(&next)) => path = next,
+            _ => return Ok(files),
+        }
+    }
+}
+
+/** the file whose top-level `key` is used, with its parsed content. That is the first of
+config_files() that sets it, or config.toml when none does */
+fn config_file_defining(key: &str) -> Result<(PathBuf, toml_edit::DocumentMut), Box<dyn Error>> {
+    let mut files = config_files()?;
+    let index = files.iter().position(|(_, document)| document.contains_key(key)).unwrap_or(0);
+    Ok(files.swap_remove(index))
+}
+
+/** edits the [[key]] entries in place, in config.toml or the included file they come from.
+Comments and formatting are kept, and comments stay where they were in the file even when
+entries are reordered */
+fn edit_config_entries(
+    key: &str,
+    edit: impl FnOnce(&mut Vec<toml_edit::Table>) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let (path, mut document) = config_file_defining(key)?;
+
+    let entries = document
+        .get_mut(key)
+        .and_then(|item| item.as_array_of_tables_mut())
+        .ok_or_else(|| format!("{} has no [[{}]] entries", path.display(), key))?;
+
+    let slots: Vec<(Option<usize>, toml_edit::Decor)> = entries
+        .iter()
+        .map(|table| (table.position(), table.decor().clone()))
+        .collect();
+
+    let mut tables: Vec<toml_edit::Table> = entries.iter().cloned().collect();
+    edit(&mut tables)?;
+
+    let mut rebuilt = toml_edit::ArrayOfTables::new();
+    for (mut table, (position, decor)) in tables.into_iter().zip(slots) {
+        if let Some(position) = position {
+            table.set_position(position);
+        }
+        *table.decor_mut() = decor;
+        rebuilt.push(table);
+    }
+    *entries = rebuilt;
+
+    std::fs::write(&path, document.to_string())?;
+    Ok(())
+}
+
+/** an OS notification, for when the window is minimized or runs in the background. Long
+summaries are cut, notification popups only show a few lines */
+fn show_desktop_notification(summary: &str, body: &str) -> Result<(), Box<dyn Error>> {
+    let body: String = match body.char_indices().nth(300) {
+        Some((end, _)) => format!("{}...", &body[..end]),
+        None => body.to_string(),
+    };
+
+    notify_rust::Notification::new()
+        .appname("WebSync Station")
+        .summary(summary)
+        .body(&body)
+        .show()?;
+    Ok(())
+}
+
+/** reads a CSV or JSON list of URLs and appends the new ones to the [[urls]] list in the config.
+URLs that are already monitored, repeated in the file or not valid are skipped. Returns the
+added entries and a summary for the log */
+fn import_url_file(path: &Path, existing: &[UrlEntry]) -> Result<(Vec<UrlEntry>, String), Box<dyn Error>> {
+    let rows = url_import::read_url_list(path)?;
+    let total = rows.len();
+
+    let mut added: Vec<UrlEntry> = Vec::new();
+    let mut duplicates = 0;
+    let mut invalid = Vec::new();
+
+    for row in rows {
+        let known = existing.iter().chain(&added).any(|entry| entry.url.trim() == row.url);
+        if known {
+            duplicates += 1;
+            continue;
+        }
+        if let Err(e) = check_url_syntax(&row.url) {
+            invalid.push(e);
+            continue;
+        }
+
+        added.push(UrlEntry {
+            description: row.description,
+            url: row.url,
+            ..Default::default()
+        });
+    }
+
+    if !added.is_empty() {
+        append_config_urls(&added)?;
+    }
+
+    let mut summary = format!(
+        "Imported {} of {} URLs from {} ({} already monitored",
+        added.len(),
+        total,
+        path.display(),
+        duplicates
+    );
+    if !invalid.is_empty() {
+        summary.push_str(&format!(", {} invalid: {}", invalid.len(), invalid.join("; ")));
+    }
+    summary.push(')');
+
+    Ok((added, summary))
+}
+
+/** adds [[urls]] entries at the end of the list, in config.toml or the included file the list
+comes from (a new list in config.toml would replace the base file's). The rest of the file is
+kept as is */
+fn append_config_urls(entries: &[UrlEntry]) -> Result<(), Box<dyn Error>> {
+    let (path, mut document) = config_file_defining("urls")?;
+
+    let urls = document
+        .entry("urls")
+        .or_insert_with(|| toml_edit::Item::ArrayOfTables(toml_edit::ArrayOfTables::new()))
+        .as_array_of_tables_mut()
+        .ok_or_else(|| format!("urls in {} is not a list of [[urls]] tables", path.display()))?;
+
+    for entry in entries {
+        let mut table = toml_edit::Table::new();
+        table["description"] = toml_edit::value(entry.description.as_str());
+        table["url"] = toml_edit::value(entry.url.as_str());
+        urls.push(table);
+    }
+
+    std::fs::write(&path, document.to_string())?;
+    Ok(())
+}
+
+/** sets (table, key, value) in the file the value is used from (config.toml or a file it
+includes), new values go to config.toml, creating missing tables. A comment after an old value
+stays with the new one */
+fn set_config_table_values(values: &[(&str, &str, toml_edit::Value)]) -> Result<(), Box<dyn Error>> {
+    let mut files = config_files()?;
+    let changed = apply_config_table_values(&mut files, values)?;
+
+    for ((path, document), changed) in files.iter().zip(changed) {
+        if changed {
+            std::fs::write(path, document.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/** the in-memory part of set_config_table_values, returns which of the files changed */
+fn apply_config_table_values(
+    files: &mut [(PathBuf, toml_edit::DocumentMut)],
+    values: &[(&str, &str, toml_edit::Value)],
+) -> Result<Vec<bool>, Box<dyn Error>> {
+    let mut changed = vec![false; files.len()];
+
+    for (table, key, value) in values {
+        let index = files
+            .iter()
+            .position(|(_, document)| {
+                document.get(table).and_then(|item| item.as_table_like()).is_some_and(|table| table.contains_key(key))
+            })
+            .unwrap_or(0);
+        changed[index] = true;
+
+        let (path, document) = &mut files[index];
+        let table = document
+            .entry(table)
+            .or_insert_with(|| toml_edit::Item::Table(toml_edit::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| format!("{} in {} is not a table", table, path.display()))?;
+
+        let value = match table.get(key).and_then(|item| item.as_value()) {
+            Some(old) => keep_env_references(old, value.clone()),
+            None => value.clone(),
+        };
+        table[*key] = toml_edit::Item::Value(value);
+    }
+
+    Ok(changed)
+}
+
+/** `new` in place of `old`, keeping the old formatting. A ${VAR} that still expands to the new
+value stays, also inside lists, so secrets and webhook URLs aren't written out */
+fn keep_env_references(old: &toml_edit::Value, mut new: toml_edit::Value) -> toml_edit::Value {
+    match (old, &mut new) {
+        (toml_edit::Value::String(old_text), toml_edit::Value::String(new_text))
+            if old_text.value().contains("${")
+                && env_vars::expand(old_text.value()).is_ok_and(|expanded| expanded == *new_text.value()) =>
+        {
+            return old.clone();
+        }
+        (toml_edit::Value::Array(old_items), toml_edit::Value::Array(new_items)) => {
+            let kept: Vec<toml_edit::Value> = new_items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| match old_items.get(i) {
+                    Some(old_item) => keep_env_references(old_item, item.clone()),
+                    None => item.clone(),
+                })
+                .collect();
+            for (i, item) in kept.into_iter().enumerate() {
+                new_items.replace(i, item);
+            }
+            new_items.set_trailing(old_items.trailing().clone());
+            new_items.set_trailing_comma(old_items.trailing_comma());
+        }
+        _ => {}
+    }
+    *new.decor_mut() = old.decor().clone();
+    new
+}
+
+#[cfg(test)]
+mod config_write_tests {
+    use super::*;
+
+    #[test]
+    fn saving_a_list_keeps_the_env_references_in_it() {
+        std::env::set_var("WSS_TEST_WEBHOOK", "https://hooks.example.com/secret-token");
+        let text = "[warning_settings]\npost_request_routes = [\"${WSS_TEST_WEBHOOK}\", \"https://old.example.com\"] # routes\n";
+        let mut files = vec![(PathBuf::from("config.toml"), text.parse::<toml_edit::DocumentMut>().unwrap())];
+
+        let routes = ["https://hooks.example.com/secret-token", "https://new.example.com"];
+        let changed = apply_config_table_values(
+            &mut files,
+            &[("warning_settings", "post_request_routes", toml_edit::Value::Array(routes.into_iter().collect()))],
+        )
+        .unwrap();
+
+        assert_eq!(changed, [true]);
+        assert_eq!(
+            files[0].1.to_string(),
+            "[warning_settings]\npost_request_routes = [\"${WSS_TEST_WEBHOOK}\", \"https://new.example.com\"] # routes\n"
+        );
+    }
+}
 
-                    ui.horizontal(|ui| {
-                        if log_entries_length > 0 {
-                            ui.collapsing(
-                                format!("Restore {}", self.backups[i].description),
-                                |ui| {
-                                    let mut j = 0;
+fn swap_config_entries(key: &str, a: usize, b: usize) -> Result<(), Box<dyn Error>> {
+    edit_config_entries(key, |tables| {
+        if a >= tables.len() || b >= tables.len() {
+            return Err("config.toml doesn't match the entries shown, restart to reload it".into());
+        }
+        tables.swap(a, b);
+        Ok(())
+    })
+}
 
-                                    loop {
-                                        if j >= self.backups[i].logs.len() {
-                                            break;
-                                        }
+fn set_config_entry_value(key: &str, index: usize, field: &str, value: &str) -> Result<(), Box<dyn Error>> {
+    edit_config_entries(key, |tables| {
+        let table = tables
+            .get_mut(index)
+            .ok_or("config.toml doesn't match the entries shown, restart to reload it")?;
+        table[field] = toml_edit::value(value);
+        Ok(())
+    })
+}
 
-                                        ui.horizontal(|ui| {
-                                            let time_stamp = format_timestamp(
-                                                &self.backups[i].logs[j].timestamp,
-                                            );
+/** backup ids name folders, so they are limited to lowercase letters, digits, '-' and '_' */
+fn is_valid_backup_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
 
-                                            let size_kb =
-                                                self.backups[i].logs[j].size as f64 / 1000.0;
-                                            let size_str = format!("{:.1} KB", size_kb);
+/** true for a single file or folder name, without separators, "." or ".." */
+fn is_plain_file_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(components.next(), Some(Component::Normal(_))) && components.next().is_none()
+}
 
-                                            ui.label(format!("{}- Size:{}", time_stamp, size_str));
+/** like is_plain_file_name, but subfolders are allowed. EX: "2024/06/db.sql" */
+fn is_relative_file_path(name: &str) -> bool {
+    !name.is_empty() && Path::new(name).components().all(|component| matches!(component, Component::Normal(_)))
+}
 
-                                            if ui.button("Restore").clicked() {
+/** the date subfolder a new restore point goes into for partition_by, None when it is off */
+fn backup_partition(backup: &BackupEntry, now: DateTime<Tz>) -> Option<String> {
+    match backup.partition_by.as_deref() {
+        Some("day") => Some(now.format("%Y/%m/%d").to_string()),
+        Some("month") => Some(now.format("%Y/%m").to_string()),
+        _ => None,
+    }
+}
 
+/** the folder for a backup id below the root. Anything that ends up outside the root, e.g.
+through a symlink, is refused */
+fn confined_backup_folder(root: &Path, id: &str) -> Result<PathBuf, Box<dyn Error>> {
+    if !is_valid_backup_id(id) {
+        return Err(format!("\"{}\" is not a valid backup id", id).into());
+    }
 
-                                                let path = format!(
-                                                    "{}/{}",
-                                                    self.backups[i].description,
-                                                    self.backups[i].logs[j].filename
-                                                );
+    create_dir_all(root)?;
+    let root = root.canonicalize()?;
+    let folder = root.join(id);
+
+    if folder.exists() {
+        let resolved = folder.canonicalize()?;
+        if !resolved.starts_with(&root) {
+            return Err(format!(
+                "{} resolves to {}, outside the backup root {}",
+                folder.display(),
+                resolved.display(),
+                root.display()
+            )
+            .into());
+        }
+    }
 
+    Ok(folder)
+}
 
-                                                let token_to_use = if self.token.is_empty() {
-                                                    match create_jwt(&self.payload, &self.secret, &self.jwt_expiry) {
-                                                        Ok(jwt) => jwt,
-                                                        Err(e) => {
-                                                            println!("Failed to create JWT for warning POST: {}", e);
-                                                            String::new() // Use empty string if JWT creation fails
-                                                        }
-                                                    }
-                                                } else {
-                                                    self.token.clone()
-                                                };
+/** deletes .part files left behind when WSS was killed mid-download, returns their names */
+fn remove_partial_downloads(folder: &Path) -> Vec<String> {
+    let Ok(entries) = read_dir(folder) else {
+        return Vec::new();
+    };
 
+    let paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect();
+    let mut removed = Vec::new();
 
+    for path in paths {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()).map(String::from) else {
+            continue;
+        };
 
+        if path.extension().is_some_and(|ext| ext == "part") {
+            let deleted = if path.is_dir() { remove_dir_all(&path).is_ok() } else { remove_file(&path).is_ok() };
+            if deleted {
+                removed.push(name);
+            }
+        } else if path.is_dir() && name.chars().all(|c| c.is_ascii_digit()) {
+            // a partition_by date folder. Other folders are unpacked archives, their files are left alone
+            removed.extend(remove_partial_downloads(&path).into_iter().map(|part| format!("{}/{}", name, part)));
+        }
+    }
 
-                                                let restore_attempt = restore_backup(
-                                                    &self.backups[i].restore,
-                                                    &path,
-                                                    &token_to_use
-                                                );
+    removed
+}
 
-                                                match restore_attempt {
-                                                    Ok(_) => {
-                                                        println!("Restored file successfully");
+/** turns a description into a valid backup id. EX: "Main DB (prod)" => "main-db-prod" */
+fn slugify(description: &str) -> String {
+    let mut slug = String::new();
 
-                                                        //add the restored file to the internal log
+    for c in description.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
 
-                                                        let log_entry = InternalLogEntry {
-                                                            message: format!(
-                                                                "Successfully restored file {} from {}",
-                                                                self.backups[i].logs[j].filename,
-                                                                self.backups[i].description
-                                                            ),
-                                                            timestamp: Utc::now().to_rfc3339(),
-                                                        };
+    let slug = slug.trim_end_matches('-').to_string();
 
-                                                        self.internal_log.push(log_entry);
+    if slug.is_empty() {
+        "backup".to_string()
+    } else {
+        slug
+    }
+}
 
-  
-                                                    }
-                                                    Err(err) => {
-                                                        println!("Restore failed: {}", err);
+/** gives every backup a unique, valid id and its folder below the root. Generated ids are
+written to config.toml so they survive later changes to the description, and a folder from
+an older layout (named after the description, or the id outside the root) is moved in.
+Returns the messages worth showing the user */
+fn assign_backup_ids(backups: &mut [BackupEntry], root: &Path) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut used: Vec<String> = Vec::new();
+
+    for (index, entry) in backups.iter_mut().enumerate() {
+        if !entry.id.is_empty() && !is_valid_backup_id(&entry.id) {
+            messages.push(format!(
+                "Invalid id \"{}\" for backup {}: only a-z, 0-9, '-' and '_' are allowed. Generating a new one.",
+                entry.id, entry.description
+            ));
+            entry.id.clear();
+        } else if used.contains(&entry.id) {
+            messages.push(format!(
+                "Duplicate id \"{}\" for backup {}. Generating a new one.",
+                entry.id, entry.description
+            ));
+            entry.id.clear();
+        }
 
-                                                        //add the error to the internal log
+        if entry.id.is_empty() {
+            let base = slugify(&entry.description);
+            let mut id = base.clone();
+            let mut n = 2;
+            while used.contains(&id) {
+                id = format!("{}-{}", base, n);
+                n += 1;
+            }
 
-                                                        let log_entry = InternalLogEntry {
-                                                            message: format!(
-                                                                "Failed to restore file {} from {}: {}",
-                                                                self.backups[i].logs[j].filename,
-                                                                self.backups[i].description,
-                                                                err
-                                                            ),
-                                                            timestamp: Utc::now().to_rfc3339(),
-                                                        };
+            if let Err(e) = set_config_entry_value("backups", index, "id", &id) {
+                messages.push(format!(
+                    "Could not save id \"{}\" for backup {} to config.toml: {}",
+                    id, entry.description, e
+                ));
+            }
 
-                                                        self.internal_log.push(log_entry);
+            entry.id = id;
+        }
 
+        let new_folder = root.join(&entry.id);
+
+        if !new_folder.exists() {
+            // only plain names, a description like "../x" must never move anything
+            let old_folder = [entry.description.as_str(), entry.id.as_str()]
+                .into_iter()
+                .filter(|name| is_plain_file_name(name))
+                .map(Path::new)
+                .find(|folder| folder.is_dir());
+
+            if let Some(old_folder) = old_folder {
+                let moved = create_dir_all(root).and_then(|_| std::fs::rename(old_folder, &new_folder));
+
+                match moved {
+                    Ok(()) => messages.push(format!(
+                        "Moved backup folder {} to {}",
+                        old_folder.display(),
+                        new_folder.display()
+                    )),
+                    Err(e) => messages.push(format!(
+                        "Could not move backup folder {} to {}: {}",
+                        old_folder.display(),
+                        new_folder.display(),
+                        e
+                    )),
+                }
+            }
+        }
 
+        entry.folder = match confined_backup_folder(root, &entry.id) {
+            Ok(folder) => {
+                for part in remove_partial_downloads(&folder) {
+                    messages.push(format!("Removed unfinished download {} of {}", part, entry.description));
+                }
+                Some(folder)
+            }
+            Err(e) => {
+                messages.push(format!(
+                    "Backup {} has no usable folder and will not run: {}",
+                    entry.description, e
+                ));
+                None
+            }
+        };
 
+        used.push(entry.id.clone());
+    }
 
-                                                    }
-                                                }
+    messages
+}
 
+fn create_jwt(
+    payload: &TomlTable,
+    secret: &str,
+    expiry: &u64,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let iat = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
 
+    let json_payload = JsonValue::Object(jwt_claims(payload, iat, *expiry)?);
 
+    let token = encode(
+        &Header::default(),
+        &json_payload,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )?;
 
+    Ok(token)
+}
 
-                                                println!(
-                                                    "Restoring {}",
-                                                    self.backups[i].logs[j].filename
-                                                )
-                                            }
-                                        });
+/** converts by hand so datetimes become plain strings instead of toml's internal wrapper object */
+/// Claim precedence: the user payload is copied in the order it was written, then `iat` and
+/// `exp` are always set by us. User supplied `iat`/`exp` keys are ignored.
+fn jwt_claims(payload: &TomlTable, iat: u64, expiry: u64) -> Result<JsonMap<String, JsonValue>, Box<dyn Error>> {
+    let mut json_map = JsonMap::new();
 
-                                        j += 1;
-                                    }
-                                },
-                            );
-                        }
+    for (k, v) in payload {
+        if k == "iat" || k == "exp" {
+            warn!("Ignoring `{}` in [payload], it is always set automatically", k);
+            continue;
+        }
 
-                        ui.add_space(10.0);
+        let json_val = toml_to_json_value(v)?;
+        json_map.insert(k.clone(), json_val);
+    }
 
-                        let time_left =
-                            calc_time_to_backup(&self.backups[i].time, &self.backups[i].interval);
+    json_map.insert("iat".to_string(), json!(iat));
+    json_map.insert("exp".to_string(), json!(iat + expiry));
 
-                        ui.vertical(|ui| ui.label(format!("Next backup in {}", time_left)));
-                    });
+    Ok(json_map)
+}
 
-                    ui.add_space(10.0);
-                    ui.separator();
-                    ui.add_space(10.0);
+fn toml_to_json_value(val: &TomlValue) -> Result<JsonValue, Box<dyn Error>> {
+    let json_val = match val {
+        TomlValue::String(s) => JsonValue::String(s.clone()),
+        TomlValue::Integer(i) => json!(i),
+        TomlValue::Float(f) => serde_json::Number::from_f64(*f)
+            .map(JsonValue::Number)
+            .ok_or_else(|| format!("Payload float {} cannot be represented in JSON", f))?,
+        TomlValue::Boolean(b) => JsonValue::Bool(*b),
+        TomlValue::Datetime(dt) => JsonValue::String(dt.to_string()),
+        TomlValue::Array(items) => JsonValue::Array(
+            items
+                .iter()
+                .map(toml_to_json_value)
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        TomlValue::Table(table) => {
+            let mut json_map = JsonMap::new();
+            for (k, v) in table {
+                json_map.insert(k.clone(), toml_to_json_value(v)?);
+            }
+            JsonValue::Object(json_map)
+        }
+    };
 
-                    i += 1;
-                }
-            })
-        });
-    }
+    Ok(json_val)
 }
 
-fn send_request(url: &str) -> Result<(), Box<dyn Error>> {
-    let client = Client::builder()
-        .timeout(Duration::from_secs(10)) // Add a timeout
-        .build()?;
-    let response = client.get(url).send()?;
+#[cfg(test)]
+mod jwt_claim_tests {
+    use super::*;
 
-    if !response.status().is_success() {
-        return Err(format!("Request to {} failed with status: {}", url, response.status()).into());
+    fn claims(payload: &str) -> JsonMap<String, JsonValue> {
+        let payload: TomlTable = toml::from_str(payload).unwrap();
+        jwt_claims(&payload, 1_700_000_000, 600).unwrap()
     }
 
-    Ok(())
-}
+    #[test]
+    fn nested_tables_keep_their_structure_and_order() {
+        let claims = claims(
+            r#"
+            sub = "websync"
+            [user]
+            name = "backup-bot"
+            id = 42
+            [user.meta]
+            level = 3
+            active = true
+            "#,
+        );
 
-fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
-    let content = read_to_string("config.toml")?;
-    let config: Config = toml::from_str(&content)?;
-    Ok(config)
-}
+        assert_eq!(
+            JsonValue::Object(claims.clone()),
+            json!({
+                "sub": "websync",
+                "user": { "name": "backup-bot", "id": 42, "meta": { "level": 3, "active": true } },
+                "iat": 1_700_000_000,
+                "exp": 1_700_000_600,
+            })
+        );
+        assert_eq!(claims.keys().collect::<Vec<_>>(), ["sub", "user", "iat", "exp"]);
+        let user_keys: Vec<&String> = claims["user"].as_object().unwrap().keys().collect();
+        assert_eq!(user_keys, ["name", "id", "meta"]);
+    }
 
-fn create_jwt(
-    payload: &HashMap<String, TomlValue>,
-    secret: &str,
-    expiry: &u64,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let mut json_map = JsonMap::new();
+    #[test]
+    fn arrays_convert_item_by_item() {
+        let claims = claims(
+            r#"
+            roles = ["read", "write"]
+            weights = [1, 2.5]
+            scopes = [{ name = "db", level = 1 }, { name = "files" }]
+            "#,
+        );
 
-    for (k, v) in payload {
-        let json_val = toml_to_json_value(v)?;
-        json_map.insert(k.clone(), json_val);
+        assert_eq!(claims["roles"], json!(["read", "write"]));
+        assert_eq!(claims["weights"], json!([1, 2.5]));
+        assert_eq!(claims["scopes"], json!([{ "name": "db", "level": 1 }, { "name": "files" }]));
     }
 
-    let iat = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    #[test]
+    fn datetimes_become_the_strings_written_in_the_config() {
+        let claims = claims(
+            r#"
+            issued = 2024-06-01T12:30:00Z
+            offset = 2024-06-01T12:30:00+02:00
+            day = 2024-06-01
+            at = 07:45:00
+            [window]
+            from = 2024-01-01T00:00:00Z
+            "#,
+        );
 
-    let exp = iat + expiry;
+        assert_eq!(claims["issued"], json!("2024-06-01T12:30:00Z"));
+        assert_eq!(claims["offset"], json!("2024-06-01T12:30:00+02:00"));
+        assert_eq!(claims["day"], json!("2024-06-01"));
+        assert_eq!(claims["at"], json!("07:45:00"));
+        assert_eq!(claims["window"], json!({ "from": "2024-01-01T00:00:00Z" }));
+    }
 
-    json_map.insert("iat".to_string(), json!(iat));
-    json_map.insert("exp".to_string(), json!(exp));
+    #[test]
+    fn iat_and_exp_in_the_payload_are_ignored() {
+        let claims = claims(
+            r#"
+            iat = 1
+            exp = 2
+            sub = "websync"
+            "#,
+        );
 
-    let json_payload = JsonValue::Object(json_map);
+        assert_eq!(claims.keys().collect::<Vec<_>>(), ["sub", "iat", "exp"]);
+        assert_eq!(claims["iat"], json!(1_700_000_000));
+        assert_eq!(claims["exp"], json!(1_700_000_600));
+    }
+}
 
-    let token = encode(
-        &Header::default(),
-        &json_payload,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )?;
+/** the status the backup url answers with, using the same credentials as a download. HEAD first,
+and a one byte ranged GET for servers that don't allow HEAD. Nothing is stored */
+fn probe_backup_auth(backup: &BackupEntry, token: &str, http: &HttpSettings) -> Result<StatusCode, Box<dyn Error>> {
+    let client = build_client(http, Duration::from_secs(15))?;
 
-    Ok(token)
-}
+    debug!("Auth test HEAD {}", backup.url);
+    let status = with_backup_auth(&client, reqwest::Method::HEAD, backup, token)?
+        .send()
+        .map_err(|e| e.without_url())?
+        .status();
 
-fn toml_to_json_value(val: &TomlValue) -> Result<JsonValue, Box<dyn Error>> {
-    Ok(serde_json::to_value(val)?)
+    if status != StatusCode::METHOD_NOT_ALLOWED {
+        return Ok(status);
+    }
+
+    debug!("HEAD not allowed, auth test with a ranged GET {}", backup.url);
+    let response = with_backup_auth(&client, reqwest::Method::GET, backup, token)?
+        .header(RANGE, "bytes=0-0")
+        .send()
+        .map_err(|e| e.without_url())?;
+    Ok(response.status())
 }
 
 fn download_file(
-    url_str: &str,
-    save_folder: &str,
+    backup: &BackupEntry,
+    folder_path: &Path,
     token: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
+    http: &HttpSettings,
+    shutting_down: &AtomicBool,
+) -> Result<(String, Option<String>), Box<dyn std::error::Error>> {
+    let url_str = backup.url.as_str();
     let url = Url::parse(url_str)?;
     
     let filename_from_url = url
         .path_segments()
-        .and_then(|segments| segments.last())
+        .and_then(|mut segments| segments.next_back())
         .filter(|name| !name.is_empty())
         .map(|s| s.to_string()) // Convert to String
         .ok_or_else(|| format!("Cannot extract filename from URL path: {}", url_str))?;
 
-    let folder_path = Path::new(save_folder);
     create_dir_all(folder_path)?;
 
-    let client = Client::builder()
-        .timeout(Duration::from_secs(300)) // 5 min timeout for download
-        .build()?;
-    
-    let mut request_builder = client.get(url.clone()); // Clone URL for request
-    if !token.is_empty() {
-        request_builder = request_builder.header(AUTHORIZATION, format!("Bearer {}", token));
+    // the blocking client's timeout covers waiting for the headers and then every single read
+    // on its own, so a large download is never cut off as long as data keeps arriving
+    let stall_timeout = Duration::from_secs(backup.stall_timeout_secs.unwrap_or(300));
+    let connect_timeout = Duration::from_secs(backup.connect_timeout_secs.unwrap_or(30));
+    let mut builder = client_builder(http, stall_timeout)?.connect_timeout(connect_timeout);
+    if backup.raw {
+        // store exactly what the server sent, even if it is Content-Encoding compressed
+        builder = builder.no_gzip().no_deflate().no_brotli();
     }
+    let client = builder.build()?;
     
-    let mut response = request_builder.send()?;
-
-    if !response.status().is_success() {
-        return Err(format!("Request to {} failed with status: {}", url_str, response.status()).into());
+    // basic auth takes the Authorization header, so it replaces the bearer token
+    let request_builder = with_backup_auth(&client, reqwest::Method::GET, backup, token)?;
+    
+    debug!("Downloading backup from {}", url_str);
+    // runs on a backup worker thread, so waiting for a rate limit doesn't block the UI. Errors
+    // lose the url, which may hold the token
+    let mut response = send_with_retry_after(request_builder, url_str, Duration::from_secs(600), shutting_down)
+        .map_err(|e| e.without_url())?;
+
+    let status = response.status();
+    if !status.is_success() && !backup.accept_statuses.contains(&status.as_u16()) {
+        return Err(format!("Request to {} failed with status: {}", url_str, status).into());
     }
 
     // Try to get filename from Content-Disposition header first
@@ -1170,6 +6545,10 @@ fn download_file(
     } else {
         filename_from_url // Fallback if header is not present
     };
+
+    if let Some(template) = &backup.filename_template {
+        final_filename = render_filename_template(template, &backup.description, &final_filename);
+    }
     
     // Sanitize filename to prevent path traversal or invalid characters
     final_filename = sanitize_filename::sanitize(&final_filename);
@@ -1178,6 +6557,119 @@ fn download_file(
     }
 
 
+    let etag = response.headers().get(ETAG).and_then(|value| value.to_str().ok()).map(str::to_string);
+
+    // checked on the .part file, so a download that doesn't match never becomes a restore point
+    let mut verified = None;
+    let mut body = StallDetector::new(&mut response, stall_timeout);
+    let filename = store_backup_file(folder_path, final_filename, &mut body, |part_path| {
+        verified = verify_download_checksum(backup, &client, token, etag.as_deref(), part_path)?;
+        Ok(())
+    })
+    .map_err(|e| format!("Download from {} failed: {}", url_str, e))?;
+
+    Ok((filename, verified))
+}
+
+/** compares a downloaded file with the SHA-256 the server publishes for it, from checksum_url
+and/or the ETag. Returns that checksum, or None when the backup verifies neither */
+fn verify_download_checksum(
+    backup: &BackupEntry,
+    client: &Client,
+    token: &str,
+    etag: Option<&str>,
+    path: &Path,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let mut expected = Vec::new();
+
+    if backup.verify_etag {
+        let etag = etag.ok_or("verify_etag is set, but the server sent no ETag")?;
+        expected.push(("the ETag", normalize_checksum(etag.trim_start_matches("W/"))));
+    }
+
+    if let Some(checksum_url) = &backup.checksum_url {
+        // same auth as the download itself
+        let mut source = backup.clone();
+        source.url = checksum_url.clone();
+        let response = with_backup_auth(client, reqwest::Method::GET, &source, token)?
+            .send()
+            .map_err(|e| format!("could not fetch checksum_url: {}", e.without_url()))?;
+        if !response.status().is_success() {
+            return Err(format!("checksum_url answered with status {}", response.status()).into());
+        }
+        let text = response.text()?;
+        // the first word, so `sha256sum` output ("<hash>  <file>") works as well as a bare hash
+        let checksum = text.split_whitespace().next().unwrap_or_default();
+        expected.push(("checksum_url", normalize_checksum(checksum)));
+    }
+
+    if expected.is_empty() {
+        return Ok(None);
+    }
+
+    let actual = integrity::hash_path(path)?;
+    for (source, checksum) in &expected {
+        if checksum.len() != 64 || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("{} \"{}\" is not a SHA-256 checksum", source, checksum).into());
+        }
+        if *checksum != actual {
+            return Err(format!(
+                "checksum mismatch, {} says {} but the download has {}",
+                source, checksum, actual
+            )
+            .into());
+        }
+    }
+
+    Ok(Some(actual))
+}
+
+/** lowercase hex without quotes or a "sha256:" prefix */
+fn normalize_checksum(checksum: &str) -> String {
+    let checksum = checksum.trim().trim_matches('"').to_ascii_lowercase();
+    checksum.strip_prefix("sha256:").unwrap_or(&checksum).to_string()
+}
+
+/** passes reads through and turns a read that fails after `timeout` without any data into a
+clear "stalled" error, instead of the client's generic timeout message */
+struct StallDetector<R> {
+    inner: R,
+    timeout: Duration,
+    last_data: Instant,
+}
+
+impl<R> StallDetector<R> {
+    fn new(inner: R, timeout: Duration) -> Self {
+        StallDetector { inner, timeout, last_data: Instant::now() }
+    }
+}
+
+impl<R: std::io::Read> std::io::Read for StallDetector<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.inner.read(buf) {
+            Ok(n) => {
+                self.last_data = Instant::now();
+                Ok(n)
+            }
+            Err(e) if self.last_data.elapsed() >= self.timeout => Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("download stalled, no data for {} seconds ({})", self.last_data.elapsed().as_secs(), e),
+            )),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/** picks a free name for `filename` in the folder (file_0.sql, file_1.sql, ...) and streams
+`source` into it. The data goes to a .part file that is only renamed into place once it is
+complete and `finish` (given the .part path) agrees, so a crash or failure never leaves a
+half-written backup */
+fn store_backup_file(
+    folder_path: &Path,
+    mut final_filename: String,
+    source: &mut impl std::io::Read,
+    finish: impl FnOnce(&Path) -> Result<(), Box<dyn Error>>,
+) -> Result<String, Box<dyn Error>> {
     // Handle filename conflicts by appending a number
     let mut candidate_path = folder_path.join(&final_filename);
     if candidate_path.exists() {
@@ -1205,14 +6697,293 @@ fn download_file(
         }
     }
     
-    let mut dest_file = File::create(&candidate_path)?;
-    copy(&mut response, &mut dest_file)?;
+    let part_path = folder_path.join(format!("{}.part", final_filename));
+    let written = File::create(&part_path).and_then(|mut part_file| {
+        copy(source, &mut part_file)?;
+        part_file.sync_all()
+    });
+    let stored = match written {
+        Ok(()) => finish(&part_path).and_then(|_| Ok(rename(&part_path, &candidate_path)?)),
+        Err(e) => Err(format!("writing {} was interrupted: {}", final_filename, e).into()),
+    };
+
+    if let Err(e) = stored {
+        let _ = remove_file(&part_path);
+        return Err(e);
+    }
 
     Ok(final_filename)
 }
 
-fn load_log(foldername: &str) -> Result<Log, Box<dyn std::error::Error>> {
-    let folder = Path::new(foldername);
+/** runs a backup's `command` through the shell and stores what it writes to stdout. A
+non-zero exit status fails the backup, with the end of stderr in the message */
+fn run_backup_command(backup: &BackupEntry, command: &str, folder_path: &Path) -> Result<String, Box<dyn Error>> {
+    create_dir_all(folder_path)?;
+
+    let default_filename = format!("{}_{}.dump", backup.id, Utc::now().format("%Y-%m-%dT%H%M"));
+    let mut final_filename = match &backup.filename_template {
+        Some(template) => render_filename_template(template, &backup.description, &default_filename),
+        None => default_filename,
+    };
+    final_filename = sanitize_filename::sanitize(&final_filename);
+    if final_filename.is_empty() {
+        final_filename = format!("{}.dump", backup.id);
+    }
+
+    debug!("Running backup command for {}: {}", backup.description, command);
+    let mut child = shell_command(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("could not start `{}`: {}", command, e))?;
+
+    // read stderr on the side, a command that writes a lot to it would block otherwise
+    let mut stderr = child.stderr.take().ok_or("stderr of the command is not available")?;
+    let stderr_reader = thread::spawn(move || {
+        let mut text = String::new();
+        let _ = std::io::Read::read_to_string(&mut stderr, &mut text);
+        text
+    });
+
+    let mut stdout = child.stdout.take().ok_or("stdout of the command is not available")?;
+    let child = Arc::new(Mutex::new(child));
+    let (watching, timed_out) = kill_after(&child, backup.command_timeout_minutes);
+    let mut exit_status = None;
+    let stored = store_backup_file(folder_path, final_filename, &mut stdout, |_| {
+        let status = wait_child(&child)?;
+        exit_status = Some(status);
+        if timed_out.load(Ordering::SeqCst) {
+            Err(format!("`{}` was killed after {} minutes", command, backup.command_timeout_minutes).into())
+        } else if status.success() {
+            Ok(())
+        } else {
+            Err(format!("`{}` exited with {}", command, status).into())
+        }
+    });
+    drop(watching);
+
+    if exit_status.is_none() {
+        // writing the output failed, don't leave the command running
+        let _ = child.lock().map(|mut child| {
+            kill_tree(&mut child);
+            child.wait()
+        });
+    }
+
+    let stderr_text = stderr_reader.join().unwrap_or_default();
+    match stored {
+        Ok(filename) => {
+            info!("Backup command for {} finished: {}", backup.description, command);
+            Ok(filename)
+        }
+        Err(e) => {
+            let tail: Vec<&str> = stderr_text.lines().rev().take(5).collect();
+            if tail.is_empty() {
+                Err(e)
+            } else {
+                let tail: Vec<&str> = tail.into_iter().rev().collect();
+                Err(format!("{}. stderr: {}", e, tail.join(" | ")).into())
+            }
+        }
+    }
+}
+
+/** runs a backup's pre_command or post_command (`name`) through the shell with WSS_BACKUP_ID
+and `env` set. What it prints goes to `log`, a non-zero exit status is returned as an error */
+fn run_backup_hook(
+    backup: &BackupEntry,
+    name: &str,
+    command: &str,
+    env: &[(&str, String)],
+    log: &mut Vec<(LogLevel, String)>,
+) -> Result<(), String> {
+    debug!("Running {} for {}: {}", name, backup.description, command);
+    let mut child = shell_command(command)
+        .env("WSS_BACKUP_ID", &backup.id)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("{} of {} could not start `{}`: {}", name, backup.description, command, e))?;
+
+    // both are read on the side, so a hook that fills one pipe doesn't block on it
+    let readers: Vec<thread::JoinHandle<String>> = [
+        child.stdout.take().map(|out| Box::new(out) as Box<dyn std::io::Read + Send>),
+        child.stderr.take().map(|err| Box::new(err) as Box<dyn std::io::Read + Send>),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|mut pipe| {
+        thread::spawn(move || {
+            let mut text = String::new();
+            let _ = pipe.read_to_string(&mut text);
+            text
+        })
+    })
+    .collect();
+
+    let child = Arc::new(Mutex::new(child));
+    let (watching, timed_out) = kill_after(&child, backup.command_timeout_minutes);
+    let status = wait_child(&child).map_err(|e| format!("{} `{}` could not be waited for: {}", name, command, e));
+    drop(watching);
+
+    let text = readers
+        .into_iter()
+        .map(|reader| reader.join().unwrap_or_default())
+        .collect::<Vec<String>>()
+        .join("\n");
+    let lines: Vec<&str> = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    if !lines.is_empty() {
+        // the end is what matters when a hook is chatty
+        let tail = &lines[lines.len().saturating_sub(10)..];
+        log.push((LogLevel::Info, format!("{} of {}: {}", name, backup.description, tail.join(" | "))));
+    }
+
+    let status = status?;
+    if timed_out.load(Ordering::SeqCst) {
+        Err(format!("{} `{}` was killed after {} minutes", name, command, backup.command_timeout_minutes))
+    } else if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{} `{}` exited with {}", name, command, status))
+    }
+}
+
+/** kills `child` once it has run for `minutes`, unless the returned sender is dropped first.
+The flag tells whether it had to. 0 minutes never kills it */
+fn kill_after(child: &Arc<Mutex<Child>>, minutes: u64) -> (Sender<()>, Arc<AtomicBool>) {
+    let (watching, stop) = std::sync::mpsc::channel::<()>();
+    let timed_out = Arc::new(AtomicBool::new(false));
+    let child = Arc::clone(child);
+    let flag = Arc::clone(&timed_out);
+    thread::spawn(move || {
+        if minutes == 0 {
+            return;
+        }
+        if let Err(std::sync::mpsc::RecvTimeoutError::Timeout) = stop.recv_timeout(Duration::from_secs(minutes * 60)) {
+            if let Ok(mut child) = child.lock() {
+                if matches!(child.try_wait(), Ok(None)) {
+                    flag.store(true, Ordering::SeqCst);
+                    kill_tree(&mut child);
+                }
+            }
+        }
+    });
+    (watching, timed_out)
+}
+
+/** waits for a child that [kill_after] may kill meanwhile, so the lock is only held briefly */
+fn wait_child(child: &Mutex<Child>) -> std::io::Result<ExitStatus> {
+    loop {
+        let status = child
+            .lock()
+            .map_err(|_| std::io::Error::other("the command's watchdog panicked"))?
+            .try_wait()?;
+        if let Some(status) = status {
+            return Ok(status);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/** `sh -c` or `cmd /C`, without popping up a console window on Windows */
+fn shell_command(command: &str) -> Command {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]).creation_flags(CREATE_NO_WINDOW);
+        shell
+    }
+
+    #[cfg(not(windows))]
+    {
+        use std::os::unix::process::CommandExt;
+
+        // its own process group, so kill_tree also reaches what the shell started
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]).process_group(0);
+        shell
+    }
+}
+
+/** kills a child from [shell_command] together with the processes it started, which would
+otherwise keep its pipes open */
+fn kill_tree(child: &mut Child) {
+    #[cfg(windows)]
+    {
+        let _ = Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &child.id().to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+
+    #[cfg(not(windows))]
+    unsafe {
+        // SAFETY: only sends a signal, to the group shell_command gave the child
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+
+    let _ = child.kill();
+}
+
+/** unpacks a stored archive into `<name>_<timestamp>` next to it and removes the archive, so
+the folder is the restore point. It is unpacked under a .part name first, like downloads, so
+a crash never leaves a half-unpacked restore point */
+fn unpack_backup(folder: &Path, filename: &str) -> Result<(String, ArchiveFormat), Box<dyn Error>> {
+    let archive_path = folder.join(filename);
+
+    let Some(format) = ArchiveFormat::from_filename(filename) else {
+        let _ = remove_file(&archive_path);
+        return Err(format!(
+            "extract_archive is set, but {} is not a .zip, .tar or .tar.gz file",
+            filename
+        )
+        .into());
+    };
+
+    let unpacked = format!(
+        "{}_{}",
+        format.strip_extension(filename),
+        Utc::now().format("%Y-%m-%dT%H%M%S")
+    );
+    let part_path = folder.join(format!("{}.part", unpacked));
+
+    let result = archive::extract(&archive_path, format, &part_path)
+        .and_then(|()| Ok(rename(&part_path, folder.join(&unpacked))?));
+    let _ = remove_file(&archive_path);
+
+    if let Err(e) = result {
+        let _ = remove_dir_all(&part_path);
+        return Err(format!("Could not unpack {}: {}", filename, e).into());
+    }
+
+    Ok((unpacked, format))
+}
+
+/** fills in {description}, {timestamp}, {date}, {filename} and {ext} */
+fn render_filename_template(template: &str, description: &str, original_filename: &str) -> String {
+    let now = Utc::now();
+    let original = Path::new(original_filename);
+    let ext = original.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    let rendered = template
+        .replace("{description}", description)
+        .replace("{timestamp}", &now.format("%Y-%m-%dT%H%M").to_string())
+        .replace("{date}", &now.format("%Y-%m-%d").to_string())
+        .replace("{filename}", original_filename)
+        .replace("{ext}", ext);
+
+    // "{ext}" on a file without extension would otherwise leave a trailing dot
+    rendered.trim_end_matches('.').to_string()
+}
+
+fn load_log(folder: &Path) -> Result<Log, Box<dyn std::error::Error>> {
     let log_path = folder.join("log.toml");
 
     let content: String = read_to_string(log_path)?;
@@ -1220,11 +6991,83 @@ fn load_log(foldername: &str) -> Result<Log, Box<dyn std::error::Error>> {
     Ok(log)
 }
 
-fn add_to_backup_log(filename: &str, foldername: &str) -> Result<(), Box<dyn std::error::Error>> {
+/** "latest" plus the extension of the backup, keeping compound ones. EX: "db_1.sql.gz" => "latest.sql.gz" */
+fn latest_copy_name(filename: &str) -> String {
+    let path = Path::new(filename);
+
+    let extension = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext @ ("gz" | "bz2" | "xz" | "zst")) => {
+            let stem = Path::new(path.file_stem().unwrap_or_default());
+            match stem.extension().and_then(|inner| inner.to_str()) {
+                Some(inner) => format!("{}.{}", inner, ext),
+                None => ext.to_string(),
+            }
+        }
+        Some(ext) => ext.to_string(),
+        None => return "latest".to_string(),
+    };
+
+    format!("latest.{}", extension)
+}
+
+/** points latest.<ext> in the backup folder at the newest backup. A relative symlink where the
+platform allows it, a copy otherwise. It is not in log.toml, so rotation never counts it */
+fn update_latest_copy(folder: &Path, filename: &str, tracked: &[LogEntry]) -> Result<(), Box<dyn Error>> {
+    let latest_name = latest_copy_name(filename);
+
+    if tracked.iter().any(|entry| entry.filename == latest_name) {
+        return Err(format!(
+            "{} is a backup itself, set a filename_template so it isn't overwritten",
+            latest_name
+        )
+        .into());
+    }
+
+    let latest = folder.join(latest_name);
+
+    // remove_file also removes a symlink without touching what it points at
+    if latest.symlink_metadata().is_ok() {
+        remove_file(&latest)?;
+    }
+
+    #[cfg(unix)]
+    {
+        if std::os::unix::fs::symlink(filename, &latest).is_ok() {
+            return Ok(());
+        }
+    }
+
+    if folder.join(filename).is_dir() {
+        return Err("latest_copy of an unpacked archive needs symlinks, which aren't available here".into());
+    }
+
+    std::fs::copy(folder.join(filename), &latest)?;
+    Ok(())
+}
+
+fn write_backup_log(folder: &Path, entries: &[LogEntry]) {
+    let log_path = folder.join("log.toml");
+    let log = Log {
+        entries: entries.to_vec(),
+    };
+    if let Ok(toml_str) = toml::to_string(&log) {
+        // ignore write errors here; handle them if you care
+        let _ = write(&log_path, toml_str);
+    } else {
+        error!("Failed to write log file!");
+    }
+}
+
+fn add_to_backup_log(
+    filename: &str,
+    folder: &Path,
+    duration_ms: u64,
+    archive: Option<ArchiveFormat>,
+    sha256: Option<String>,
+    server_checksum: Option<String>,
+) -> Result<LogEntry, Box<dyn std::error::Error>> {
     // makes sure there is a log file
 
-    let folder = Path::new(foldername);
-
     let mut candidate_path = folder.join("log");
 
     candidate_path.set_extension("toml");
@@ -1238,7 +7081,7 @@ fn add_to_backup_log(filename: &str, foldername: &str) -> Result<(), Box<dyn std
     if !log_exists {
         let _ = File::create(&candidate_path);
     } else {
-        let logs_load = load_log(foldername);
+        let logs_load = load_log(folder);
 
         match logs_load {
             Ok(log_entries) => {
@@ -1251,28 +7094,140 @@ fn add_to_backup_log(filename: &str, foldername: &str) -> Result<(), Box<dyn std
 
     //add the new entry to the log
 
+    // an unpacked archive counts with everything in its folder
+    let size = archive::disk_size(&folder.join(filename));
+
     let new_entry = LogEntry {
         filename: filename.to_string(),
         timestamp: Utc::now().to_rfc3339(),
-        size: 12345,
+        size,
+        duration_ms: Some(duration_ms),
+        archive: archive.map(|format| format.as_str().to_string()),
+        sha256,
+        server_checksum,
     };
 
-    logs.entries.push(new_entry);
+    logs.entries.push(new_entry.clone());
 
     //write to the log file
 
     let toml_string = toml::to_string(&logs)?;
     write(candidate_path, toml_string)?;
 
+    Ok(new_entry)
+}
+
+/** `<file>.meta.json` next to a restore point (or its unpacked folder) */
+fn sidecar_path(folder: &Path, filename: &str) -> PathBuf {
+    folder.join(format!("{}.meta.json", filename))
+}
+
+/** writes what log.toml knows about a restore point next to it, so the file documents itself
+for other tools. It isn't in the log, so rotation doesn't count it, delete_file removes it */
+fn write_sidecar(backup: &BackupEntry, folder: &Path, entry: &LogEntry) -> Result<(), Box<dyn Error>> {
+    let source = match &backup.command {
+        Some(command) => json!({ "command": command }),
+        None => json!({ "url": backup.url }),
+    };
+
+    let metadata = json!({
+        "backup": backup.id,
+        "description": backup.description,
+        "source": source,
+        "filename": entry.filename,
+        "timestamp": entry.timestamp,
+        "size": entry.size,
+        "sha256": entry.sha256,
+        "server_checksum": entry.server_checksum,
+        "duration_ms": entry.duration_ms,
+        "archive": entry.archive,
+    });
+
+    write(sidecar_path(folder, &entry.filename), serde_json::to_string_pretty(&metadata)?)?;
     Ok(())
 }
 
-fn load_internal_log() -> Result<InternalLog, Box<dyn std::error::Error>> {
-    let log_path = Path::new("internal_log.toml");
+fn load_internal_log(format: InternalLogFormat) -> Result<InternalLog, Box<dyn std::error::Error>> {
+    let content: String = read_to_string(format.path())?;
+
+    let appended = match format {
+        InternalLogFormat::Toml => match toml::from_str::<InternalLog>(&content) {
+            Ok(log) => log.entries,
+            Err(e) => {
+                // most likely an append that was cut short, drop the last entry and carry on
+                let cut = content.rfind("[[entries]]").ok_or(e)?;
+                let log: InternalLog = toml::from_str(&content[..cut])?;
+                warn!("Dropping an unfinished entry at the end of {}", format.path().display());
+                write(format.path(), &content[..cut])?;
+                log.entries
+            }
+        },
+        InternalLogFormat::Jsonl => content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    // most likely the last line of a write that was cut short
+                    warn!("Skipping unreadable line in {}: {}", format.path().display(), e);
+                    None
+                }
+            })
+            .collect(),
+    };
 
-    let content: String = read_to_string(log_path)?;
-    let log: InternalLog = toml::from_str(&content)?;
-    Ok(log)
+    // a repeated message is appended again with its new count, keep the latest
+    let mut entries: Vec<InternalLogEntry> = Vec::new();
+    for entry in appended {
+        match entries.last_mut() {
+            Some(last) if last.message == entry.message && last.level == entry.level => *last = entry,
+            _ => entries.push(entry),
+        }
+    }
+
+    Ok(InternalLog { entries })
+}
+
+/** where internal_log.toml, state.toml, stats.toml and the backup folders go. Set once at
+startup by --data-dir, or else by data_dir in config.toml */
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/** the data folder, empty (the working directory) until one is set */
+fn data_dir() -> &'static Path {
+    DATA_DIR.get().map_or(Path::new(""), PathBuf::as_path)
+}
+
+/** a state file inside the data folder */
+fn data_path(name: &str) -> PathBuf {
+    data_dir().join(name)
+}
+
+/** sets the data folder and creates it if it is missing. The first call wins, so --data-dir
+overrides data_dir in config.toml, which is also why a reloaded config can't move it */
+fn set_data_dir(dir: &str) -> std::io::Result<()> {
+    if DATA_DIR.get().is_some() {
+        return Ok(());
+    }
+
+    let dir = PathBuf::from(dir.trim());
+    if !dir.as_os_str().is_empty() {
+        create_dir_all(&dir)?;
+        info!("Storing data in {}", dir.display());
+    }
+    let _ = DATA_DIR.set(dir);
+    Ok(())
+}
+
+fn load_state() -> Result<AppState, Box<dyn std::error::Error>> {
+    let content: String = read_to_string(data_path("state.toml"))?;
+    let state: AppState = toml::from_str(&content)?;
+    Ok(state)
+}
+
+fn write_state(state: &AppState) -> Result<(), Box<dyn std::error::Error>> {
+    let toml_string = toml::to_string(state)?;
+    write(data_path("state.toml"), toml_string)?;
+    Ok(())
 }
 
 fn extract_filename_from_cd(cd: &str) -> Option<String> {
@@ -1280,28 +7235,497 @@ fn extract_filename_from_cd(cd: &str) -> Option<String> {
     let parts: Vec<&str> = cd.split(';').collect();
     for part in parts {
         let trimmed = part.trim();
-        if trimmed.starts_with("filename=") {
-            let filename = trimmed[9..].trim_matches('"').to_string();
+        if let Some(value) = trimmed.strip_prefix("filename=") {
+            let filename = value.trim_matches('"').to_string();
             return Some(filename);
         }
     }
     None
 }
 
-fn format_timestamp(ts: &str) -> String {
-    use chrono::{DateTime, Local};
+/** a random number in 0..upper, good enough for spreading load. Avoids pulling in a rand crate */
+fn random_below(upper: u32) -> u32 {
+    use std::hash::{BuildHasher, Hasher};
 
-    match DateTime::parse_from_rfc3339(ts) {
-        Ok(parsed) => {
-            let local = parsed.with_timezone(&Local);
-            local.format("%d.%m.%Y %H:%M").to_string()
+    if upper == 0 {
+        return 0;
+    }
+
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(Utc::now().timestamp_nanos_opt().unwrap_or_default() as u128);
+    (hasher.finish() % upper as u64) as u32
+}
+
+/// Sends every minute boundary to the returned receiver, including the ones missed while the
+/// machine slept. Stops once `shutting_down` is set or the receiver is dropped.
+fn spawn_ticker(shutting_down: Arc<AtomicBool>) -> Receiver<DateTime<Utc>> {
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    thread::spawn(move || {
+        let mut last_tick = minute_start(Utc::now());
+
+        loop {
+            let now = Utc::now();
+            let next_tick = minute_start(now) + chrono::Duration::minutes(1);
+
+            let sleep_dur = (next_tick - now)
+                .to_std()
+                .unwrap_or_else(|_| Duration::from_secs(60));
+
+            thread::sleep(sleep_dur);
+
+            if shutting_down.load(Ordering::SeqCst) {
+                break; // no new ticks once the app is closing
+            }
+
+            let current_tick = minute_start(Utc::now());
+
+            if current_tick == last_tick {
+                continue; // woke up early, this minute was already sent
+            }
+
+            let ticks = missed_ticks(last_tick, current_tick);
+            last_tick = current_tick;
+
+            // poke the UI
+            if ticks.into_iter().any(|tick| tx.send(tick).is_err()) {
+                break; // if the receiver was dropped, exit the loop
+            }
+        }
+    });
+
+    rx
+}
+
+/** the machine's hostname, "unknown" if it can't be read */
+fn machine_hostname() -> String {
+    hostname::get()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/** the name alerts are labelled with: http.instance_name if set, otherwise the hostname */
+fn instance_label(http: &HttpSettings) -> String {
+    if http.instance_name.is_empty() {
+        machine_hostname()
+    } else {
+        http.instance_name.clone()
+    }
+}
+
+fn minute_start(time: DateTime<Utc>) -> DateTime<Utc> {
+    time.with_second(0)
+        .and_then(|t| t.with_nanosecond(0))
+        .unwrap_or(time)
+}
+
+/// The minute boundaries after `last_tick` up to and including `current_tick`. After a sleep
+/// this replays what was missed (at most a week). If the clock went backwards only the current
+/// minute is returned instead of a catch-up batch.
+fn missed_ticks(last_tick: DateTime<Utc>, current_tick: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let max_catch_up = 7 * 24 * 60;
+    let missed = (current_tick - last_tick).num_minutes();
+
+    if missed <= 0 {
+        return vec![current_tick];
+    }
+
+    let first = missed.min(max_catch_up) - 1;
+    (0..=first)
+        .rev()
+        .map(|back| current_tick - chrono::Duration::minutes(back))
+        .collect()
+}
+
+#[cfg(test)]
+mod missed_tick_tests {
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn a_gap_replays_every_minute_boundary_in_order() {
+        assert_eq!(missed_ticks(at(10, 0), at(10, 5)), [at(10, 1), at(10, 2), at(10, 3), at(10, 4), at(10, 5)]);
+    }
+
+    #[test]
+    fn a_normal_tick_or_a_clock_going_back_is_just_the_current_minute() {
+        assert_eq!(missed_ticks(at(10, 0), at(10, 1)), [at(10, 1)]);
+        assert_eq!(missed_ticks(at(10, 0), at(9, 30)), [at(9, 30)]);
+    }
+
+    #[test]
+    fn catch_up_is_capped_at_a_week() {
+        let ticks = missed_ticks(at(10, 0), at(10, 0) + chrono::Duration::days(30));
+
+        assert_eq!(ticks.len(), 7 * 24 * 60);
+        assert_eq!(ticks.last(), Some(&(at(10, 0) + chrono::Duration::days(30))));
+    }
+
+    #[test]
+    fn a_backup_scheduled_during_the_gap_runs_once_on_wake() {
+        let mut app = StatusChecker {
+            backups: vec![BackupEntry {
+                description: "Daily".to_string(),
+                id: "daily".to_string(),
+                interval: "d".to_string(),
+                time: 10 * 60 + 3, // 10:03
+                enabled: true,
+                ..Default::default()
+            }],
+            timezone: Tz::UTC,
+            ..Default::default()
+        };
+
+        let ticks = missed_ticks(at(10, 0), at(10, 5));
+        assert_eq!(ticks.iter().filter(|tick| is_backup_due(&app.backups[0], tick, app.timezone)).count(), 1);
+
+        app.auto_backup(&ticks);
+        // the same gap delivered again, e.g. after the clock was set back, doesn't queue it twice
+        app.auto_backup(&ticks);
+
+        let queued: Vec<&str> = app.backup_queue.iter().map(|queued| queued.id.as_str()).collect();
+        assert_eq!(queued, ["daily"]);
+    }
+}
+
+/** whether a backup is scheduled in the minute starting at `tick`. Schedules are wall-clock
+times in `timezone`. When the clocks go back, a time that happens twice only runs the first
+time, and when they go forward, a time that is skipped runs right after the jump. Hourly
+backups, and custom ones with a period of an hour or less, just follow the real hours */
+fn is_backup_due(backup: &BackupEntry, tick: &DateTime<Utc>, timezone: Tz) -> bool {
+    let local = tick.with_timezone(&timezone);
+    let wall_clock = local.naive_local();
+
+    let sub_hourly = backup.interval == "custom" && backup.period_minutes.is_some_and(|period| period <= 60);
+    if backup.cron.is_none() && (backup.interval == "h" || sub_hourly) {
+        return is_scheduled_at(backup, wall_clock);
+    }
+
+    if is_scheduled_at(backup, wall_clock) {
+        return timezone.from_local_datetime(&wall_clock).earliest() == Some(local);
+    }
+
+    let one_minute = chrono::Duration::minutes(1);
+    let previous = (*tick - one_minute).with_timezone(&timezone).naive_local();
+    let mut skipped = previous + one_minute;
+    while skipped < wall_clock {
+        if is_scheduled_at(backup, skipped) {
+            return true;
+        }
+        skipped += one_minute;
+    }
+
+    false
+}
+
+/** whether a backup's interval/time or cron expression matches a wall-clock minute */
+fn is_scheduled_at(backup: &BackupEntry, wall_clock: NaiveDateTime) -> bool {
+    let minute = wall_clock.minute();
+    let hour = wall_clock.hour() * 60;
+    let day = wall_clock.weekday() as u32 * 24 * 60;
+    let month = (wall_clock.day() - 1) * 24 * 60; // day() is 1-based, time counts from the 1st
+
+    let interval = &backup.interval;
+    let time = backup.time;
+
+    if backup.cron.is_some() {
+        // an invalid cron expression never fires, the error was logged at load
+        match &backup.schedule {
+            // cron only looks at the fields, so the wall clock can be passed in as UTC
+            Some(schedule) => schedule.includes(minute_start(Utc.from_utc_datetime(&wall_clock))),
+            None => false,
+        }
+    } else if interval == "h" {
+        let hour_time = time % 60;
+        minute == hour_time
+    } else if interval == "d" {
+        let day_minute = hour + minute;
+        let day_time = time % (24 * 60);
+        day_minute == day_time
+    } else if interval == "w" && !backup.weekday_set.is_empty() {
+        backup.weekday_set.contains(&wall_clock.weekday()) && hour + minute == time % (24 * 60)
+    } else if interval == "w" {
+        let week_minute = day + hour + minute;
+        let week_time = time % (7 * 24 * 60);
+        week_minute == week_time
+    } else if interval == "m" {
+        let month_minute = month + hour + minute;
+        let month_time = time % (31 * 24 * 60);
+        month_minute == month_time
+    } else if interval == "custom" {
+        // from time on until midnight, and again from time the next day
+        match backup.period_minutes {
+            Some(period) if period > 0 => hour + minute >= time && (hour + minute - time).is_multiple_of(period),
+            _ => false,
+        }
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod custom_interval_tests {
+    use super::*;
+
+    #[test]
+    fn runs_from_at_until_midnight_then_again_from_at() {
+        let backup = BackupEntry {
+            interval: "custom".to_string(),
+            period_minutes: Some(360),
+            time: 7 * 60, // at = "07:00"
+            ..Default::default()
+        };
+
+        let runs: Vec<u32> = (0..24 * 60)
+            .filter(|minute| {
+                let wall_clock = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap().and_hms_opt(minute / 60, minute % 60, 0).unwrap();
+                is_scheduled_at(&backup, wall_clock)
+            })
+            .collect();
+
+        assert_eq!(runs, [7 * 60, 13 * 60, 19 * 60]);
+    }
+
+    #[test]
+    fn the_night_without_runs_is_not_overdue() {
+        let mut backup = BackupEntry {
+            interval: "custom".to_string(),
+            period_minutes: Some(360),
+            time: 7 * 60,
+            ..Default::default()
+        };
+        backup.logs.push(LogEntry {
+            filename: "backup.sql".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2024, 6, 1, 19, 0, 0).unwrap().to_rfc3339(),
+            size: 4,
+            duration_ms: None,
+            archive: None,
+            sha256: None,
+            server_checksum: None,
+        });
+
+        // 01:00 is a period after the last run, but the next one is at 07:00
+        assert!(!is_backup_overdue(&backup, Utc.with_ymd_and_hms(2024, 6, 2, 1, 0, 0).unwrap(), Tz::UTC));
+        assert!(!is_backup_overdue(&backup, Utc.with_ymd_and_hms(2024, 6, 2, 7, 0, 30).unwrap(), Tz::UTC));
+        assert!(is_backup_overdue(&backup, Utc.with_ymd_and_hms(2024, 6, 2, 7, 1, 0).unwrap(), Tz::UTC));
+    }
+
+    #[test]
+    fn a_manual_backup_before_a_missed_run_does_not_hide_it() {
+        let mut backup = BackupEntry {
+            interval: "d".to_string(),
+            time: 2 * 60, // at = "02:00"
+            ..Default::default()
+        };
+        backup.logs.push(LogEntry {
+            filename: "backup.sql".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2024, 6, 2, 1, 50, 0).unwrap().to_rfc3339(),
+            size: 4,
+            duration_ms: None,
+            archive: None,
+            sha256: None,
+            server_checksum: None,
+        });
+
+        // less than a day after the manual backup, but the 02:00 run was missed
+        assert!(is_backup_overdue(&backup, Utc.with_ymd_and_hms(2024, 6, 2, 9, 0, 0).unwrap(), Tz::UTC));
+    }
+}
+
+/** time of the newest restore point in log.toml */
+fn last_backup_success(backup: &BackupEntry) -> Option<DateTime<Utc>> {
+    backup
+        .logs
+        .iter()
+        .filter_map(|entry| DateTime::parse_from_rfc3339(&entry.timestamp).ok())
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .max()
+}
+
+/** true when max_staleness_hours is set and the last success (or the app start, before the
+first one) is older than that */
+fn is_backup_stale(backup: &BackupEntry, now: DateTime<Utc>) -> bool {
+    let Some(hours) = backup.max_staleness_hours else {
+        return false;
+    };
+
+    let last = last_backup_success(backup).unwrap_or(backup.watched_since);
+    now - last > chrono::Duration::hours(hours as i64)
+}
+
+/** true when a scheduled run has passed since the newest restore point, or there is none yet.
+The current minute doesn't count, the tick for it runs the backup anyway */
+fn is_backup_overdue(backup: &BackupEntry, now: DateTime<Utc>, timezone: Tz) -> bool {
+    let Some(last) = last_backup_success(backup) else {
+        // never backed up, as long as it has a usable schedule at all
+        return match &backup.cron {
+            Some(_) => backup.schedule.is_some(),
+            None => interval_period_minutes(&backup.interval, backup.period_minutes).is_some(),
+        };
+    };
+
+    match last_scheduled_slot(backup, now, timezone) {
+        Some(slot) => minute_start(last) < slot,
+        None => false,
+    }
+}
+
+/** the start of the newest minute before the current one that a backup was due in, looking
+back at most a monthly period and a day. None when nothing in that window was due */
+fn last_scheduled_slot(backup: &BackupEntry, now: DateTime<Utc>, timezone: Tz) -> Option<DateTime<Utc>> {
+    let one_minute = chrono::Duration::minutes(1);
+    let mut slot = minute_start(now);
+    for _ in 0..(31 + 1) * 24 * 60 {
+        slot -= one_minute;
+        if is_backup_due(backup, &slot, timezone) {
+            return Some(slot);
         }
+    }
+
+    None
+}
+
+/** EX: 1536 => "1.5 KB", 3221225472 => "3.0 GB" */
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/** free bytes on the disk a backup folder is on. The folder is only created by the first
+backup, so before that the nearest existing parent is asked */
+fn free_space(folder: &Path) -> std::io::Result<u64> {
+    let existing = folder.ancestors().find(|dir| dir.exists()).unwrap_or(Path::new("."));
+    fs2::available_space(existing)
+}
+
+fn format_timestamp(ts: &str, timezone: Option<Tz>) -> String {
+    match DateTime::parse_from_rfc3339(ts) {
+        Ok(parsed) => format_in_zone(parsed.with_timezone(&Utc), timezone, "%d.%m.%Y %H:%M"),
         Err(_) => "Invalid timestamp".to_string(),
     }
 }
 
-fn calc_time_to_backup(time: &u32, interval: &str) -> String {
-    let current_time = Utc::now();
+/** internal log timestamps, to the second and with the zone. Unparsable ones are shown as stored */
+fn format_log_timestamp(ts: &str, timezone: Option<Tz>) -> String {
+    match DateTime::parse_from_rfc3339(ts) {
+        Ok(parsed) => format_in_zone(parsed.with_timezone(&Utc), timezone, "%Y-%m-%d %H:%M:%S %Z"),
+        Err(_) => ts.to_string(),
+    }
+}
+
+/** `at` in the configured timezone, or in the system's zone when none is set */
+fn format_in_zone(at: DateTime<Utc>, timezone: Option<Tz>, format: &str) -> String {
+    match timezone {
+        Some(timezone) => at.with_timezone(&timezone).format(format).to_string(),
+        None => at.with_timezone(&Local).format(format).to_string(),
+    }
+}
+
+/** minutes in one period of a backup interval, None for unknown intervals and for "custom"
+without a usable period_minutes */
+fn interval_period_minutes(interval: &str, period_minutes: Option<u32>) -> Option<u32> {
+    match interval {
+        "h" => Some(60),
+        "d" => Some(24 * 60),
+        "w" => Some(7 * 24 * 60),
+        "m" => Some(31 * 24 * 60),
+        "custom" => period_minutes.filter(|period| (1..=24 * 60).contains(period)),
+        _ => None,
+    }
+}
+
+/** when a backup runs, for the window. Like describe_backup_time, with the weekday list
+for weekly backups on several days, EX: "Mon/Wed/Fri 02:30" */
+fn describe_backup_schedule(backup: &BackupEntry) -> String {
+    if backup.interval == "w" && !backup.weekday_set.is_empty() {
+        let days: Vec<String> = backup.weekday_set.iter().map(|day| day.to_string()).collect();
+        format!("{} {}", days.join("/"), describe_backup_time(backup.time, "d"))
+    } else {
+        describe_backup_time(backup.time, &backup.interval)
+    }
+}
+
+/** readable form of a backup time within its interval, EX: 6485 weekly => "Fri 12:05".
+For "custom" it is the first run of the day */
+fn describe_backup_time(time: u32, interval: &str) -> String {
+    const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+    let clock = format!("{:02}:{:02}", (time / 60) % 24, time % 60);
+
+    match interval {
+        "h" => format!("xx:{:02}", time % 60),
+        "d" => clock,
+        "w" => format!("{} {}", WEEKDAYS[(time / 1440) as usize % 7], clock),
+        "m" => format!("day {} {}", time / 1440 + 1, clock),
+        _ => clock,
+    }
+}
+
+/** minute offset within the interval for a readable `at`, the inverse of describe_backup_time.
+EX: ":05" hourly => 5, "02:30" daily => 150, "Fri 12:05" weekly => 6485, "15 02:30" monthly */
+fn parse_backup_at(at: &str, interval: &str) -> Result<u32, String> {
+    let parse_clock = |clock: &str| {
+        NaiveTime::parse_from_str(clock, "%H:%M")
+            .map(|parsed| parsed.hour() * 60 + parsed.minute())
+            .map_err(|_| format!("\"{}\" is not a HH:MM time", clock))
+    };
+
+    let example = match interval {
+        "h" => "\":05\"",
+        "d" => "\"02:30\"",
+        "w" => "\"Fri 12:05\"",
+        "m" => "\"15 02:30\" (day of the month first)",
+        "custom" => "\"00:30\" (the first run of the day)",
+        _ => return Err(format!("unknown interval \"{}\"", interval)),
+    };
+
+    let parts: Vec<&str> = at.split_whitespace().collect();
+    let parsed = match (interval, parts.as_slice()) {
+        ("h", [minute]) => minute
+            .trim_start_matches(':')
+            .parse::<u32>()
+            .ok()
+            .filter(|minute| *minute < 60)
+            .ok_or_else(|| format!("\"{}\" is not a minute of the hour", minute)),
+        ("d", [clock]) | ("custom", [clock]) => parse_clock(clock),
+        ("w", [day, clock]) => {
+            let day = day
+                .parse::<Weekday>()
+                .map_err(|_| format!("\"{}\" is not a weekday", day))?;
+            Ok(day.num_days_from_monday() * 24 * 60 + parse_clock(clock)?)
+        }
+        ("m", [day, clock]) | ("m", ["day", day, clock]) => {
+            let day = day
+                .parse::<u32>()
+                .ok()
+                .filter(|day| (1..=31).contains(day))
+                .ok_or_else(|| format!("\"{}\" is not a day of the month", day))?;
+            Ok((day - 1) * 24 * 60 + parse_clock(clock)?)
+        }
+        _ => Err(format!("\"{}\" doesn't fit interval \"{}\"", at, interval)),
+    };
+
+    parsed.map_err(|e| format!("{}, EX: at = {}", e, example))
+}
+
+fn calc_time_to_backup(backup: &BackupEntry, timezone: Tz) -> String {
+    let time = &backup.time;
+    let interval = backup.interval.as_str();
+    let current_time = Utc::now().with_timezone(&timezone);
     let mut time_to_backup: i32 = 10000;
     let mut wrap_constant = 0;
 
@@ -1323,6 +7747,16 @@ fn calc_time_to_backup(time: &u32, interval: &str) -> String {
             (weekday * 1440 + current_time.hour() * 60 + current_time.minute()) as i32;
         time_to_backup = *time as i32 - current_minutes;
         wrap_constant = 10080;
+
+        // the nearest of the listed days, each at the same time of day
+        if let Some(nearest) = backup
+            .weekday_set
+            .iter()
+            .map(|day| (day.num_days_from_monday() * 1440 + time % 1440) as i32 - current_minutes)
+            .min_by_key(|minutes| minutes.rem_euclid(10080))
+        {
+            time_to_backup = nearest.rem_euclid(10080);
+        }
     }
 
     if interval == "m" {
@@ -1335,13 +7769,46 @@ fn calc_time_to_backup(time: &u32, interval: &str) -> String {
         wrap_constant = minutes_in_month;
     }
 
+    if let ("custom", Some(period)) = (interval, backup.period_minutes.filter(|period| *period > 0)) {
+        let current_minutes = (current_time.hour() * 60 + current_time.minute()) as i32;
+        let offset = *time as i32;
+        let period = period as i32;
+        // next run today, or else the first one tomorrow since the count restarts at midnight
+        let runs_passed = (current_minutes - offset).max(0) + period - 1;
+        let next = offset + runs_passed / period * period;
+        time_to_backup = if next < 1440 { next - current_minutes } else { 1440 + offset - current_minutes };
+    }
+
     if time_to_backup < 0 {
-        time_to_backup = wrap_constant + time_to_backup;
+        time_to_backup += wrap_constant;
     }
 
     time_to_backup_to_text(time_to_backup)
 }
 
+/** accepts both classic 5-field cron and the 6/7-field form with seconds (and year) */
+fn parse_cron(expression: &str) -> Result<Schedule, Box<dyn std::error::Error>> {
+    let trimmed = expression.trim();
+
+    let full_expression = if trimmed.split_whitespace().count() == 5 {
+        format!("0 {}", trimmed)
+    } else {
+        trimmed.to_string()
+    };
+
+    Ok(Schedule::from_str(&full_expression)?)
+}
+
+fn calc_time_to_cron_backup(schedule: &Schedule, timezone: Tz) -> String {
+    match schedule.upcoming(timezone).next() {
+        Some(next) => {
+            let minutes = (next.with_timezone(&Utc) - Utc::now()).num_minutes().max(0) as i32;
+            time_to_backup_to_text(minutes)
+        }
+        None => "never.".to_string(),
+    }
+}
+
 fn time_to_backup_to_text(time_to_backup: i32) -> String {
     let time_string: String;
 
@@ -1367,16 +7834,14 @@ fn try_to_send_email(
 ) -> Result<(), Box<dyn std::error::Error>> {
 
 
-    //log the parameters
+    //log the parameters, the body can contain log lines so it is only shown at debug level
 
-    println!("Sending email to: {}", address);
-    println!("Subject: {}", subject);
-    println!("Content: {}", content);
-    println!("SMTP server: {}", smtp.server);
-    println!("SMTP port: {}", smtp.port);
-    println!("SMTP username: {}", smtp.username);
-    println!("SMTP password: {}", "<hidden>");
-    println!("SMTP from: {}", smtp.from);
+    info!("Sending email to: {} with subject '{}'", address, subject);
+    debug!(
+        "SMTP server: {}:{}, username: {}, password: <hidden>, from: {}",
+        smtp.server, smtp.port, smtp.username, smtp.from
+    );
+    debug!("Email content: {}", content);
 
 
 
@@ -1390,71 +7855,128 @@ fn try_to_send_email(
 
     let creds = Credentials::new(smtp.username.to_owned(), smtp.password.to_owned());
 
-    let tls_parameters = TlsParameters::new(smtp.server.clone())?;
+    let transport_builder = match smtp.tls_mode.as_str() {
+        "starttls" => {
+            let tls_parameters = TlsParameters::new(smtp.server.clone())?;
+            SmtpTransport::relay(&smtp.server)?
+                .tls(Tls::Opportunistic(tls_parameters)) // Use Tls::Opportunistic for STARTTLS on port 587
+        }
+        // relay() defaults to a TLS wrapped connection, which is what port 465 expects
+        "implicit" => SmtpTransport::relay(&smtp.server)?,
+        "none" => {
+            if !smtp.password.is_empty() {
+                warn!("Sending SMTP credentials without TLS to {}", smtp.server);
+            }
+            SmtpTransport::builder_dangerous(&smtp.server)
+        }
+        other => return Err(format!("Unknown smtp tls_mode: {}", other).into()),
+    };
 
-    let mailer = SmtpTransport::relay(&smtp.server)?
+    let mailer = transport_builder
         .port(smtp.port)
         .credentials(creds)
-        .tls(Tls::Opportunistic(tls_parameters)) // Use Tls::Opportunistic for STARTTLS on port 587
         .timeout(Some(Duration::from_secs(20)))  // Connection/operation timeout
         .build(); // Builds a synchronous transport
 
     mailer.send(&email)?;
-    println!("Email sent successfully to {} with subject '{}'", address, subject);
+    info!("Email sent successfully to {} with subject '{}'", address, subject);
     Ok(())
 
 }
 
-pub fn delete_file(filename: &str, folder_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let folder = Path::new(folder_name);
-
+pub fn delete_file(filename: &str, folder: &Path) -> Result<(), Box<dyn std::error::Error>> {
     if !folder.exists() {
-        return Err(format!("Folder `{}` does not exist", folder_name).into());
+        return Err(format!("Folder `{}` does not exist", folder.display()).into());
     }
     if !folder.is_dir() {
-        return Err(format!("`{}` is not a directory", folder_name).into());
+        return Err(format!("`{}` is not a directory", folder.display()).into());
+    }
+    if !is_relative_file_path(filename) {
+        return Err(format!("Refusing to delete `{}`: not a path inside the backup folder", filename).into());
     }
 
     let path: PathBuf = folder.join(filename);
 
     if !path.exists() {
-        return Err(format!("File `{}` not found in `{}`", filename, folder_name).into());
+        return Err(format!("File `{}` not found in `{}`", filename, folder.display()).into());
     }
+    // unpacked archives are folders. remove_dir_all doesn't follow symlinks
     if path.is_dir() {
-        return Err(format!("`{}` is a directory, not a file", path.display()).into());
+        remove_dir_all(&path)?;
+    } else {
+        remove_file(&path)?;
     }
 
-    remove_file(&path)?;
+    // write_sidecar metadata goes with its restore point, most backups don't have one
+    let _ = remove_file(sidecar_path(folder, filename));
+
+    // partition_by date folders that are empty now go too. remove_dir only removes empty ones
+    let mut parent = path.parent();
+    while let Some(dir) = parent.filter(|dir| *dir != folder) {
+        if std::fs::remove_dir(dir).is_err() {
+            break;
+        }
+        parent = dir.parent();
+    }
 
     Ok(())
 }
 
-fn print_to_internal_log_file(internal_log: InternalLog) {
-    let log_path = Path::new("internal_log.toml");
-    let toml_str = toml::to_string(&internal_log).unwrap();
+/** adds entries to the end of the log file, as [[entries]] tables or JSON lines */
+fn append_internal_log(format: InternalLogFormat, entries: &[InternalLogEntry]) -> Result<(), Box<dyn Error>> {
+    if entries.is_empty() {
+        return Ok(());
+    }
 
-    let result = write(&log_path, &toml_str);
+    let text = match format {
+        InternalLogFormat::Toml => {
+            let tables = toml::to_string(&InternalLog {
+                entries: entries.to_vec(),
+            })?;
+            format!("\n{}", tables)
+        }
+        InternalLogFormat::Jsonl => {
+            let mut lines = String::new();
+            for entry in entries {
+                lines.push_str(&serde_json::to_string(entry)?);
+                lines.push('\n');
+            }
+            lines
+        }
+    };
 
-    match result {
-        Ok(_) => println!("Log written successfully!"),
-        Err(e) => println!("Failed to write log: {}", e),
-    }
+    let mut file = OpenOptions::new().create(true).append(true).open(format.path())?;
+    file.write_all(text.as_bytes())?;
+
+    debug!("Appended {} entries to the log", entries.len());
+    Ok(())
 }
 
 fn join_with_line_breaks(lines: Vec<String>) -> String {
     lines.join("\n")
 }
 
+/** writes the (success, message) results of sending a warning to the console log */
+fn log_delivery(results: &[(bool, String)]) {
+    for (success, message) in results {
+        if *success {
+            info!("{}", message);
+        } else {
+            error!("{}", message);
+        }
+    }
+}
+
 
 
 fn send_warning_post_request(
     token: &str,
     json_payload_string: &str,
     url: &str,
+    http: &HttpSettings,
+    shutting_down: &AtomicBool,
 ) -> Result<(), Box<dyn Error>> {
-    let client = Client::builder()
-        .timeout(Duration::from_secs(15)) // Set a reasonable timeout
-        .build()?;
+    let client = build_client(http, Duration::from_secs(15))?; // Set a reasonable timeout
 
     let mut request_builder = client.post(url)
         .header(CONTENT_TYPE, "application/json")
@@ -1464,7 +7986,9 @@ fn send_warning_post_request(
         request_builder = request_builder.header(AUTHORIZATION, format!("Bearer {}", token));
     }
 
-    let response = request_builder.send()?;
+    debug!("Sending warning POST to {}", url);
+    // a warning that is minutes late is of little use, so only wait briefly for a rate limit
+    let response = send_with_retry_after(request_builder, url, Duration::from_secs(30), shutting_down)?;
 
     if !response.status().is_success() {
         let status = response.status();
@@ -1480,15 +8004,155 @@ fn send_warning_post_request(
 }
 
 
-fn restore_backup(url: &str, filename: &str, token: &str) -> Result<(), Box<dyn Error>> {
+/** the curl equivalent of restore_backup() for the first restore target, for reproducing
+failed restores by hand */
+fn restore_curl_command(
+    backup: &BackupEntry,
+    filename: &str,
+    archive: Option<ArchiveFormat>,
+    token: &str,
+    http: &HttpSettings,
+) -> String {
+    // an unpacked archive has to be packed up again first, next to its folder
+    let (pack, filename) = match archive {
+        Some(format) => {
+            let packed = format!("{}.{}", filename, format.as_str());
+            let pack = match format {
+                ArchiveFormat::Zip => format!("(cd {} && zip -r - .) > {} && ", shell_quote(filename), shell_quote(&packed)),
+                ArchiveFormat::Tar => format!("tar -cf {} -C {} . && ", shell_quote(&packed), shell_quote(filename)),
+                ArchiveFormat::TarGz => format!("tar -czf {} -C {} . && ", shell_quote(&packed), shell_quote(filename)),
+            };
+            (pack, packed)
+        }
+        None => (String::new(), filename.to_string()),
+    };
+
+    let mut command = format!("{}curl -X POST -A {}", pack, shell_quote(&http.user_agent));
+
+    if http.danger_accept_invalid_certs {
+        command.push_str(" -k");
+    } else if let Some(path) = &http.ca_cert_path {
+        command.push_str(&format!(" --cacert {}", shell_quote(path)));
+    }
+
+    if !http.instance_name.is_empty() {
+        command.push_str(&format!(
+            " -H {}",
+            shell_quote(&format!("X-WebSync-Instance: {}", header_safe(&http.instance_name)))
+        ));
+    }
+
+    if !token.is_empty() {
+        command.push_str(&format!(
+            " -H {}",
+            shell_quote(&format!("Authorization: Bearer {}", token))
+        ));
+    }
+
+    command.push_str(&format!(
+        " -F {} {}",
+        shell_quote(&format!(
+            "{}=@{};type={}",
+            backup.restore_field_name, filename, backup.restore_content_type
+        )),
+        shell_quote(backup.restore.urls().first().copied().unwrap_or_default())
+    ));
+
+    command
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/** uploads a restore point to the first `restore` target that accepts it. Returns that target
+and the errors of the targets tried before it */
+fn restore_backup(
+    backup: &BackupEntry,
+    filename: &str,
+    archive: Option<ArchiveFormat>,
+    token: &str,
+    http: &HttpSettings,
+) -> Result<(String, Vec<String>), Box<dyn Error>> {
+    let path = Path::new(filename);
+
+    // an unpacked archive is packed up again for the upload
+    if let Some(format) = archive.filter(|_| path.is_dir()) {
+        let temp = std::env::temp_dir().join(format!("websync-restore-{}", Uuid::new_v4()));
+        create_dir_all(&temp)?;
+
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("backup");
+        let packed = temp.join(format!("{}.{}", name, format.as_str()));
+
+        let result = archive::pack(path, format, &packed)
+            .and_then(|()| restore_backup(backup, &packed.display().to_string(), None, token, http));
+        let _ = remove_dir_all(&temp);
+        return result;
+    }
+
+    if !path.exists() {
+        return Err(format!("Backup file `{}` is missing", filename).into());
+    }
+    if !path.is_file() {
+        return Err(format!("`{}` is not a file", filename).into());
+    }
+    if path.metadata()?.len() == 0 {
+        return Err(format!("Backup file `{}` is empty, refusing to restore it", filename).into());
+    }
+    File::open(path).map_err(|e| format!("Backup file `{}` cannot be opened: {}", filename, e))?;
+
+    let mut failed = Vec::new();
+    for url in backup.restore.urls() {
+        match upload_restore(backup, url, filename, token, http) {
+            Ok(()) => return Ok((url.to_string(), failed)),
+            Err(e) => {
+                warn!("Restore to {} failed: {}", url, e);
+                failed.push(e.to_string());
+            }
+        }
+    }
+
+    match failed.len() {
+        0 => Err("No restore route is set for this backup".into()),
+        1 => Err(failed.remove(0).into()),
+        _ => Err(format!("all {} restore targets failed: {}", failed.len(), failed.join("; ")).into()),
+    }
+}
+
+/** uploads a restore point the way restore_backup does, to a backup whose restore route was
+swapped for its test_restore_url, then checks test_restore_health_url if one is set */
+fn test_restore(
+    staging: &BackupEntry,
+    filename: &str,
+    archive: Option<ArchiveFormat>,
+    token: &str,
+    http: &HttpSettings,
+) -> Result<String, Box<dyn Error>> {
+    let (target, _) = restore_backup(staging, filename, archive, token, http)?;
+
+    let Some(health_url) = &staging.test_restore_health_url else {
+        return Ok(format!("restored to {}", target));
+    };
+    send_request(health_url, basic_auth(&staging.username, &staging.password), None, None, http)
+        .map_err(|e| format!("restored to {} but the health check at {} failed: {}", target, health_url, e))?;
+
+    Ok(format!("restored to {} and {} is healthy", target, health_url))
+}
+
+/** POSTs the file as multipart form data to one restore target */
+fn upload_restore(
+    backup: &BackupEntry,
+    url: &str,
+    filename: &str,
+    token: &str,
+    http: &HttpSettings,
+) -> Result<(), Box<dyn Error>> {
     let part = multipart::Part::file(filename)?
-                   .mime_str("application/octet-stream")?;
+                   .mime_str(&backup.restore_content_type)?;
     let form = multipart::Form::new()
-                   .part("file", part);
+                   .part(backup.restore_field_name.clone(), part);
 
-    let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(300))
-        .build()?;
+    let client = build_client(http, Duration::from_secs(300))?;
 
     let mut req = client.post(url)
         .multipart(form);
@@ -1497,6 +8161,7 @@ fn restore_backup(url: &str, filename: &str, token: &str) -> Result<(), Box<dyn
         req = req.header(AUTHORIZATION, format!("Bearer {}", token));
     }
 
+    debug!("Uploading {} to {}", filename, url);
     let resp = req.send()?;
     if !resp.status().is_success() {
         return Err(format!(
@@ -0,0 +1,89 @@
+use chrono::{DateTime, Datelike, NaiveTime, Utc, Weekday};
+use serde::Deserialize;
+
+/// A period where failures are still logged but no warnings are sent. Either a one-off
+/// window with `start`/`end` (RFC 3339), or a recurring one with `from`/`to` ("HH:MM", UTC)
+/// on the listed `weekdays` (every day when empty).
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct MaintenanceWindow {
+    pub description: String,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub weekdays: Vec<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl MaintenanceWindow {
+    /** checks the window can be understood, so mistakes show up at startup */
+    pub fn validate(&self) -> Result<(), String> {
+        match (&self.start, &self.end, &self.from, &self.to) {
+            (Some(start), Some(end), None, None) => {
+                let start = parse_datetime(start)?;
+                let end = parse_datetime(end)?;
+                if end <= start {
+                    return Err("end must be after start".to_string());
+                }
+            }
+            (None, None, Some(from), Some(to)) => {
+                parse_clock(from)?;
+                parse_clock(to)?;
+                for weekday in &self.weekdays {
+                    weekday
+                        .parse::<Weekday>()
+                        .map_err(|_| format!("unknown weekday \"{}\"", weekday))?;
+                }
+            }
+            _ => return Err("set either start and end, or from and to".to_string()),
+        }
+
+        Ok(())
+    }
+
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        if let (Some(start), Some(end)) = (&self.start, &self.end) {
+            return match (parse_datetime(start), parse_datetime(end)) {
+                (Ok(start), Ok(end)) => start <= now && now < end,
+                _ => false,
+            };
+        }
+
+        let (from, to) = match (&self.from, &self.to) {
+            (Some(from), Some(to)) => match (parse_clock(from), parse_clock(to)) {
+                (Ok(from), Ok(to)) => (from, to),
+                _ => return false,
+            },
+            _ => return false,
+        };
+
+        let time = now.time();
+        let weekday = now.weekday();
+
+        let day_matches = |day: Weekday| {
+            self.weekdays.is_empty()
+                || self
+                    .weekdays
+                    .iter()
+                    .any(|name| name.parse::<Weekday>().ok() == Some(day))
+        };
+
+        if from <= to {
+            day_matches(weekday) && from <= time && time < to
+        } else {
+            // the window runs past midnight, the part after midnight belongs to the previous day
+            (day_matches(weekday) && time >= from) || (day_matches(weekday.pred()) && time < to)
+        }
+    }
+}
+
+fn parse_datetime(value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|parsed| parsed.with_timezone(&Utc))
+        .map_err(|e| format!("\"{}\" is not an RFC 3339 timestamp: {}", value, e))
+}
+
+fn parse_clock(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .map_err(|_| format!("\"{}\" is not a HH:MM time", value))
+}
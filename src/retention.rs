@@ -0,0 +1,260 @@
+use crate::BackupEntry;
+use chrono::{DateTime, Datelike, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// How many restore points to keep per tier when `retention = "gfs"`.
+/// Each tier keeps the newest backup of each of the last N hours/days/weeks/months.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct GfsTiers {
+    pub hourly: usize,
+    pub daily: usize,
+    pub weekly: usize,
+    pub monthly: usize,
+}
+
+impl Default for GfsTiers {
+    fn default() -> Self {
+        Self {
+            hourly: 24,
+            daily: 7,
+            weekly: 4,
+            monthly: 12,
+        }
+    }
+}
+
+/// Returns the restore points that should be rotated out, oldest first, together with the
+/// rule that selected them. Logs are appended in backup order, so index 0 is the oldest.
+pub fn expired_entries(backup: &BackupEntry, now: DateTime<Utc>) -> Vec<(String, String)> {
+    let mut expired: Vec<(String, String)> = Vec::new();
+
+    if backup.retention.as_deref() == Some("gfs") {
+        let kept = gfs_kept(backup, &backup.gfs);
+
+        for entry in &backup.logs {
+            if !kept.contains(&entry.filename) {
+                expired.push((entry.filename.clone(), "not kept by GFS retention".to_string()));
+            }
+        }
+    } else {
+        let number_over_limit = backup.logs.len().saturating_sub(backup.max as usize);
+
+        for entry in backup.logs.iter().take(number_over_limit) {
+            expired.push((entry.filename.clone(), format!("more than {} backups", backup.max)));
+        }
+
+        if let Some(max_age_days) = backup.max_age_days {
+            let cutoff = now - Duration::days(max_age_days as i64);
+
+            for entry in &backup.logs {
+                let is_too_old = match parse_timestamp(&entry.timestamp) {
+                    Some(timestamp) => timestamp < cutoff,
+                    None => false, // never delete something we can't date
+                };
+
+                if is_too_old && !is_expired(&expired, &entry.filename) {
+                    expired.push((entry.filename.clone(), format!("older than {} days", max_age_days)));
+                }
+            }
+        }
+    }
+
+    if let Some(max_total_bytes) = backup.max_total_bytes {
+        let mut total: u64 = backup
+            .logs
+            .iter()
+            .filter(|entry| !is_expired(&expired, &entry.filename))
+            .map(|entry| entry.size)
+            .sum();
+
+        // oldest first, but the newest backup is always kept even if it alone exceeds the quota
+        let candidates = backup.logs.len().saturating_sub(1);
+
+        for entry in backup.logs.iter().take(candidates) {
+            if total <= max_total_bytes {
+                break;
+            }
+
+            if !is_expired(&expired, &entry.filename) {
+                total -= entry.size;
+                expired.push((
+                    entry.filename.clone(),
+                    format!("over the {} byte quota", max_total_bytes),
+                ));
+            }
+        }
+    }
+
+    // keep the oldest-first order of the log
+    expired.sort_by_key(|(filename, _)| {
+        backup
+            .logs
+            .iter()
+            .position(|entry| entry.filename == *filename)
+    });
+
+    expired
+}
+
+fn is_expired(expired: &[(String, String)], filename: &str) -> bool {
+    expired.iter().any(|(expired_filename, _)| expired_filename == filename)
+}
+
+type BucketKey = fn(&DateTime<Utc>) -> String;
+
+/** filenames kept by the grandfather-father-son tiers */
+fn gfs_kept(backup: &BackupEntry, tiers: &GfsTiers) -> HashSet<String> {
+    let mut kept = HashSet::new();
+
+    let mut dated: Vec<(DateTime<Utc>, &str)> = Vec::new();
+    for entry in &backup.logs {
+        match parse_timestamp(&entry.timestamp) {
+            Some(timestamp) => dated.push((timestamp, &entry.filename)),
+            None => {
+                kept.insert(entry.filename.clone()); // never delete something we can't date
+            }
+        }
+    }
+
+    // newest first, so the first backup seen in a bucket is the one we keep
+    dated.sort_by_key(|(timestamp, _)| std::cmp::Reverse(*timestamp));
+
+    if let Some((_, newest)) = dated.first() {
+        kept.insert(newest.to_string());
+    }
+
+    let bucket_keys: [(usize, BucketKey); 4] = [
+        (tiers.hourly, |t| t.format("%Y-%m-%dT%H").to_string()),
+        (tiers.daily, |t| t.format("%Y-%m-%d").to_string()),
+        (tiers.weekly, |t| {
+            let week = t.iso_week();
+            format!("{}-W{}", week.year(), week.week())
+        }),
+        (tiers.monthly, |t| t.format("%Y-%m").to_string()),
+    ];
+
+    for (count, bucket_key) in bucket_keys {
+        let mut buckets = HashSet::new();
+
+        for (timestamp, filename) in &dated {
+            if buckets.len() >= count {
+                break;
+            }
+            if buckets.insert(bucket_key(timestamp)) {
+                kept.insert(filename.to_string());
+            }
+        }
+    }
+
+    kept
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|parsed| parsed.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LogEntry;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    /// A backup holding one restore point per (filename, timestamp), oldest first like log.toml.
+    fn backup(max: u32, max_age_days: Option<u32>, entries: &[(&str, &str)]) -> BackupEntry {
+        BackupEntry {
+            max,
+            max_age_days,
+            logs: entries
+                .iter()
+                .map(|(filename, timestamp)| LogEntry {
+                    filename: filename.to_string(),
+                    timestamp: timestamp.to_string(),
+                    size: 100,
+                    duration_ms: None,
+                    archive: None,
+                    sha256: None,
+                    server_checksum: None,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn filenames(expired: &[(String, String)]) -> Vec<&str> {
+        expired.iter().map(|(filename, _)| filename.as_str()).collect()
+    }
+
+    #[test]
+    fn entries_older_than_max_age_days_expire() {
+        let backup = backup(
+            100,
+            Some(30),
+            &[
+                ("a.sql", "2024-04-01T12:00:00+00:00"),
+                ("b.sql", "2024-05-02T11:59:59+00:00"),
+                ("c.sql", "2024-05-02T12:00:00+00:00"),
+                ("d.sql", "2024-05-31T12:00:00+00:00"),
+            ],
+        );
+
+        let expired = expired_entries(&backup, now());
+
+        assert_eq!(filenames(&expired), ["a.sql", "b.sql"]);
+        assert!(expired.iter().all(|(_, reason)| reason == "older than 30 days"));
+    }
+
+    #[test]
+    fn age_is_compared_in_utc_whatever_offset_was_logged() {
+        // 13:30 at +02:00 is 11:30 UTC, just before the cutoff of 2024-05-02T12:00 UTC
+        let backup = backup(100, Some(30), &[("a.sql", "2024-05-02T13:30:00+02:00"), ("b.sql", "2024-05-31T08:00:00-04:00")]);
+
+        assert_eq!(filenames(&expired_entries(&backup, now())), ["a.sql"]);
+    }
+
+    #[test]
+    fn count_and_age_both_apply() {
+        let backup = backup(
+            4,
+            Some(30),
+            &[
+                ("a.sql", "2024-03-01T00:00:00+00:00"),
+                ("b.sql", "2024-04-20T00:00:00+00:00"),
+                ("c.sql", "2024-05-20T00:00:00+00:00"),
+                ("d.sql", "2024-05-30T00:00:00+00:00"),
+                ("e.sql", "2024-06-01T00:00:00+00:00"),
+            ],
+        );
+
+        let expired = expired_entries(&backup, now());
+
+        assert_eq!(
+            expired,
+            [
+                ("a.sql".to_string(), "more than 4 backups".to_string()),
+                ("b.sql".to_string(), "older than 30 days".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn undated_entries_are_never_expired_by_age() {
+        let backup = backup(100, Some(1), &[("a.sql", "not a timestamp"), ("b.sql", "2024-01-01T00:00:00+00:00")]);
+
+        assert_eq!(filenames(&expired_entries(&backup, now())), ["b.sql"]);
+    }
+
+    #[test]
+    fn nothing_expires_by_age_without_max_age_days() {
+        let backup = backup(100, None, &[("a.sql", "2000-01-01T00:00:00+00:00")]);
+
+        assert!(expired_entries(&backup, now()).is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use std::env;
+use std::io;
+
+/// A systemd unit that runs this binary with `--headless` from the current folder, so
+/// config.toml, backups and the internal log are found where they are now.
+pub fn systemd_unit() -> io::Result<String> {
+    let exe = env::current_exe()?;
+    let dir = env::current_dir()?;
+
+    Ok(format!(
+        "[Unit]
+Description=WebSync Station uptime monitoring and backups
+Wants=network-online.target
+After=network-online.target
+
+[Service]
+ExecStart=\"{}\" --headless
+WorkingDirectory={}
+Restart=on-failure
+RestartSec=30
+
+[Install]
+WantedBy=multi-user.target
+",
+        exe.display(),
+        dir.display()
+    ))
+}
+
+#[cfg(windows)]
+pub use windows::{enter_exe_dir, install, run, uninstall, SERVICE_NAME};
+
+#[cfg(windows)]
+mod windows {
+    use crate::StatusChecker;
+    use std::env;
+    use std::error::Error;
+    use std::ffi::OsString;
+    use std::sync::atomic::Ordering;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use tracing::error;
+    use windows_service::service::{
+        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+        ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    };
+    use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+    use windows_service::service_dispatcher;
+    use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+    use windows_service::define_windows_service;
+
+    pub const SERVICE_NAME: &str = "WebSyncStation";
+
+    define_windows_service!(ffi_service_main, service_main);
+
+    /// Hands control to the service manager. Only returns when the service has stopped.
+    pub fn run() -> windows_service::Result<()> {
+        service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+    }
+
+    /// Services start in System32, config.toml lives next to the exe.
+    pub fn enter_exe_dir() -> std::io::Result<()> {
+        let exe = env::current_exe()?;
+        match exe.parent() {
+            Some(dir) => env::set_current_dir(dir),
+            None => Ok(()),
+        }
+    }
+
+    /// Registers the current exe as an automatically started service. Needs an elevated prompt.
+    pub fn install() -> Result<(), Box<dyn Error>> {
+        let manager = ServiceManager::local_computer(
+            None::<&str>,
+            ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+        )?;
+
+        let info = ServiceInfo {
+            name: OsString::from(SERVICE_NAME),
+            display_name: OsString::from("WebSync Station"),
+            service_type: ServiceType::OWN_PROCESS,
+            start_type: ServiceStartType::AutoStart,
+            error_control: ServiceErrorControl::Normal,
+            executable_path: env::current_exe()?,
+            launch_arguments: vec![OsString::from("--service")],
+            dependencies: vec![],
+            account_name: None, // LocalSystem
+            account_password: None,
+        };
+
+        let service = manager.create_service(&info, ServiceAccess::CHANGE_CONFIG)?;
+        service.set_description("Uptime monitoring and backups, see config.toml next to the exe")?;
+        Ok(())
+    }
+
+    /// Stops the service if it runs and removes it. Needs an elevated prompt.
+    pub fn uninstall() -> Result<(), Box<dyn Error>> {
+        let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+        let service = manager.open_service(
+            SERVICE_NAME,
+            ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+        )?;
+
+        if service.query_status()?.current_state != ServiceState::Stopped {
+            service.stop()?;
+
+            // running backups are allowed to finish, give them a while
+            let deadline = Instant::now() + Duration::from_secs(60);
+            while service.query_status()?.current_state != ServiceState::Stopped && Instant::now() < deadline {
+                thread::sleep(Duration::from_secs(1));
+            }
+        }
+
+        service.delete()?;
+        Ok(())
+    }
+
+    fn service_main(_arguments: Vec<OsString>) {
+        if let Err(e) = run_service() {
+            error!("Service failed: {}", e);
+        }
+    }
+
+    fn run_service() -> windows_service::Result<()> {
+        let app = StatusChecker::headless();
+        let stop_flag = match &app {
+            Ok(app) => app.shutting_down.clone(),
+            Err(_) => Default::default(),
+        };
+
+        let status_handle = service_control_handler::register(SERVICE_NAME, move |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                stop_flag.store(true, Ordering::SeqCst);
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        })?;
+
+        let status = |state: ServiceState, exit_code: ServiceExitCode| ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted: if state == ServiceState::Running {
+                ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+            } else {
+                ServiceControlAccept::empty()
+            },
+            exit_code,
+            checkpoint: 0,
+            wait_hint: Duration::default(),
+            process_id: None,
+        };
+
+        let mut app = match app {
+            Ok(app) => app,
+            Err(e) => {
+                error!("Failed to load config: {}", e);
+                status_handle.set_service_status(status(ServiceState::Stopped, ServiceExitCode::ServiceSpecific(1)))?;
+                return Ok(());
+            }
+        };
+
+        status_handle.set_service_status(status(ServiceState::Running, ServiceExitCode::Win32(0)))?;
+        app.run_headless();
+        status_handle.set_service_status(status(ServiceState::Stopped, ServiceExitCode::Win32(0)))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fs::{read_to_string, write};
+
+const STATS_FILE: &str = "stats.toml";
+
+/// Uptime history per URL, one bucket per UTC day, stored in `stats.toml`.
+/// URLs are keyed by address so renaming an entry keeps its history.
+#[derive(Default, Deserialize, Serialize)]
+pub struct UptimeStats {
+    #[serde(default)]
+    urls: Vec<UrlStats>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct UrlStats {
+    url: String,
+    days: Vec<DayStats>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct DayStats {
+    date: String, // YYYY-MM-DD, so dates sort and compare as text
+    checks: u32,
+    failures: u32,
+}
+
+impl UptimeStats {
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let content = read_to_string(crate::data_path(STATS_FILE))?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        write(crate::data_path(STATS_FILE), toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, url: &str, is_ok: bool, today: NaiveDate) {
+        let index = match self.urls.iter().position(|stats| stats.url == url) {
+            Some(index) => index,
+            None => {
+                self.urls.push(UrlStats {
+                    url: url.to_string(),
+                    days: Vec::new(),
+                });
+                self.urls.len() - 1
+            }
+        };
+        let days = &mut self.urls[index].days;
+        let date = format_date(today);
+
+        if days.last().map(|day| &day.date) != Some(&date) {
+            days.push(DayStats {
+                date,
+                checks: 0,
+                failures: 0,
+            });
+        }
+
+        if let Some(day) = days.last_mut() {
+            day.checks += 1;
+            if !is_ok {
+                day.failures += 1;
+            }
+        }
+    }
+
+    /** drops days outside the window, and URLs left without any history */
+    pub fn prune(&mut self, today: NaiveDate, window_days: u32) {
+        let first_kept = format_date(today - Duration::days(window_days.saturating_sub(1) as i64));
+
+        for stats in &mut self.urls {
+            stats.days.retain(|day| day.date >= first_kept);
+        }
+        self.urls.retain(|stats| !stats.days.is_empty());
+    }
+
+    /** percentage of successful checks over the kept days, None before the first check */
+    pub fn uptime_percent(&self, url: &str) -> Option<f64> {
+        let stats = self.urls.iter().find(|stats| stats.url == url)?;
+
+        let checks: u32 = stats.days.iter().map(|day| day.checks).sum();
+        let failures: u32 = stats.days.iter().map(|day| day.failures).sum();
+
+        if checks == 0 {
+            return None;
+        }
+
+        Some((checks - failures) as f64 * 100.0 / checks as f64)
+    }
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
@@ -0,0 +1,108 @@
+use serde::Deserialize;
+use std::error::Error;
+use std::fs::read_to_string;
+use std::path::Path;
+
+/// One `description,url` pair from an imported list.
+pub struct ImportedUrl {
+    pub description: String,
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+struct JsonUrl {
+    #[serde(default)]
+    description: String,
+    url: String,
+}
+
+/// Reads the URLs to monitor from a `.json` file (an array of objects with `url` and an
+/// optional `description`) or from CSV (any other extension). An empty description falls
+/// back to the URL.
+pub fn read_url_list(path: &Path) -> Result<Vec<ImportedUrl>, Box<dyn Error>> {
+    let content = read_to_string(path)?;
+    let content = content.trim_start_matches('\u{feff}'); // spreadsheet exports often start with a BOM
+
+    let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let rows = if is_json { parse_json(content)? } else { parse_csv(content)? };
+
+    Ok(rows
+        .into_iter()
+        .map(|(description, url)| {
+            let url = url.trim().to_string();
+            let description = match description.trim() {
+                "" => url.clone(),
+                description => description.to_string(),
+            };
+            ImportedUrl { description, url }
+        })
+        .filter(|row| !row.url.is_empty())
+        .collect())
+}
+
+fn parse_json(content: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let entries: Vec<JsonUrl> = serde_json::from_str(content)?;
+    Ok(entries.into_iter().map(|entry| (entry.description, entry.url)).collect())
+}
+
+/// One `description,url` pair per line, or just a URL. Fields may be quoted ("Shop, EU"),
+/// a first line naming the columns is skipped, and `;` works as the separator when a line
+/// has no comma (as spreadsheets in some locales export).
+fn parse_csv(content: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let mut rows = Vec::new();
+    let mut first = true;
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let separator = if !line.contains(',') && line.contains(';') { ';' } else { ',' };
+        let fields = split_csv_line(line, separator).map_err(|e| format!("line {}: {}", number + 1, e))?;
+
+        let row = match fields.as_slice() {
+            [url] => (String::new(), url.clone()),
+            [description, url, ..] => (description.clone(), url.clone()),
+            [] => continue,
+        };
+
+        let is_header = first && row.1.trim().eq_ignore_ascii_case("url");
+        first = false;
+        if !is_header {
+            rows.push(row);
+        }
+    }
+
+    Ok(rows)
+}
+
+fn split_csv_line(line: &str, separator: char) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"'); // "" inside quotes is a literal quote
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            c if c == separator && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err("a quoted field is not closed".to_string());
+    }
+    fields.push(field);
+
+    Ok(fields)
+}